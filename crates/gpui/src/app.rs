@@ -242,6 +242,7 @@ pub struct AppContext {
     pub(crate) layout_id_buffer: Vec<LayoutId>, // We recycle this memory across layout requests.
     pub(crate) propagate_event: bool,
     pub(crate) prompt_builder: Option<PromptBuilder>,
+    reduced_motion: Option<bool>,
 }
 
 impl AppContext {
@@ -303,6 +304,7 @@ impl AppContext {
                 layout_id_buffer: Default::default(),
                 propagate_event: true,
                 prompt_builder: Some(PromptBuilder::Default),
+                reduced_motion: None,
             }),
         });
 
@@ -649,6 +651,21 @@ impl AppContext {
         self.platform.should_auto_hide_scrollbars()
     }
 
+    /// Returns whether animations should snap straight to their final state, for
+    /// users who are sensitive to motion. Follows the platform's accessibility
+    /// setting unless overridden with [`AppContext::set_reduced_motion`].
+    pub fn should_reduce_motion(&self) -> bool {
+        self.reduced_motion
+            .unwrap_or_else(|| self.platform.should_reduce_motion())
+    }
+
+    /// Overrides the platform's reduced motion setting, or follows it again when
+    /// given `None`, and redraws all windows.
+    pub fn set_reduced_motion(&mut self, reduced_motion: Option<bool>) {
+        self.reduced_motion = reduced_motion;
+        self.refresh();
+    }
+
     /// Restart the application.
     pub fn restart(&self, binary_path: Option<PathBuf>) {
         self.platform.restart(binary_path)
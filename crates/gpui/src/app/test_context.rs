@@ -753,6 +753,13 @@ impl VisualTestContext {
         self.simulate_window_resize(self.window, size)
     }
 
+    /// Returns whether anything asked to be called back on the next frame, e.g. to
+    /// continue an animation. Test windows never request frames, so these callbacks
+    /// are never run.
+    pub fn has_pending_frame_callbacks(&mut self) -> bool {
+        self.update(|cx| !cx.window.next_frame_callbacks.borrow().is_empty())
+    }
+
     /// debug_bounds returns the bounds of the element with the given selector.
     pub fn debug_bounds(&mut self, selector: &'static str) -> Option<Bounds<Pixels>> {
         self.update(|cx| cx.window.rendered_frame.debug_bounds.get(selector).copied())
@@ -16,26 +16,27 @@
 //! constructed by combining these two systems into an all-in-one element.
 
 use crate::{
-    point, px, size, Action, AnyDrag, AnyElement, AnyTooltip, AnyView, AppContext, Bounds,
-    ClickEvent, DispatchPhase, Element, ElementId, FocusHandle, Global, GlobalElementId, Hitbox,
-    HitboxId, IntoElement, IsZero, KeyContext, KeyDownEvent, KeyUpEvent, LayoutId,
+    fill, hsla, point, px, size, Action, Along, AnyDrag, AnyElement, AnyTooltip, AnyView,
+    AppContext, Axis, Bounds, ClickEvent, DefiniteLength, DispatchPhase, DrawPhase, Element,
+    ElementId, EntityId, Fill, FocusHandle, Global, GlobalElementId, Hitbox, HitboxId, Hsla,
+    IntoElement, IsZero, KeyContext, KeyDownEvent, KeyUpEvent, LayoutId, Length,
     ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent,
-    ParentElement, Pixels, Point, Render, ScrollWheelEvent, SharedString, Size, Style,
-    StyleRefinement, Styled, Task, TooltipId, View, Visibility, WindowContext,
+    ParentElement, Pixels, Point, Render, Rgba, ScrollDelta, ScrollWheelEvent, SharedString, Size,
+    Style, StyleRefinement, Styled, Task, TooltipId, TouchPhase, View, Visibility, WindowContext,
 };
 use collections::HashMap;
 use refineable::Refineable;
 use smallvec::SmallVec;
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
+    cell::{Cell, RefCell},
     cmp::Ordering,
-    fmt::Debug,
+    fmt::{self, Debug},
     marker::PhantomData,
     mem,
     ops::DerefMut,
     rc::Rc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use taffy::style::Overflow;
 use util::ResultExt;
@@ -43,6 +44,15 @@ use util::ResultExt;
 const DRAG_THRESHOLD: f64 = 2.;
 pub(crate) const TOOLTIP_DELAY: Duration = Duration::from_millis(500);
 
+/// How quickly kinetic scrolling slows down. After `t` seconds, the velocity of
+/// a flick has decayed by a factor of `e^(-KINETIC_SCROLL_FRICTION * t)`.
+const KINETIC_SCROLL_FRICTION: f32 = 6.;
+/// The speed, in pixels per second, below which kinetic scrolling stops.
+const KINETIC_SCROLL_MIN_VELOCITY: f32 = 20.;
+/// Lifting off after holding still for this long doesn't flick.
+const KINETIC_SCROLL_MAX_PAUSE: Duration = Duration::from_millis(100);
+const KINETIC_SCROLL_FRAME: Duration = Duration::from_millis(16);
+
 /// The styling information for a given group.
 pub struct GroupStyle {
     /// The identifier for this group.
@@ -277,6 +287,14 @@ impl Interactivity {
             }));
     }
 
+    /// Bind the given callback to changes of this element's scroll offset caused by
+    /// scroll wheel events, or by [`ScrollHandle::scroll_to`] on the element's tracked
+    /// scroll handle. The callback receives the new offset.
+    /// The imperative API equivalent to [`InteractiveElement::on_scroll`]
+    pub fn on_scroll(&mut self, listener: impl Fn(Point<Pixels>, &mut WindowContext) + 'static) {
+        self.scroll_listeners.push(Box::new(listener));
+    }
+
     /// Bind the given callback to an action dispatch during the capture phase
     /// The imperative API equivalent to [`InteractiveElement::capture_action`]
     ///
@@ -727,6 +745,15 @@ pub trait InteractiveElement: Sized {
         self
     }
 
+    /// Bind the given callback to changes of this element's scroll offset caused by
+    /// scroll wheel events, or by [`ScrollHandle::scroll_to`] on the element's tracked
+    /// scroll handle. The callback receives the new offset.
+    /// The fluent API equivalent to [`Interactivity::on_scroll`]
+    fn on_scroll(mut self, listener: impl Fn(Point<Pixels>, &mut WindowContext) + 'static) -> Self {
+        self.interactivity().on_scroll(listener);
+        self
+    }
+
     /// Capture the given action, before normal action dispatch can fire
     /// The fluent API equivalent to [`Interactivity::on_scroll_wheel`]
     ///
@@ -915,6 +942,40 @@ pub trait StatefulInteractiveElement: InteractiveElement {
         self
     }
 
+    /// Keep scrolling after a trackpad flick, slowing down until the content comes
+    /// to rest. Touching the trackpad again or reaching the end of the content stops
+    /// it. Only has an effect on elements that scroll. While
+    /// [`WindowContext::should_reduce_motion`] is set, a flick jumps straight to
+    /// where it would have come to rest.
+    fn kinetic_scroll(mut self, enabled: bool) -> Self {
+        self.interactivity().kinetic_scroll = enabled;
+        self
+    }
+
+    /// Animate changes to the given style property. Whenever its computed value
+    /// differs from the one in the previous frame, the element eases from what it
+    /// was showing towards the new value over `duration`, using an easing function
+    /// such as [`ease_in_out`](crate::ease_in_out).
+    ///
+    /// Lengths are interpolated when both ends are in pixels or rems; any other
+    /// change is applied immediately, as are all changes while
+    /// [`WindowContext::should_reduce_motion`] is set.
+    fn transition(
+        mut self,
+        property: StyleProperty,
+        duration: Duration,
+        easing: impl Fn(f32) -> f32 + 'static,
+    ) -> Self {
+        let transitions = &mut self.interactivity().transitions;
+        transitions.retain(|transition| transition.property != property);
+        transitions.push(Transition {
+            property,
+            duration,
+            easing: Box::new(easing),
+        });
+        self
+    }
+
     /// Set the given styles to be applied when this element is active.
     fn active(mut self, f: impl FnOnce(StyleRefinement) -> StyleRefinement) -> Self
     where
@@ -1042,6 +1103,8 @@ pub(crate) type MouseMoveListener =
 pub(crate) type ScrollWheelListener =
     Box<dyn Fn(&ScrollWheelEvent, DispatchPhase, &Hitbox, &mut WindowContext) + 'static>;
 
+pub(crate) type ScrollListener = Box<dyn Fn(Point<Pixels>, &mut WindowContext) + 'static>;
+
 pub(crate) type ClickListener = Box<dyn Fn(&ClickEvent, &mut WindowContext) + 'static>;
 
 pub(crate) type DragListener = Box<dyn Fn(&dyn Any, &mut WindowContext) -> AnyView + 'static>;
@@ -1081,6 +1144,12 @@ pub fn div() -> Div {
     Div {
         interactivity,
         children: SmallVec::default(),
+        sticky_top: None,
+        sticky_left: None,
+        sticky_offset: Point::default(),
+        scrollbars: SmallVec::new(),
+        #[cfg(debug_assertions)]
+        debug_bounds_color: None,
     }
 }
 
@@ -1088,6 +1157,58 @@ pub fn div() -> Div {
 pub struct Div {
     interactivity: Interactivity,
     children: SmallVec<[AnyElement; 2]>,
+    sticky_top: Option<Pixels>,
+    sticky_left: Option<Pixels>,
+    sticky_offset: Point<Pixels>,
+    scrollbars: SmallVec<[Axis; 2]>,
+    #[cfg(debug_assertions)]
+    debug_bounds_color: Option<Hsla>,
+}
+
+impl Div {
+    /// Keep this element pinned `offset` below the top of the enclosing scroll
+    /// container once its content scrolls past it. The element never moves above
+    /// its natural position, and scrolls away with its parent once the parent's
+    /// bottom edge reaches it.
+    ///
+    /// The viewport the element is pinned to is the current content mask, which is
+    /// the intersection of every ancestor that clips its content, whether it
+    /// scrolls (`overflow_y_scroll`) or not (`overflow_hidden`). When an ancestor
+    /// clips part of the scroll container away, the element is pinned to the part
+    /// that's still visible.
+    pub fn sticky_top(mut self, offset: Pixels) -> Self {
+        self.sticky_top = Some(offset);
+        self
+    }
+
+    /// Keep this element pinned `offset` right of the left edge of the enclosing
+    /// scroll container once its content scrolls past it. See [`Div::sticky_top`].
+    pub fn sticky_left(mut self, offset: Pixels) -> Self {
+        self.sticky_left = Some(offset);
+        self
+    }
+
+    /// Draw a scrollbar along the given axis while this element's content overflows it.
+    /// The thumb can be dragged, and clicking the track scrolls by a page. Only has an
+    /// effect on elements that scroll along that axis.
+    pub fn show_scrollbar(mut self, axis: Axis) -> Self {
+        if !self.scrollbars.contains(&axis) {
+            self.scrollbars.push(axis);
+        }
+        self
+    }
+
+    /// Outline this element's bounds with the given color to debug its layout.
+    /// Only painted in debug builds; a translucent color keeps content readable.
+    pub fn debug_bounds(mut self, color: Hsla) -> Self {
+        #[cfg(debug_assertions)]
+        {
+            self.debug_bounds_color = Some(color);
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = color;
+        self
+    }
 }
 
 /// A frame state for a `Div` element, which contains layout IDs for its children.
@@ -1154,6 +1275,25 @@ impl Element for Div {
         request_layout: &mut Self::RequestLayoutState,
         cx: &mut WindowContext,
     ) -> Option<Hitbox> {
+        // The content mask is the visible part of the enclosing scroll container,
+        // clipped by any other ancestors, so a sticky element that scrolled past it
+        // is pushed back in, but no further than the far edge of its containing block.
+        let viewport = cx.content_mask().bounds;
+        let containing_block = cx.containing_block().unwrap_or(viewport);
+        self.sticky_offset = point(
+            sticky_offset(
+                self.sticky_left.map(|left| viewport.left() + left),
+                bounds.left(),
+                containing_block.right() - bounds.right(),
+            ),
+            sticky_offset(
+                self.sticky_top.map(|top| viewport.top() + top),
+                bounds.top(),
+                containing_block.bottom() - bounds.bottom(),
+            ),
+        );
+        let bounds = Bounds::new(bounds.origin + self.sticky_offset, bounds.size);
+
         let mut child_min = point(Pixels::MAX, Pixels::MAX);
         let mut child_max = Point::default();
         let content_size = if request_layout.child_layout_ids.is_empty() {
@@ -1193,10 +1333,12 @@ impl Element for Div {
             content_size,
             cx,
             |_style, scroll_offset, hitbox, cx| {
-                cx.with_element_offset(scroll_offset, |cx| {
-                    for child in &mut self.children {
-                        child.prepaint(cx);
-                    }
+                cx.with_element_offset(scroll_offset + self.sticky_offset, |cx| {
+                    cx.with_containing_block(bounds, |cx| {
+                        for child in &mut self.children {
+                            child.prepaint(cx);
+                        }
+                    })
                 });
                 hitbox
             },
@@ -1211,15 +1353,203 @@ impl Element for Div {
         hitbox: &mut Option<Hitbox>,
         cx: &mut WindowContext,
     ) {
+        let bounds = Bounds::new(bounds.origin + self.sticky_offset, bounds.size);
+        let mut scrollbar_style = None;
         self.interactivity
-            .paint(global_id, bounds, hitbox.as_ref(), cx, |_style, cx| {
+            .paint(global_id, bounds, hitbox.as_ref(), cx, |style, cx| {
                 for child in &mut self.children {
                     child.paint(cx);
                 }
+                if !self.scrollbars.is_empty() {
+                    scrollbar_style = Some(style.clone());
+                }
+            });
+
+        if let (Some(global_id), Some(hitbox), Some(style)) =
+            (global_id, hitbox.as_ref(), scrollbar_style)
+        {
+            let scroll_max = self.interactivity.scroll_max(bounds, &style, cx.rem_size());
+            self.paint_scrollbars(global_id, hitbox, scroll_max, cx);
+        }
+
+        #[cfg(debug_assertions)]
+        if let Some(color) = self.debug_bounds_color {
+            cx.paint_quad(crate::outline(bounds, color));
+        }
+    }
+}
+
+impl Div {
+    fn paint_scrollbars(
+        &self,
+        global_id: &GlobalElementId,
+        hitbox: &Hitbox,
+        scroll_max: Size<Pixels>,
+        cx: &mut WindowContext,
+    ) {
+        let Some(scroll_offset) = self.interactivity.scroll_offset.clone() else {
+            return;
+        };
+        let drag = cx.with_element_state(
+            global_id,
+            |drag: Option<Rc<Cell<Option<ScrollbarDrag>>>>, _| {
+                let drag = drag.unwrap_or_default();
+                (drag.clone(), drag)
+            },
+        );
+        let bounds = hitbox.bounds;
+        let current_offset = *scroll_offset.borrow();
+
+        for &axis in &self.scrollbars {
+            let Some(thumb) = scrollbar_thumb_bounds(bounds, axis, scroll_max, current_offset)
+            else {
+                continue;
+            };
+            let track = scrollbar_track_bounds(bounds, axis);
+            let thumb_color = if drag.get().map_or(false, |drag| drag.axis == axis) {
+                hsla(0., 0., 0.5, 0.8)
+            } else {
+                hsla(0., 0., 0.5, 0.5)
+            };
+            cx.paint_quad(fill(thumb, thumb_color).corner_radii(SCROLLBAR_WIDTH / 2.));
+
+            let scroll_max = scroll_max.along(axis);
+            cx.on_mouse_event({
+                let drag = drag.clone();
+                let scroll_offset = scroll_offset.clone();
+                let hitbox = hitbox.clone();
+                move |event: &MouseDownEvent, phase, cx| {
+                    if phase != DispatchPhase::Bubble
+                        || event.button != MouseButton::Left
+                        || !hitbox.is_hovered(cx)
+                        || !track.contains(&event.position)
+                    {
+                        return;
+                    }
+
+                    let mut offset = scroll_offset.borrow_mut();
+                    if thumb.contains(&event.position) {
+                        drag.set(Some(ScrollbarDrag {
+                            axis,
+                            start_position: event.position.along(axis),
+                            start_offset: offset.along(axis),
+                        }));
+                    } else {
+                        // Page towards the click.
+                        let page = bounds.size.along(axis);
+                        let delta = if event.position.along(axis) < thumb.origin.along(axis) {
+                            page
+                        } else {
+                            -page
+                        };
+                        *offset = offset.apply_along(axis, |offset| {
+                            (offset + delta).clamp(-scroll_max, px(0.))
+                        });
+                    }
+                    drop(offset);
+
+                    cx.stop_propagation();
+                    cx.refresh();
+                }
+            });
+
+            cx.on_mouse_event({
+                let drag = drag.clone();
+                let scroll_offset = scroll_offset.clone();
+                let thumb_travel = track.size.along(axis) - thumb.size.along(axis);
+                move |event: &MouseMoveEvent, phase, cx| {
+                    let Some(drag) = drag.get().filter(|drag| drag.axis == axis) else {
+                        return;
+                    };
+                    if phase != DispatchPhase::Bubble || !event.dragging() || thumb_travel <= px(0.)
+                    {
+                        return;
+                    }
+
+                    let delta = event.position.along(axis) - drag.start_position;
+                    let new_offset = (drag.start_offset - delta * (scroll_max / thumb_travel))
+                        .clamp(-scroll_max, px(0.));
+                    let mut offset = scroll_offset.borrow_mut();
+                    if offset.along(axis) != new_offset {
+                        *offset = offset.apply_along(axis, |_| new_offset);
+                        drop(offset);
+                        cx.refresh();
+                    }
+                    cx.stop_propagation();
+                }
             });
+        }
+
+        cx.on_mouse_event(move |_: &MouseUpEvent, phase, cx| {
+            if phase == DispatchPhase::Capture && drag.take().is_some() {
+                cx.refresh();
+            }
+        });
+    }
+}
+
+const SCROLLBAR_WIDTH: Pixels = px(8.);
+const SCROLLBAR_MIN_THUMB_LENGTH: Pixels = px(16.);
+
+#[derive(Clone, Copy)]
+struct ScrollbarDrag {
+    axis: Axis,
+    start_position: Pixels,
+    start_offset: Pixels,
+}
+
+/// Returns how far a sticky element at `start` moves to stay at or past `pinned`,
+/// limited to the `room` left before it'd leave its containing block.
+fn sticky_offset(pinned: Option<Pixels>, start: Pixels, room: Pixels) -> Pixels {
+    match pinned {
+        Some(pinned) => (pinned - start).min(room).max(px(0.)),
+        None => px(0.),
     }
 }
 
+/// The track of a scrollbar along `axis`, which runs along the trailing edge of `bounds`.
+fn scrollbar_track_bounds(bounds: Bounds<Pixels>, axis: Axis) -> Bounds<Pixels> {
+    match axis {
+        Axis::Vertical => Bounds::new(
+            point(bounds.right() - SCROLLBAR_WIDTH, bounds.top()),
+            size(SCROLLBAR_WIDTH, bounds.size.height),
+        ),
+        Axis::Horizontal => Bounds::new(
+            point(bounds.left(), bounds.bottom() - SCROLLBAR_WIDTH),
+            size(bounds.size.width, SCROLLBAR_WIDTH),
+        ),
+    }
+}
+
+/// The thumb of a scrollbar along `axis`, sized by the visible fraction of the content
+/// and positioned by the scroll offset. `None` when the content fits.
+fn scrollbar_thumb_bounds(
+    bounds: Bounds<Pixels>,
+    axis: Axis,
+    scroll_max: Size<Pixels>,
+    scroll_offset: Point<Pixels>,
+) -> Option<Bounds<Pixels>> {
+    let scroll_max = scroll_max.along(axis);
+    if scroll_max <= px(0.) {
+        return None;
+    }
+
+    let track = scrollbar_track_bounds(bounds, axis);
+    let track_length = track.size.along(axis);
+    let viewport_length = bounds.size.along(axis);
+    let thumb_length = (track_length * (viewport_length / (viewport_length + scroll_max)))
+        .max(SCROLLBAR_MIN_THUMB_LENGTH)
+        .min(track_length);
+    let progress = (-scroll_offset.along(axis) / scroll_max).clamp(0., 1.);
+    let thumb_start = (track_length - thumb_length) * progress;
+    Some(Bounds::new(
+        track
+            .origin
+            .apply_along(axis, |origin| origin + thumb_start),
+        track.size.apply_along(axis, |_| thumb_length),
+    ))
+}
+
 impl IntoElement for Div {
     type Element = Self;
 
@@ -1265,6 +1595,7 @@ pub struct Interactivity {
     pub(crate) mouse_up_listeners: Vec<MouseUpListener>,
     pub(crate) mouse_move_listeners: Vec<MouseMoveListener>,
     pub(crate) scroll_wheel_listeners: Vec<ScrollWheelListener>,
+    pub(crate) scroll_listeners: Vec<ScrollListener>,
     pub(crate) key_down_listeners: Vec<KeyDownListener>,
     pub(crate) key_up_listeners: Vec<KeyUpListener>,
     pub(crate) modifiers_changed_listeners: Vec<ModifiersChangedListener>,
@@ -1276,6 +1607,8 @@ pub struct Interactivity {
     pub(crate) hover_listener: Option<Box<dyn Fn(&bool, &mut WindowContext)>>,
     pub(crate) tooltip_builder: Option<TooltipBuilder>,
     pub(crate) occlude_mouse: bool,
+    pub(crate) transitions: Vec<Transition>,
+    pub(crate) kinetic_scroll: bool,
 
     #[cfg(debug_assertions)]
     pub(crate) location: Option<core::panic::Location<'static>>,
@@ -1423,30 +1756,13 @@ impl Interactivity {
         cx: &mut WindowContext,
     ) -> Point<Pixels> {
         if let Some(scroll_offset) = self.scroll_offset.as_ref() {
+            let scroll_max = self.scroll_max(bounds, style, cx.rem_size());
             if let Some(scroll_handle) = &self.tracked_scroll_handle {
-                scroll_handle.0.borrow_mut().overflow = style.overflow;
+                let mut scroll_handle = scroll_handle.0.borrow_mut();
+                scroll_handle.overflow = style.overflow;
+                scroll_handle.scroll_max = scroll_max;
             }
 
-            let rem_size = cx.rem_size();
-            let padding_size = size(
-                style
-                    .padding
-                    .left
-                    .to_pixels(bounds.size.width.into(), rem_size)
-                    + style
-                        .padding
-                        .right
-                        .to_pixels(bounds.size.width.into(), rem_size),
-                style
-                    .padding
-                    .top
-                    .to_pixels(bounds.size.height.into(), rem_size)
-                    + style
-                        .padding
-                        .bottom
-                        .to_pixels(bounds.size.height.into(), rem_size),
-            );
-            let scroll_max = (self.content_size + padding_size - bounds.size).max(&Size::default());
             // Clamp scroll offset in case scroll max is smaller now (e.g., if children
             // were removed or the bounds became larger).
             let mut scroll_offset = scroll_offset.borrow_mut();
@@ -1458,6 +1774,30 @@ impl Interactivity {
         }
     }
 
+    /// How far the content can be scrolled along each axis, which is zero when it
+    /// fits within the bounds.
+    fn scroll_max(&self, bounds: Bounds<Pixels>, style: &Style, rem_size: Pixels) -> Size<Pixels> {
+        let padding_size = size(
+            style
+                .padding
+                .left
+                .to_pixels(bounds.size.width.into(), rem_size)
+                + style
+                    .padding
+                    .right
+                    .to_pixels(bounds.size.width.into(), rem_size),
+            style
+                .padding
+                .top
+                .to_pixels(bounds.size.height.into(), rem_size)
+                + style
+                    .padding
+                    .bottom
+                    .to_pixels(bounds.size.height.into(), rem_size),
+        );
+        (self.content_size + padding_size - bounds.size).max(&Size::default())
+    }
+
     /// Paint this element according to this interactivity state's configured styles
     /// and bind the element's mouse and keyboard events.
     ///
@@ -1515,7 +1855,12 @@ impl Interactivity {
                                 }
 
                                 self.paint_mouse_listeners(hitbox, element_state.as_mut(), cx);
-                                self.paint_scroll_listener(hitbox, &style, cx);
+                                self.paint_scroll_listener(
+                                    hitbox,
+                                    &style,
+                                    element_state.as_mut(),
+                                    cx,
+                                );
                             }
 
                             self.paint_keyboard_listeners(cx);
@@ -1559,6 +1904,7 @@ impl Interactivity {
                         FONT_SIZE,
                         &[cx.text_style().to_run(str_len)],
                         None,
+                        Pixels::ZERO,
                     )
                     .ok()
                     .and_then(|mut text| text.pop())
@@ -2042,10 +2388,33 @@ impl Interactivity {
         }
     }
 
-    fn paint_scroll_listener(&self, hitbox: &Hitbox, style: &Style, cx: &mut WindowContext) {
+    fn paint_scroll_listener(
+        &mut self,
+        hitbox: &Hitbox,
+        style: &Style,
+        element_state: Option<&mut InteractiveElementState>,
+        cx: &mut WindowContext,
+    ) {
         if let Some(scroll_offset) = self.scroll_offset.clone() {
             let overflow = style.overflow;
             let line_height = cx.line_height();
+            let scroll_max = self.scroll_max(hitbox.bounds, style, cx.rem_size());
+            let scroll_listeners = Rc::new(mem::take(&mut self.scroll_listeners));
+            if let Some(scroll_handle) = self.tracked_scroll_handle.as_ref() {
+                scroll_handle.0.borrow_mut().scroll_listeners = scroll_listeners.clone();
+            }
+            let kinetic_scroll =
+                element_state
+                    .filter(|_| self.kinetic_scroll)
+                    .map(|element_state| {
+                        element_state
+                            .kinetic_scroll
+                            .get_or_insert_with(Default::default)
+                            .clone()
+                    });
+            let kinetic_scroll_offset = scroll_offset.clone();
+            let reduce_motion = cx.should_reduce_motion();
+            let parent_view_id = cx.parent_view_id();
             let hitbox = hitbox.clone();
             cx.on_mouse_event(move |event: &ScrollWheelEvent, phase, cx| {
                 if phase == DispatchPhase::Bubble && hitbox.is_hovered(cx) {
@@ -2075,8 +2444,67 @@ impl Interactivity {
                         scroll_offset.y += delta_y;
                     }
 
+                    scroll_offset.x = scroll_offset.x.clamp(-scroll_max.width, px(0.));
+                    scroll_offset.y = scroll_offset.y.clamp(-scroll_max.height, px(0.));
+                    let mut new_scroll_offset = *scroll_offset;
+                    drop(scroll_offset);
+
+                    if let Some(kinetic_scroll) = kinetic_scroll.as_ref() {
+                        let mut kinetic_scroll = kinetic_scroll.borrow_mut();
+                        let now = cx.background_executor().now();
+                        // Any new input takes over from a flick that's still running.
+                        kinetic_scroll.task = None;
+                        match event.touch_phase {
+                            TouchPhase::Started => kinetic_scroll.velocity = Point::default(),
+                            TouchPhase::Moved => {
+                                let elapsed = kinetic_scroll
+                                    .last_event
+                                    .map(|last_event| now - last_event)
+                                    .unwrap_or_default()
+                                    .as_secs_f32();
+                                if !matches!(event.delta, ScrollDelta::Pixels(_)) {
+                                    kinetic_scroll.velocity = Point::default();
+                                } else if elapsed > 0. {
+                                    let delta = new_scroll_offset - old_scroll_offset;
+                                    kinetic_scroll.velocity =
+                                        point(delta.x.0 / elapsed, delta.y.0 / elapsed);
+                                }
+                            }
+                            TouchPhase::Ended => {
+                                let paused = kinetic_scroll.last_event.map_or(true, |last_event| {
+                                    now - last_event > KINETIC_SCROLL_MAX_PAUSE
+                                });
+                                let mut velocity = mem::take(&mut kinetic_scroll.velocity);
+                                if !paused && !KineticScrollState::is_settled(velocity) {
+                                    if reduce_motion {
+                                        // Skip straight to where the flick would come to rest.
+                                        new_scroll_offset = KineticScrollState::advance(
+                                            &mut velocity,
+                                            0.,
+                                            &kinetic_scroll_offset,
+                                            scroll_max,
+                                        );
+                                    } else {
+                                        kinetic_scroll.task = Some(Self::spawn_kinetic_scroll(
+                                            velocity,
+                                            kinetic_scroll_offset.clone(),
+                                            scroll_max,
+                                            scroll_listeners.clone(),
+                                            parent_view_id,
+                                            cx,
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        kinetic_scroll.last_event = Some(now);
+                    }
+
                     cx.stop_propagation();
-                    if *scroll_offset != old_scroll_offset {
+                    if new_scroll_offset != old_scroll_offset {
+                        for listener in scroll_listeners.iter() {
+                            listener(new_scroll_offset, cx);
+                        }
                         cx.refresh();
                     }
                 }
@@ -2084,6 +2512,51 @@ impl Interactivity {
         }
     }
 
+    /// Keep moving the scroll offset at the given velocity, in pixels per second,
+    /// decaying it with [`KINETIC_SCROLL_FRICTION`] until it settles or the offset
+    /// reaches the end of the content along every axis that's still moving.
+    fn spawn_kinetic_scroll(
+        mut velocity: Point<f32>,
+        scroll_offset: Rc<RefCell<Point<Pixels>>>,
+        scroll_max: Size<Pixels>,
+        scroll_listeners: Rc<Vec<ScrollListener>>,
+        parent_view_id: Option<EntityId>,
+        cx: &mut WindowContext,
+    ) -> Task<()> {
+        cx.spawn(|mut cx| async move {
+            let mut last_frame = cx.background_executor().now();
+            loop {
+                cx.background_executor().timer(KINETIC_SCROLL_FRAME).await;
+                let now = cx.background_executor().now();
+                let elapsed = (now - last_frame).as_secs_f32();
+                last_frame = now;
+
+                let decay = (-KINETIC_SCROLL_FRICTION * elapsed).exp();
+                let old_scroll_offset = *scroll_offset.borrow();
+                let new_scroll_offset =
+                    KineticScrollState::advance(&mut velocity, decay, &scroll_offset, scroll_max);
+                if new_scroll_offset != old_scroll_offset {
+                    let updated = cx.update(|cx| {
+                        for listener in scroll_listeners.iter() {
+                            listener(new_scroll_offset, cx);
+                        }
+                        if let Some(parent_view_id) = parent_view_id {
+                            cx.notify(parent_view_id);
+                        } else {
+                            cx.refresh();
+                        }
+                    });
+                    if updated.is_err() {
+                        break;
+                    }
+                }
+                if KineticScrollState::is_settled(velocity) {
+                    break;
+                }
+            }
+        })
+    }
+
     /// Compute the visual style for this element, based on the current bounds and the element's state.
     pub fn compute_style(
         &self,
@@ -2188,10 +2661,196 @@ impl Interactivity {
                     style.refine(active_style)
                 }
             }
+
+            self.apply_transitions(&mut style, &mut element_state.transitions, cx);
         }
 
         style
     }
+
+    /// Replace the transitioned properties of `style` with their current values.
+    ///
+    /// New targets are only picked up during paint, once hover and drag styles are
+    /// known. Earlier phases of the frame keep showing the transition in progress,
+    /// so a change takes effect in the layout of the following frame.
+    fn apply_transitions(
+        &self,
+        style: &mut Style,
+        states: &mut HashMap<StyleProperty, TransitionState>,
+        cx: &mut WindowContext,
+    ) {
+        if self.transitions.is_empty() {
+            return;
+        }
+        // Snap straight to the target, so later changes start from there.
+        if cx.should_reduce_motion() {
+            states.clear();
+            return;
+        }
+
+        let now = cx.background_executor().now();
+        let rem_size = cx.rem_size();
+        let is_painting = cx.window.draw_phase == DrawPhase::Paint;
+        let mut running = false;
+        for transition in &self.transitions {
+            let target = TransitionValue::get(style, transition.property);
+            let state = states
+                .entry(transition.property)
+                .or_insert_with(|| TransitionState {
+                    from: target.clone(),
+                    to: target.clone(),
+                    start: now,
+                    duration: transition.duration,
+                });
+
+            // Start from whatever was on screen, so that interrupting a
+            // transition doesn't make the element jump.
+            if is_painting && state.to != target {
+                let current = state.value_at(now, transition.easing.as_ref(), rem_size);
+                *state = TransitionState {
+                    from: current,
+                    to: target,
+                    start: now,
+                    duration: transition.duration,
+                };
+            }
+
+            running |= state.is_running(now);
+            state
+                .value_at(now, transition.easing.as_ref(), rem_size)
+                .set(style, transition.property);
+        }
+
+        if is_painting && running {
+            let parent_id = cx.parent_view_id();
+            cx.on_next_frame(move |cx| {
+                if let Some(parent_id) = parent_id {
+                    cx.notify(parent_id)
+                } else {
+                    cx.refresh()
+                }
+            });
+        }
+    }
+}
+
+/// A style property that can be animated with [`Div::transition`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StyleProperty {
+    /// The width of the element.
+    Width,
+    /// The height of the element.
+    Height,
+    /// The background color of the element.
+    Background,
+}
+
+pub(crate) struct Transition {
+    property: StyleProperty,
+    duration: Duration,
+    easing: Box<dyn Fn(f32) -> f32>,
+}
+
+#[derive(Clone, PartialEq)]
+enum TransitionValue {
+    Length(Length),
+    Background(Option<Fill>),
+}
+
+impl TransitionValue {
+    fn get(style: &Style, property: StyleProperty) -> Self {
+        match property {
+            StyleProperty::Width => Self::Length(style.size.width),
+            StyleProperty::Height => Self::Length(style.size.height),
+            StyleProperty::Background => Self::Background(style.background.clone()),
+        }
+    }
+
+    fn set(self, style: &mut Style, property: StyleProperty) {
+        match (property, self) {
+            (StyleProperty::Width, Self::Length(width)) => style.size.width = width,
+            (StyleProperty::Height, Self::Length(height)) => style.size.height = height,
+            (StyleProperty::Background, Self::Background(background)) => {
+                style.background = background
+            }
+            _ => {}
+        }
+    }
+
+    fn interpolate(&self, to: &Self, delta: f32, rem_size: Pixels) -> Self {
+        match (self, to) {
+            (Self::Length(from), Self::Length(to)) => {
+                match (
+                    absolute_pixels(*from, rem_size),
+                    absolute_pixels(*to, rem_size),
+                ) {
+                    (Some(from), Some(to)) => {
+                        Self::Length(px(from.0 + (to.0 - from.0) * delta).into())
+                    }
+                    _ => Self::Length(*to),
+                }
+            }
+            (Self::Background(from), Self::Background(to)) => {
+                let from_color = from.as_ref().and_then(Fill::color);
+                let to_color = to.as_ref().and_then(Fill::color);
+                // Fade in from, or out to, a transparent version of the other color.
+                let (from_color, to_color) = match (from_color, to_color) {
+                    (Some(from), Some(to)) => (from, to),
+                    (Some(from), None) => (from, Hsla { a: 0., ..from }),
+                    (None, Some(to)) => (Hsla { a: 0., ..to }, to),
+                    (None, None) => return Self::Background(to.clone()),
+                };
+                let from = from_color.to_rgb();
+                let to = to_color.to_rgb();
+                let lerp = |from: f32, to: f32| from + (to - from) * delta;
+                Self::Background(Some(Fill::Color(
+                    Rgba {
+                        r: lerp(from.r, to.r),
+                        g: lerp(from.g, to.g),
+                        b: lerp(from.b, to.b),
+                        a: lerp(from.a, to.a),
+                    }
+                    .into(),
+                )))
+            }
+            _ => to.clone(),
+        }
+    }
+}
+
+fn absolute_pixels(length: Length, rem_size: Pixels) -> Option<Pixels> {
+    match length {
+        Length::Definite(DefiniteLength::Absolute(length)) => Some(length.to_pixels(rem_size)),
+        _ => None,
+    }
+}
+
+/// The progress of a single property's transition, kept in the element state.
+pub(crate) struct TransitionState {
+    from: TransitionValue,
+    to: TransitionValue,
+    start: Instant,
+    duration: Duration,
+}
+
+impl TransitionState {
+    fn is_running(&self, now: Instant) -> bool {
+        now < self.start + self.duration
+    }
+
+    fn value_at(
+        &self,
+        now: Instant,
+        easing: &dyn Fn(f32) -> f32,
+        rem_size: Pixels,
+    ) -> TransitionValue {
+        if !self.is_running(now) {
+            return self.to.clone();
+        }
+        let delta =
+            now.saturating_duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32();
+        self.from.interpolate(&self.to, easing(delta), rem_size)
+    }
 }
 
 /// The per-frame state of an interactive element. Used for tracking stateful interactions like clicks
@@ -2204,6 +2863,53 @@ pub struct InteractiveElementState {
     pub(crate) pending_mouse_down: Option<Rc<RefCell<Option<MouseDownEvent>>>>,
     pub(crate) scroll_offset: Option<Rc<RefCell<Point<Pixels>>>>,
     pub(crate) active_tooltip: Option<Rc<RefCell<Option<ActiveTooltip>>>>,
+    pub(crate) transitions: HashMap<StyleProperty, TransitionState>,
+    pub(crate) kinetic_scroll: Option<Rc<RefCell<KineticScrollState>>>,
+}
+
+/// Tracks trackpad scrolling on an element with [`Div::kinetic_scroll`], to carry
+/// on with the flick's velocity once the gesture ends.
+#[derive(Default)]
+pub(crate) struct KineticScrollState {
+    /// The velocity of the gesture in progress, in pixels per second.
+    velocity: Point<f32>,
+    last_event: Option<Instant>,
+    task: Option<Task<()>>,
+}
+
+impl KineticScrollState {
+    fn is_settled(velocity: Point<f32>) -> bool {
+        velocity.x.abs() < KINETIC_SCROLL_MIN_VELOCITY
+            && velocity.y.abs() < KINETIC_SCROLL_MIN_VELOCITY
+    }
+
+    /// Moves the scroll offset as far as a flick goes while its velocity decays by
+    /// the given factor, stopping it along any axis that reaches the end of the
+    /// content. Returns the new offset.
+    fn advance(
+        velocity: &mut Point<f32>,
+        decay: f32,
+        scroll_offset: &RefCell<Point<Pixels>>,
+        scroll_max: Size<Pixels>,
+    ) -> Point<Pixels> {
+        // Integrate the exponential decay so that the distance covered doesn't
+        // depend on how often the offset is advanced.
+        let distance = (1. - decay) / KINETIC_SCROLL_FRICTION;
+        let mut scroll_offset = scroll_offset.borrow_mut();
+        let target = *scroll_offset + point(px(velocity.x * distance), px(velocity.y * distance));
+        *scroll_offset = point(
+            target.x.clamp(-scroll_max.width, px(0.)),
+            target.y.clamp(-scroll_max.height, px(0.)),
+        );
+        if scroll_offset.x != target.x {
+            velocity.x = 0.;
+        }
+        if scroll_offset.y != target.y {
+            velocity.y = 0.;
+        }
+        *velocity = *velocity * decay;
+        *scroll_offset
+    }
 }
 
 /// The current active tooltip
@@ -2437,13 +3143,50 @@ where
     }
 }
 
-#[derive(Default, Debug)]
+impl Stateful<Div> {
+    /// Pin this element below the top of its viewport. See [`Div::sticky_top`].
+    pub fn sticky_top(mut self, offset: Pixels) -> Self {
+        self.element = self.element.sticky_top(offset);
+        self
+    }
+
+    /// Pin this element right of the left edge of its viewport. See [`Div::sticky_left`].
+    pub fn sticky_left(mut self, offset: Pixels) -> Self {
+        self.element = self.element.sticky_left(offset);
+        self
+    }
+
+    /// Draw a scrollbar along the given axis. See [`Div::show_scrollbar`].
+    pub fn show_scrollbar(mut self, axis: Axis) -> Self {
+        self.element = self.element.show_scrollbar(axis);
+        self
+    }
+}
+
+#[derive(Default)]
 struct ScrollHandleState {
     offset: Rc<RefCell<Point<Pixels>>>,
     bounds: Bounds<Pixels>,
     child_bounds: Vec<Bounds<Pixels>>,
     requested_scroll_top: Option<(usize, Pixels)>,
     overflow: Point<Overflow>,
+    scroll_max: Size<Pixels>,
+    /// The `on_scroll` listeners of the element as of the last frame, which are
+    /// notified of changes made with [`ScrollHandle::scroll_to`].
+    scroll_listeners: Rc<Vec<ScrollListener>>,
+}
+
+impl Debug for ScrollHandleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrollHandleState")
+            .field("offset", &self.offset)
+            .field("bounds", &self.bounds)
+            .field("child_bounds", &self.child_bounds)
+            .field("requested_scroll_top", &self.requested_scroll_top)
+            .field("overflow", &self.overflow)
+            .field("scroll_max", &self.scroll_max)
+            .finish_non_exhaustive()
+    }
 }
 
 /// A handle to the scrollable aspects of an element.
@@ -2534,6 +3277,28 @@ impl ScrollHandle {
         *state.offset.borrow_mut() = position;
     }
 
+    /// Scroll to the given offset, clamped to the content as of the last frame.
+    /// Like [`ScrollHandle::offset`], the offset becomes more negative as you scroll
+    /// further down. Along an axis where the content fits within the element, the
+    /// offset is pinned to zero. The element's `on_scroll` listeners are notified
+    /// if the offset changes.
+    pub fn scroll_to(&self, offset: Point<Pixels>, cx: &mut WindowContext) {
+        let state = self.0.borrow();
+        let new_offset = point(
+            offset.x.clamp(-state.scroll_max.width, px(0.)),
+            offset.y.clamp(-state.scroll_max.height, px(0.)),
+        );
+        if mem::replace(&mut *state.offset.borrow_mut(), new_offset) == new_offset {
+            return;
+        }
+        let scroll_listeners = state.scroll_listeners.clone();
+        drop(state);
+        for listener in scroll_listeners.iter() {
+            listener(new_offset, cx);
+        }
+        cx.refresh();
+    }
+
     /// Get the logical scroll top, based on a child index and a pixel offset.
     pub fn logical_scroll_top(&self) -> (usize, Pixels) {
         let ix = self.top_item();
@@ -2554,3 +3319,496 @@ impl ScrollHandle {
         self.0.borrow_mut().requested_scroll_top = Some((ix, px));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        self as gpui, canvas, img, linear, red, ImageData, ImageSource, Modifiers, TestAppContext,
+        ViewContext, VisualTestContext,
+    };
+
+    #[test]
+    fn test_scrollbar_thumb_bounds() {
+        let bounds = Bounds::new(point(px(0.), px(0.)), size(px(100.), px(200.)));
+        let scroll_max = size(px(0.), px(800.));
+        let halfway = point(px(0.), px(-400.));
+
+        assert_eq!(
+            scrollbar_thumb_bounds(bounds, Axis::Vertical, scroll_max, halfway),
+            Some(Bounds::new(point(px(92.), px(80.)), size(px(8.), px(40.))))
+        );
+        assert_eq!(
+            scrollbar_thumb_bounds(bounds, Axis::Horizontal, scroll_max, halfway),
+            None
+        );
+    }
+
+    #[gpui::test]
+    fn test_show_scrollbar(cx: &mut TestAppContext) {
+        let cx = cx.add_empty_window();
+        cx.draw(point(px(0.), px(0.)), size(px(100.), px(100.)), |_| {
+            div()
+                .id("container")
+                .size(px(100.))
+                .overflow_y_scroll()
+                .show_scrollbar(Axis::Vertical)
+                .child(div().h(px(400.)))
+        });
+
+        let thumb = scrollbar_thumb_bounds(
+            Bounds::new(point(px(0.), px(0.)), size(px(100.), px(100.))),
+            Axis::Vertical,
+            size(px(0.), px(300.)),
+            Point::default(),
+        )
+        .unwrap();
+        cx.update(|cx| {
+            let thumb = thumb.scale(cx.scale_factor());
+            let quads = &cx.window.next_frame.scene.quads;
+            assert!(quads.iter().any(|quad| quad.bounds == thumb));
+        });
+    }
+
+    #[gpui::test]
+    fn test_sticky_top(cx: &mut TestAppContext) {
+        let cx = cx.add_empty_window();
+        let scroll_handle = ScrollHandle::new();
+        let painted_top = Rc::new(Cell::new(None));
+
+        let mut draw = |scroll_top: Pixels| {
+            scroll_handle.set_offset(point(px(0.), -scroll_top));
+            cx.draw(point(px(0.), px(0.)), size(px(100.), px(100.)), |_| {
+                let painted_top = painted_top.clone();
+                div()
+                    .id("container")
+                    .size(px(100.))
+                    .overflow_y_scroll()
+                    .track_scroll(&scroll_handle)
+                    .child(
+                        div()
+                            .child(
+                                div().sticky_top(px(0.)).h(px(10.)).child(
+                                    canvas(
+                                        |_, _| {},
+                                        move |bounds, _, _| painted_top.set(Some(bounds.top())),
+                                    )
+                                    .size_full(),
+                                ),
+                            )
+                            .child(div().h(px(50.))),
+                    )
+                    .child(div().h(px(200.)))
+            });
+            painted_top.get().unwrap()
+        };
+
+        // Unscrolled, the element sits at its natural position.
+        assert_eq!(draw(px(0.)), px(0.));
+        // Once its natural position scrolls above the viewport, it's pinned to the top.
+        assert_eq!(draw(px(30.)), px(0.));
+        // It scrolls away with its parent once the parent's bottom edge reaches it.
+        assert_eq!(draw(px(55.)), px(-5.));
+    }
+
+    #[gpui::test]
+    fn test_sticky_top_offset(cx: &mut TestAppContext) {
+        let cx = cx.add_empty_window();
+        let scroll_handle = ScrollHandle::new();
+        let painted_top = Rc::new(Cell::new(None));
+
+        let mut draw = |scroll_top: Pixels| {
+            scroll_handle.set_offset(point(px(0.), -scroll_top));
+            cx.draw(point(px(0.), px(0.)), size(px(100.), px(100.)), |_| {
+                let painted_top = painted_top.clone();
+                div()
+                    .id("container")
+                    .size(px(100.))
+                    .overflow_y_scroll()
+                    .track_scroll(&scroll_handle)
+                    .child(div().h(px(20.)))
+                    .child(
+                        div().sticky_top(px(5.)).h(px(10.)).child(
+                            canvas(
+                                |_, _| {},
+                                move |bounds, _, _| painted_top.set(Some(bounds.top())),
+                            )
+                            .size_full(),
+                        ),
+                    )
+                    .child(div().h(px(200.)))
+            });
+            painted_top.get().unwrap()
+        };
+
+        // Below its pinned position, the element scrolls with the content.
+        assert_eq!(draw(px(0.)), px(20.));
+        assert_eq!(draw(px(10.)), px(10.));
+        // Once it would scroll past the offset, it stays there.
+        assert_eq!(draw(px(18.)), px(5.));
+        assert_eq!(draw(px(100.)), px(5.));
+    }
+
+    #[gpui::test]
+    fn test_sticky_top_in_clipped_scroll_container(cx: &mut TestAppContext) {
+        let cx = cx.add_empty_window();
+        let scroll_handle = ScrollHandle::new();
+        let painted_top = Rc::new(Cell::new(None));
+
+        let mut draw = |scroll_top: Pixels| {
+            scroll_handle.set_offset(point(px(0.), -scroll_top));
+            cx.draw(point(px(0.), px(0.)), size(px(100.), px(100.)), |_| {
+                let painted_top = painted_top.clone();
+                // The scroll container starts 20px above its clipping parent, so only
+                // the part of it below the parent's top edge is visible.
+                div().size(px(100.)).overflow_hidden().child(
+                    div()
+                        .id("container")
+                        .mt(px(-20.))
+                        .w(px(100.))
+                        .h(px(120.))
+                        .overflow_y_scroll()
+                        .track_scroll(&scroll_handle)
+                        .child(
+                            div()
+                                .child(
+                                    div().id("header").sticky_top(px(0.)).h(px(10.)).child(
+                                        canvas(
+                                            |_, _| {},
+                                            move |bounds, _, _| painted_top.set(Some(bounds.top())),
+                                        )
+                                        .size_full(),
+                                    ),
+                                )
+                                .child(div().h(px(50.))),
+                        )
+                        .child(div().h(px(200.))),
+                )
+            });
+            painted_top.get().unwrap()
+        };
+
+        // The element is pinned to the top of the clipping parent rather than to the
+        // scroll container's own top edge, which is clipped away.
+        assert_eq!(draw(px(0.)), px(0.));
+        assert_eq!(draw(px(30.)), px(0.));
+        // It's still released at its parent's bottom edge.
+        assert_eq!(draw(px(55.)), px(-25.));
+    }
+
+    #[gpui::test]
+    fn test_aspect_ratio(cx: &mut TestAppContext) {
+        let cx = cx.add_empty_window();
+        let mut painted_size = |element: Div| {
+            let painted_size = Rc::new(Cell::new(None));
+            cx.draw(point(px(0.), px(0.)), size(px(500.), px(500.)), |_| {
+                let painted_size = painted_size.clone();
+                div().child(
+                    element.child(
+                        canvas(
+                            |_, _| {},
+                            move |bounds, _, _| painted_size.set(Some(bounds.size)),
+                        )
+                        .size_full(),
+                    ),
+                )
+            });
+            painted_size.get().unwrap()
+        };
+
+        assert_eq!(
+            painted_size(div().w(px(200.)).aspect_ratio(2.)),
+            size(px(200.), px(100.))
+        );
+        // An explicit height wins over the ratio.
+        assert_eq!(
+            painted_size(div().w(px(200.)).h(px(30.)).aspect_ratio(2.)),
+            size(px(200.), px(30.))
+        );
+    }
+
+    #[gpui::test]
+    fn test_opacity(cx: &mut TestAppContext) {
+        assert_eq!(
+            div().opacity(0.5).interactivity.base_style.opacity,
+            Some(0.5)
+        );
+        assert_eq!(div().opacity(2.).interactivity.base_style.opacity, Some(1.));
+        assert_eq!(
+            div().opacity(-1.).interactivity.base_style.opacity,
+            Some(0.)
+        );
+
+        // Nested opacities multiply into the painted colors and images.
+        let cx = cx.add_empty_window();
+        let background_alphas = Rc::new(RefCell::new(Vec::new()));
+        let image_opacities = Rc::new(RefCell::new(Vec::new()));
+        let image = std::sync::Arc::new(ImageData::new(image::ImageBuffer::new(1, 1)));
+        cx.draw(point(px(0.), px(0.)), size(px(100.), px(100.)), |_| {
+            let background_alphas = background_alphas.clone();
+            let image_opacities = image_opacities.clone();
+            div().size_full().opacity(0.5).bg(red()).child(
+                div()
+                    .size_full()
+                    .opacity(0.5)
+                    .bg(red())
+                    .child(img(ImageSource::Data(image)).size(px(10.)))
+                    .child(
+                        canvas(
+                            |_, _| {},
+                            move |_, _, cx| {
+                                let scene = &cx.window.next_frame.scene;
+                                *background_alphas.borrow_mut() =
+                                    scene.quads.iter().map(|quad| quad.background.a).collect();
+                                *image_opacities.borrow_mut() = scene
+                                    .polychrome_sprites
+                                    .iter()
+                                    .map(|sprite| sprite.opacity)
+                                    .collect();
+                            },
+                        )
+                        .size_full(),
+                    ),
+            )
+        });
+        assert_eq!(*background_alphas.borrow(), [0.5, 0.25]);
+        assert_eq!(*image_opacities.borrow(), [0.25]);
+    }
+
+    #[gpui::test]
+    fn test_group_hover(cx: &mut TestAppContext) {
+        struct Row {
+            icon_color: Rc<Cell<Option<Hsla>>>,
+        }
+
+        impl Render for Row {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                let icon_color = self.icon_color.clone();
+                div().size_full().child(
+                    div().group("row").w_full().h(px(20.)).child(
+                        div()
+                            .size(px(10.))
+                            .group_hover("row", |style| style.text_color(red()))
+                            .child(
+                                canvas(
+                                    |_, _| {},
+                                    move |_, _, cx| icon_color.set(Some(cx.text_style().color)),
+                                )
+                                .size_full(),
+                            ),
+                    ),
+                )
+            }
+        }
+
+        let icon_color = Rc::new(Cell::new(None));
+        let (_, cx) = cx.add_window_view(|_| Row {
+            icon_color: icon_color.clone(),
+        });
+
+        // Hovering the row outside of the icon applies the icon's group hover style.
+        cx.simulate_mouse_move(point(px(50.), px(5.)), None, Modifiers::default());
+        assert_eq!(icon_color.get(), Some(red()));
+
+        cx.simulate_mouse_move(point(px(50.), px(50.)), None, Modifiers::default());
+        assert_ne!(icon_color.get(), Some(red()));
+    }
+
+    #[gpui::test]
+    fn test_width_transition(cx: &mut TestAppContext) {
+        struct Panel {
+            width: Pixels,
+            painted_width: Rc<Cell<Option<Pixels>>>,
+        }
+
+        impl Render for Panel {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                let painted_width = self.painted_width.clone();
+                div().size_full().child(
+                    div()
+                        .w(self.width)
+                        .h(px(10.))
+                        .id("panel")
+                        .transition(StyleProperty::Width, Duration::from_secs(1), linear)
+                        .child(
+                            canvas(
+                                |_, _| {},
+                                move |bounds, _, _| painted_width.set(Some(bounds.size.width)),
+                            )
+                            .size_full(),
+                        ),
+                )
+            }
+        }
+
+        let painted_width = Rc::new(Cell::new(None));
+        let (panel, cx) = cx.add_window_view(|_| Panel {
+            width: px(100.),
+            painted_width: painted_width.clone(),
+        });
+        cx.run_until_parked();
+        assert_eq!(painted_width.get(), Some(px(100.)));
+
+        // The first frame after the change still shows the old width.
+        panel.update(cx, |panel, cx| {
+            panel.width = px(200.);
+            cx.notify();
+        });
+        cx.run_until_parked();
+        assert_eq!(painted_width.get(), Some(px(100.)));
+
+        let redraw = |cx: &mut VisualTestContext| {
+            panel.update(cx, |_, cx| cx.notify());
+            cx.run_until_parked();
+        };
+
+        cx.executor().advance_clock(Duration::from_millis(500));
+        redraw(cx);
+        assert_eq!(painted_width.get(), Some(px(150.)));
+
+        // Changing course mid-transition starts from the width on screen.
+        panel.update(cx, |panel, cx| {
+            panel.width = px(50.);
+            cx.notify();
+        });
+        cx.run_until_parked();
+        assert_eq!(painted_width.get(), Some(px(150.)));
+        cx.executor().advance_clock(Duration::from_millis(500));
+        redraw(cx);
+        assert_eq!(painted_width.get(), Some(px(100.)));
+
+        cx.executor().advance_clock(Duration::from_secs(1));
+        redraw(cx);
+        assert_eq!(painted_width.get(), Some(px(50.)));
+    }
+
+    #[gpui::test]
+    fn test_scroll_to(cx: &mut TestAppContext) {
+        struct Scroller {
+            scroll_handle: ScrollHandle,
+            offsets: Rc<RefCell<Vec<Point<Pixels>>>>,
+        }
+
+        impl Render for Scroller {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                let offsets = self.offsets.clone();
+                div()
+                    .id("scroller")
+                    .size(px(100.))
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .on_scroll(move |offset, _| offsets.borrow_mut().push(offset))
+                    .child(div().h(px(300.)))
+            }
+        }
+
+        let scroll_handle = ScrollHandle::new();
+        let offsets = Rc::new(RefCell::new(Vec::new()));
+        let (_, cx) = cx.add_window_view(|_| Scroller {
+            scroll_handle: scroll_handle.clone(),
+            offsets: offsets.clone(),
+        });
+        cx.run_until_parked();
+
+        cx.update(|cx| scroll_handle.scroll_to(point(px(0.), px(-150.)), cx));
+        assert_eq!(scroll_handle.offset(), point(px(0.), px(-150.)));
+        assert_eq!(*offsets.borrow(), [point(px(0.), px(-150.))]);
+
+        // The offset is clamped to the content, and pinned to zero horizontally,
+        // where the content fits.
+        cx.update(|cx| scroll_handle.scroll_to(point(px(-50.), px(-1000.)), cx));
+        assert_eq!(scroll_handle.offset(), point(px(0.), px(-200.)));
+        assert_eq!(
+            *offsets.borrow(),
+            [point(px(0.), px(-150.)), point(px(0.), px(-200.))]
+        );
+
+        // Scrolling to where the content already is doesn't notify the listeners.
+        cx.update(|cx| scroll_handle.scroll_to(point(px(0.), px(-300.)), cx));
+        assert_eq!(offsets.borrow().len(), 2);
+    }
+
+    #[gpui::test]
+    fn test_kinetic_scroll(cx: &mut TestAppContext) {
+        struct Scroller {
+            scroll_handle: ScrollHandle,
+        }
+
+        impl Render for Scroller {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                div()
+                    .id("scroller")
+                    .size(px(100.))
+                    .overflow_y_scroll()
+                    .track_scroll(&self.scroll_handle)
+                    .kinetic_scroll(true)
+                    .child(div().h(px(2000.)))
+            }
+        }
+
+        let scroll_handle = ScrollHandle::new();
+        let (_, cx) = cx.add_window_view(|_| Scroller {
+            scroll_handle: scroll_handle.clone(),
+        });
+        cx.run_until_parked();
+
+        let scroll = |delta: Pixels, touch_phase: TouchPhase, cx: &mut VisualTestContext| {
+            cx.simulate_event(ScrollWheelEvent {
+                position: point(px(50.), px(50.)),
+                delta: ScrollDelta::Pixels(point(px(0.), delta)),
+                touch_phase,
+                ..Default::default()
+            });
+            cx.run_until_parked();
+        };
+        let next_frame = |cx: &mut VisualTestContext| {
+            cx.executor().advance_clock(KINETIC_SCROLL_FRAME);
+            cx.run_until_parked();
+            scroll_handle.offset().y
+        };
+
+        // Flick downwards at 2500px per second.
+        scroll(px(0.), TouchPhase::Started, cx);
+        cx.executor().advance_clock(Duration::from_millis(16));
+        scroll(px(-40.), TouchPhase::Moved, cx);
+        scroll(px(0.), TouchPhase::Ended, cx);
+        assert_eq!(scroll_handle.offset().y, px(-40.));
+
+        // The content keeps scrolling, slowing down as it goes.
+        let mut offset = px(-40.);
+        let mut step = px(f32::INFINITY);
+        for _ in 0..10 {
+            let next_offset = next_frame(cx);
+            assert!(next_offset < offset);
+            assert!(offset - next_offset < step);
+            step = offset - next_offset;
+            offset = next_offset;
+        }
+
+        // Until it comes to rest.
+        cx.executor().advance_clock(Duration::from_secs(2));
+        let offset = next_frame(cx);
+        assert!(offset < px(-400.) && offset > px(-500.));
+        assert_eq!(next_frame(cx), offset);
+
+        // Touching the trackpad again stops a flick in its tracks.
+        scroll(px(0.), TouchPhase::Started, cx);
+        cx.executor().advance_clock(Duration::from_millis(16));
+        scroll(px(-40.), TouchPhase::Moved, cx);
+        scroll(px(0.), TouchPhase::Ended, cx);
+        let flicked = next_frame(cx);
+        assert!(flicked < offset - px(40.));
+        scroll(px(0.), TouchPhase::Started, cx);
+        assert_eq!(next_frame(cx), flicked);
+        assert_eq!(next_frame(cx), flicked);
+
+        // Reaching the end of the content stops it too.
+        scroll_handle.set_offset(point(px(0.), px(-1880.)));
+        scroll(px(0.), TouchPhase::Started, cx);
+        cx.executor().advance_clock(Duration::from_millis(4));
+        scroll(px(-10.), TouchPhase::Moved, cx);
+        scroll(px(0.), TouchPhase::Ended, cx);
+        assert_eq!(next_frame(cx), px(-1900.));
+        assert_eq!(next_frame(cx), px(-1900.));
+    }
+}
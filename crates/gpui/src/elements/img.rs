@@ -369,6 +369,7 @@ impl Asset for Image {
         async move {
             let bytes = match source.clone() {
                 UriOrPath::Path(uri) => fs::read(uri.as_ref())?,
+                UriOrPath::Uri(uri) if uri.starts_with("data:") => decode_data_uri(&uri)?,
                 UriOrPath::Uri(uri) => {
                     let mut response = client.get(uri.as_ref(), ().into(), true).await?;
                     let mut body = Vec::new();
@@ -401,6 +402,20 @@ impl Asset for Image {
     }
 }
 
+/// Decodes the payload of a `data:` URI, which is either base64-encoded or
+/// included verbatim.
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, ImageCacheError> {
+    let (header, data) = uri
+        .strip_prefix("data:")
+        .and_then(|uri| uri.split_once(','))
+        .ok_or(ImageCacheError::InvalidDataUri)?;
+    if header.ends_with(";base64") {
+        base64::decode(data).map_err(|_| ImageCacheError::InvalidDataUri)
+    } else {
+        Ok(data.as_bytes().to_vec())
+    }
+}
+
 /// An error that can occur when interacting with the image cache.
 #[derive(Debug, Error, Clone)]
 pub enum ImageCacheError {
@@ -424,6 +439,9 @@ pub enum ImageCacheError {
     /// An error that occurred while processing an SVG.
     #[error("svg error: {0}")]
     Usvg(Arc<usvg::Error>),
+    /// A `data:` URI that couldn't be decoded.
+    #[error("invalid data URI")]
+    InvalidDataUri,
 }
 
 impl From<std::io::Error> for ImageCacheError {
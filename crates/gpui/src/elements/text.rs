@@ -30,7 +30,7 @@ impl Element for &'static str {
         cx: &mut WindowContext,
     ) -> (LayoutId, Self::RequestLayoutState) {
         let mut state = TextLayout::default();
-        let layout_id = state.layout(SharedString::from(*self), None, cx);
+        let layout_id = state.layout(SharedString::from(*self), None, Pixels::ZERO, cx);
         (layout_id, state)
     }
 
@@ -88,7 +88,7 @@ impl Element for SharedString {
         cx: &mut WindowContext,
     ) -> (LayoutId, Self::RequestLayoutState) {
         let mut state = TextLayout::default();
-        let layout_id = state.layout(self.clone(), None, cx);
+        let layout_id = state.layout(self.clone(), None, Pixels::ZERO, cx);
         (layout_id, state)
     }
 
@@ -130,6 +130,7 @@ impl IntoElement for SharedString {
 pub struct StyledText {
     text: SharedString,
     runs: Option<Vec<TextRun>>,
+    wrap_indent: Pixels,
     layout: TextLayout,
 }
 
@@ -139,6 +140,7 @@ impl StyledText {
         StyledText {
             text: text.into(),
             runs: None,
+            wrap_indent: Pixels::ZERO,
             layout: TextLayout::default(),
         }
     }
@@ -181,6 +183,13 @@ impl StyledText {
         self.runs = Some(runs);
         self
     }
+
+    /// Indent the rows that a line wraps onto by the given amount, leaving its
+    /// first row where it is.
+    pub fn with_wrap_indent(mut self, wrap_indent: Pixels) -> Self {
+        self.wrap_indent = wrap_indent;
+        self
+    }
 }
 
 impl Element for StyledText {
@@ -198,7 +207,10 @@ impl Element for StyledText {
 
         cx: &mut WindowContext,
     ) -> (LayoutId, Self::RequestLayoutState) {
-        let layout_id = self.layout.layout(self.text.clone(), self.runs.take(), cx);
+        let runs = self.runs.take();
+        let layout_id = self
+            .layout
+            .layout(self.text.clone(), runs, self.wrap_indent, cx);
         (layout_id, ())
     }
 
@@ -253,6 +265,7 @@ impl TextLayout {
         &mut self,
         text: SharedString,
         runs: Option<Vec<TextRun>>,
+        wrap_indent: Pixels,
         cx: &mut WindowContext,
     ) -> LayoutId {
         let text_style = cx.text_style();
@@ -295,6 +308,7 @@ impl TextLayout {
                         font_size,
                         &runs,
                         wrap_width, // Wrap if we know the width.
+                        wrap_indent,
                     )
                     .log_err()
                 else {
@@ -13,7 +13,7 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use util::TryFutureExt;
 use waker_fn::waker_fn;
@@ -188,8 +188,6 @@ impl BackgroundExecutor {
         future: impl Future<Output = R>,
         timeout: Option<Duration>,
     ) -> Result<R, impl Future<Output = R>> {
-        use std::time::Instant;
-
         let mut future = Box::pin(future);
         if timeout == Some(Duration::ZERO) {
             return Err(future);
@@ -326,6 +324,12 @@ impl BackgroundExecutor {
         Task::Spawned(task)
     }
 
+    /// The current time, as seen by this executor. In tests, this only moves
+    /// forward when the clock is advanced.
+    pub fn now(&self) -> Instant {
+        self.dispatcher.now()
+    }
+
     /// in tests, start_waiting lets you indicate which task is waiting (for debugging only)
     #[cfg(any(test, feature = "test-support"))]
     pub fn start_waiting(&self) {
@@ -38,7 +38,7 @@ use seahash::SeaHasher;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     fmt::{self, Debug},
     ops::Range,
@@ -152,6 +152,7 @@ pub(crate) trait Platform: 'static {
 
     fn set_cursor_style(&self, style: CursorStyle);
     fn should_auto_hide_scrollbars(&self) -> bool;
+    fn should_reduce_motion(&self) -> bool;
 
     #[cfg(target_os = "linux")]
     fn write_to_primary(&self, item: ClipboardItem);
@@ -264,6 +265,10 @@ pub trait PlatformDispatcher: Send + Sync {
     fn park(&self, timeout: Option<Duration>) -> bool;
     fn unparker(&self) -> Unparker;
 
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
     #[cfg(any(test, feature = "test-support"))]
     fn as_test(&self) -> Option<&TestDispatcher> {
         None
@@ -621,6 +621,11 @@ impl Platform for WindowsPlatform {
         false
     }
 
+    // todo(windows)
+    fn should_reduce_motion(&self) -> bool {
+        false
+    }
+
     fn write_to_clipboard(&self, item: ClipboardItem) {
         if item.text.len() > 0 {
             let mut ctx = ClipboardContext::new().unwrap();
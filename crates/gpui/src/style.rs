@@ -33,6 +33,9 @@ pub struct Style {
     /// Should the element be painted on screen?
     pub visibility: Visibility,
 
+    /// The opacity multiplied into the colors painted by the element and its children.
+    pub opacity: Option<f32>,
+
     // Overflow properties
     /// How children overflowing their container should affect layout
     #[refineable]
@@ -393,6 +396,17 @@ impl Style {
         bounds: Bounds<Pixels>,
         cx: &mut WindowContext,
         continuation: impl FnOnce(&mut WindowContext),
+    ) {
+        cx.with_element_opacity(self.opacity, |cx| {
+            self.paint_with_opacity(bounds, cx, continuation)
+        });
+    }
+
+    fn paint_with_opacity(
+        &self,
+        bounds: Bounds<Pixels>,
+        cx: &mut WindowContext,
+        continuation: impl FnOnce(&mut WindowContext),
     ) {
         #[cfg(debug_assertions)]
         if self.debug_below {
@@ -507,6 +521,7 @@ impl Default for Style {
         Style {
             display: Display::Block,
             visibility: Visibility::Visible,
+            opacity: None,
             overflow: Point {
                 x: Overflow::Visible,
                 y: Overflow::Visible,
@@ -574,7 +589,7 @@ pub struct StrikethroughStyle {
 }
 
 /// The kinds of fill that can be applied to a shape.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Fill {
     /// A solid color fill.
     Color(Hsla),
@@ -57,6 +57,24 @@ pub trait Styled: Sized {
         self
     }
 
+    /// Sets the ratio of the element's width to its height, so that the height is
+    /// computed from the width or vice versa. An explicitly set width and height
+    /// both take precedence over the ratio.
+    /// [Docs](https://tailwindcss.com/docs/aspect-ratio)
+    fn aspect_ratio(mut self, ratio: f32) -> Self {
+        self.style().aspect_ratio = Some(ratio);
+        self
+    }
+
+    /// Sets the opacity of the element and its children, clamped to `0.0..=1.0`.
+    /// Nested opacities multiply, and apply to everything painted within the
+    /// element, including text, images and emoji.
+    /// [Docs](https://tailwindcss.com/docs/opacity)
+    fn opacity(mut self, opacity: f32) -> Self {
+        self.style().opacity = Some(opacity.clamp(0., 1.));
+        self
+    }
+
     /// Sets the behavior of content that overflows the container to be hidden.
     /// [Docs](https://tailwindcss.com/docs/overflow#hiding-content-that-overflows)
     fn overflow_hidden(mut self) -> Self {
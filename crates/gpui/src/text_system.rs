@@ -366,13 +366,15 @@ impl WindowTextSystem {
 
     /// Shape a multi line string of text, at the given font_size, for painting to the screen.
     /// Subsets of the text can be styled independently with the `runs` parameter.
-    /// If `wrap_width` is provided, the line breaks will be adjusted to fit within the given width.
+    /// If `wrap_width` is provided, the line breaks will be adjusted to fit within the given width,
+    /// and the lines after each break are indented by `wrap_indent`.
     pub fn shape_text(
         &self,
         text: SharedString,
         font_size: Pixels,
         runs: &[TextRun],
         wrap_width: Option<Pixels>,
+        wrap_indent: Pixels,
     ) -> Result<SmallVec<[WrappedLine; 1]>> {
         let mut runs = runs.iter().cloned().peekable();
         let mut font_runs = self.font_runs_pool.lock().pop().unwrap_or_default();
@@ -429,9 +431,13 @@ impl WindowTextSystem {
                 run_start += run_len_within_line;
             }
 
-            let layout = self
-                .line_layout_cache
-                .layout_wrapped_line(&line_text, font_size, &font_runs, wrap_width);
+            let layout = self.line_layout_cache.layout_wrapped_line(
+                &line_text,
+                font_size,
+                &font_runs,
+                wrap_width,
+                wrap_indent,
+            );
 
             lines.push(WrappedLine {
                 layout,
@@ -56,6 +56,7 @@ impl ShapedLine {
             line_height,
             &self.decoration_runs,
             &[],
+            Pixels::ZERO,
             cx,
         )?;
 
@@ -94,6 +95,7 @@ impl WrappedLine {
             line_height,
             &self.decoration_runs,
             &self.wrap_boundaries,
+            self.wrap_indent,
             cx,
         )?;
 
@@ -107,6 +109,7 @@ fn paint_line(
     line_height: Pixels,
     decoration_runs: &[DecorationRun],
     wrap_boundaries: &[WrapBoundary],
+    wrap_indent: Pixels,
     cx: &mut WindowContext,
 ) -> Result<()> {
     let line_bounds = Bounds::new(origin, size(layout.width, line_height));
@@ -131,6 +134,7 @@ fn paint_line(
 
                 if wraps.peek() == Some(&&WrapBoundary { run_ix, glyph_ix }) {
                     wraps.next();
+                    let line_start_x = origin.x + wrap_indent;
                     if let Some((background_origin, background_color)) = current_background.as_mut()
                     {
                         cx.paint_quad(fill(
@@ -140,7 +144,7 @@ fn paint_line(
                             },
                             *background_color,
                         ));
-                        background_origin.x = origin.x;
+                        background_origin.x = line_start_x;
                         background_origin.y += line_height;
                     }
                     if let Some((underline_origin, underline_style)) = current_underline.as_mut() {
@@ -149,7 +153,7 @@ fn paint_line(
                             glyph_origin.x - underline_origin.x,
                             underline_style,
                         );
-                        underline_origin.x = origin.x;
+                        underline_origin.x = line_start_x;
                         underline_origin.y += line_height;
                     }
                     if let Some((strikethrough_origin, strikethrough_style)) =
@@ -160,11 +164,11 @@ fn paint_line(
                             glyph_origin.x - strikethrough_origin.x,
                             strikethrough_style,
                         );
-                        strikethrough_origin.x = origin.x;
+                        strikethrough_origin.x = line_start_x;
                         strikethrough_origin.y += line_height;
                     }
 
-                    glyph_origin.x = origin.x;
+                    glyph_origin.x = line_start_x;
                     glyph_origin.y += line_height;
                 }
                 prev_glyph_position = glyph.position;
@@ -290,7 +294,7 @@ fn paint_line(
         if let Some(boundary) = wrap_boundaries.last() {
             let run = &layout.runs[boundary.run_ix];
             let glyph = &run.glyphs[boundary.glyph_ix];
-            last_line_end_x -= glyph.position.x;
+            last_line_end_x -= glyph.position.x - wrap_indent;
         }
 
         if let Some((background_origin, background_color)) = current_background.take() {
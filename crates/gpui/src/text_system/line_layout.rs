@@ -119,6 +119,7 @@ impl LineLayout {
         &self,
         text: &str,
         wrap_width: Pixels,
+        wrap_indent: Pixels,
     ) -> SmallVec<[WrapBoundary; 1]> {
         let mut boundaries = SmallVec::new();
 
@@ -163,7 +164,13 @@ impl LineLayout {
 
             let next_x = glyphs.peek().map_or(self.width, |(_, _, x)| *x);
             let width = next_x - last_boundary_x;
-            if width > wrap_width && boundary > last_boundary {
+            // Lines after the first are indented, leaving less room for text.
+            let line_width = if boundaries.is_empty() {
+                wrap_width
+            } else {
+                wrap_width - wrap_indent
+            };
+            if width > line_width && boundary > last_boundary {
                 if let Some(last_candidate_ix) = last_candidate_ix.take() {
                     last_boundary = last_candidate_ix;
                     last_boundary_x = last_candidate_x;
@@ -192,6 +199,9 @@ pub struct WrappedLineLayout {
 
     /// The width of the line, if it was wrapped
     pub wrap_width: Option<Pixels>,
+
+    /// How far the lines after each wrap boundary are indented
+    pub wrap_indent: Pixels,
 }
 
 /// A boundary at which a line was wrapped
@@ -269,6 +279,7 @@ impl WrappedLineLayout {
             let glyph = &run.glyphs[line_start_boundary.glyph_ix];
             wrapped_line_start_index = glyph.index;
             wrapped_line_start_x = glyph.position.x;
+            position.x -= self.wrap_indent;
         } else {
             wrapped_line_start_index = 0;
             wrapped_line_start_x = Pixels::ZERO;
@@ -324,7 +335,10 @@ impl WrappedLineLayout {
                 continue;
             } else {
                 let line_start_x = self.unwrapped_layout.x_for_index(line_start_ix);
-                let x = self.unwrapped_layout.x_for_index(index) - line_start_x;
+                let mut x = self.unwrapped_layout.x_for_index(index) - line_start_x;
+                if ix > 0 {
+                    x += self.wrap_indent;
+                }
                 return Some(point(x, line_y));
             }
         }
@@ -415,12 +429,14 @@ impl LineLayoutCache {
         font_size: Pixels,
         runs: &[FontRun],
         wrap_width: Option<Pixels>,
+        wrap_indent: Pixels,
     ) -> Arc<WrappedLineLayout> {
         let key = &CacheKeyRef {
             text,
             font_size,
             runs,
             wrap_width,
+            wrap_indent,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
@@ -441,7 +457,7 @@ impl LineLayoutCache {
 
             let unwrapped_layout = self.layout_line(text, font_size, runs);
             let wrap_boundaries = if let Some(wrap_width) = wrap_width {
-                unwrapped_layout.compute_wrap_boundaries(text.as_ref(), wrap_width)
+                unwrapped_layout.compute_wrap_boundaries(text.as_ref(), wrap_width, wrap_indent)
             } else {
                 SmallVec::new()
             };
@@ -449,12 +465,14 @@ impl LineLayoutCache {
                 unwrapped_layout,
                 wrap_boundaries,
                 wrap_width,
+                wrap_indent,
             });
             let key = Arc::new(CacheKey {
                 text: text.into(),
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width,
+                wrap_indent,
             });
 
             let mut current_frame = self.current_frame.write();
@@ -473,6 +491,7 @@ impl LineLayoutCache {
             font_size,
             runs,
             wrap_width: None,
+            wrap_indent: Pixels::ZERO,
         } as &dyn AsCacheKeyRef;
 
         let current_frame = self.current_frame.upgradable_read();
@@ -492,6 +511,7 @@ impl LineLayoutCache {
                 font_size,
                 runs: SmallVec::from(runs),
                 wrap_width: None,
+                wrap_indent: Pixels::ZERO,
             });
             current_frame.lines.insert(key.clone(), layout.clone());
             current_frame.used_lines.push(key);
@@ -517,6 +537,7 @@ struct CacheKey {
     font_size: Pixels,
     runs: SmallVec<[FontRun; 1]>,
     wrap_width: Option<Pixels>,
+    wrap_indent: Pixels,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
@@ -525,6 +546,7 @@ struct CacheKeyRef<'a> {
     font_size: Pixels,
     runs: &'a [FontRun],
     wrap_width: Option<Pixels>,
+    wrap_indent: Pixels,
 }
 
 impl<'a> PartialEq for (dyn AsCacheKeyRef + 'a) {
@@ -548,6 +570,7 @@ impl AsCacheKeyRef for CacheKey {
             font_size: self.font_size,
             runs: self.runs.as_slice(),
             wrap_width: self.wrap_width,
+            wrap_indent: self.wrap_indent,
         }
     }
 }
@@ -49,7 +49,12 @@ impl LineWrapper {
                     continue;
                 }
 
-                if prev_c == ' ' && c != ' ' && first_non_whitespace_ix.is_some() {
+                // Lines can wrap after a space or a soft hyphen, but not at a
+                // non-breaking space.
+                if (prev_c == ' ' || prev_c == '\u{ad}')
+                    && c != ' '
+                    && first_non_whitespace_ix.is_some()
+                {
                     last_candidate_ix = ix;
                     last_candidate_width = width;
                 }
@@ -143,7 +148,9 @@ impl Boundary {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{font, TestAppContext, TestDispatcher, TextRun, WindowTextSystem, WrapBoundary};
+    use crate::{
+        font, point, TestAppContext, TestDispatcher, TextRun, WindowTextSystem, WrapBoundary,
+    };
     use rand::prelude::*;
 
     #[test]
@@ -212,6 +219,41 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_wrap_line_at_break_hints() {
+        let dispatcher = TestDispatcher::new(StdRng::seed_from_u64(0));
+        let cx = TestAppContext::new(dispatcher, None);
+
+        cx.update(|cx| {
+            let text_system = cx.text_system().clone();
+            let mut wrapper = LineWrapper::new(
+                text_system.font_id(&font("Courier")).unwrap(),
+                px(16.),
+                text_system.platform_text_system.clone(),
+            );
+            // A soft hyphen is a break opportunity.
+            assert_eq!(
+                wrapper
+                    .wrap_line("aa\u{ad}bbbbbbbbb", px(72.))
+                    .collect::<Vec<_>>(),
+                &[Boundary::new(4, 0), Boundary::new(11, 0)],
+            );
+            // A non-breaking space isn't, unlike a regular space.
+            assert_eq!(
+                wrapper
+                    .wrap_line("aa bbbbbbbbb", px(72.))
+                    .collect::<Vec<_>>(),
+                &[Boundary::new(3, 0), Boundary::new(10, 0)],
+            );
+            assert_eq!(
+                wrapper
+                    .wrap_line("aa\u{a0}bbbbbbbbb", px(72.))
+                    .collect::<Vec<_>>(),
+                &[Boundary::new(8, 0)],
+            );
+        });
+    }
+
     // For compatibility with the test macro
     use crate as gpui;
 
@@ -258,6 +300,7 @@ mod tests {
                         normal.with_len(7),
                     ],
                     Some(px(72.)),
+                    px(0.),
                 )
                 .unwrap();
 
@@ -280,4 +323,60 @@ mod tests {
             );
         });
     }
+
+    #[crate::test]
+    fn test_wrap_indent(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let text_system = WindowTextSystem::new(cx.text_system().clone());
+            let run = TextRun {
+                len: 19,
+                font: font("Courier"),
+                color: Default::default(),
+                underline: Default::default(),
+                strikethrough: None,
+                background_color: None,
+            };
+            let shape = |wrap_indent| {
+                text_system
+                    .shape_text(
+                        "aaaa aaaa aaaa aaaa".into(),
+                        px(16.),
+                        &[run.clone()],
+                        Some(px(72.)),
+                        wrap_indent,
+                    )
+                    .unwrap()
+                    .pop()
+                    .unwrap()
+            };
+            let unindented = shape(px(0.));
+            let indented = shape(px(20.));
+            let line_height = px(20.);
+            let indent = point(px(20.), px(0.));
+
+            // The first row stays put, and the rows it wraps onto are indented.
+            assert_eq!(indented.wrap_boundaries().len(), 3);
+            assert_eq!(
+                indented.position_for_index(1, line_height),
+                unindented.position_for_index(1, line_height)
+            );
+            let position = unindented.position_for_index(11, line_height).unwrap();
+            assert_eq!(
+                indented.position_for_index(11, line_height),
+                Some(position + indent)
+            );
+
+            // Positions map back to the same indices.
+            let position = position + point(px(1.), line_height / 2.);
+            assert_eq!(
+                indented.index_for_position(position + indent, line_height),
+                unindented.index_for_position(position, line_height)
+            );
+            // The indent before a wrapped row is before its first character.
+            assert_eq!(
+                indented.index_for_position(point(px(5.), position.y), line_height),
+                Err(10)
+            );
+        });
+    }
 }
@@ -504,13 +504,15 @@ pub struct Window {
     pub(crate) text_style_stack: Vec<TextStyleRefinement>,
     pub(crate) element_offset_stack: Vec<Point<Pixels>>,
     pub(crate) content_mask_stack: Vec<ContentMask<Pixels>>,
+    pub(crate) containing_block_stack: Vec<Bounds<Pixels>>,
+    pub(crate) element_opacity: Option<f32>,
     pub(crate) requested_autoscroll: Option<Bounds<Pixels>>,
     pub(crate) rendered_frame: Frame,
     pub(crate) next_frame: Frame,
     pub(crate) next_hitbox_id: HitboxId,
     pub(crate) next_tooltip_id: TooltipId,
     pub(crate) tooltip_bounds: Option<TooltipBounds>,
-    next_frame_callbacks: Rc<RefCell<Vec<FrameCallback>>>,
+    pub(crate) next_frame_callbacks: Rc<RefCell<Vec<FrameCallback>>>,
     pub(crate) dirty_views: FxHashSet<EntityId>,
     pub(crate) focus_handles: Arc<RwLock<SlotMap<FocusId, AtomicUsize>>>,
     focus_listeners: SubscriberSet<(), AnyWindowFocusListener>,
@@ -529,6 +531,8 @@ pub struct Window {
     pub(crate) last_input_timestamp: Rc<Cell<Instant>>,
     pub(crate) refreshing: bool,
     pub(crate) draw_phase: DrawPhase,
+    /// Whether animations snap to their final state, as of the last draw.
+    reduce_motion: bool,
     activation_observers: SubscriberSet<(), AnyObserver>,
     pub(crate) focus: Option<FocusId>,
     focus_enabled: bool,
@@ -777,6 +781,8 @@ impl Window {
             text_style_stack: Vec::new(),
             element_offset_stack: Vec::new(),
             content_mask_stack: Vec::new(),
+            containing_block_stack: Vec::new(),
+            element_opacity: None,
             requested_autoscroll: None,
             rendered_frame: Frame::new(DispatchTree::new(cx.keymap.clone(), cx.actions.clone())),
             next_frame: Frame::new(DispatchTree::new(cx.keymap.clone(), cx.actions.clone())),
@@ -802,6 +808,7 @@ impl Window {
             last_input_timestamp,
             refreshing: false,
             draw_phase: DrawPhase::None,
+            reduce_motion: false,
             activation_observers: SubscriberSet::new(),
             focus: None,
             focus_enabled: true,
@@ -866,6 +873,13 @@ impl<'a> WindowContext<'a> {
         self.window.handle
     }
 
+    /// Returns whether animations should snap straight to their final state. Unlike
+    /// [`AppContext::should_reduce_motion`], this is read once per draw, so the
+    /// elements of a frame agree on it.
+    pub fn should_reduce_motion(&self) -> bool {
+        self.window.reduce_motion
+    }
+
     /// Mark the window as dirty, scheduling it to be redrawn on the next frame.
     pub fn refresh(&mut self) {
         if self.window.draw_phase == DrawPhase::None {
@@ -1340,6 +1354,7 @@ impl<'a> WindowContext<'a> {
     pub fn draw(&mut self) {
         self.window.dirty.set(false);
         self.window.requested_autoscroll = None;
+        self.window.reduce_motion = self.app.should_reduce_motion();
 
         // Restore the previously-used input handler.
         if let Some(input_handler) = self.window.platform_window.take_input_handler() {
@@ -1776,6 +1791,37 @@ impl<'a> WindowContext<'a> {
         }
     }
 
+    /// Invoke the given function with the given opacity multiplied into the current one. The
+    /// opacity applies to the colors of everything painted within the function. This method
+    /// should only be called during the paint phase of element drawing.
+    pub fn with_element_opacity<R>(
+        &mut self,
+        opacity: Option<f32>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        debug_assert_eq!(
+            self.window.draw_phase,
+            DrawPhase::Paint,
+            "this method can only be called during paint"
+        );
+        let Some(opacity) = opacity else {
+            return f(self);
+        };
+        let previous_opacity = self.window.element_opacity;
+        self.window.element_opacity = Some(previous_opacity.unwrap_or(1.) * opacity);
+        let result = f(self);
+        self.window.element_opacity = previous_opacity;
+        result
+    }
+
+    /// Multiplies the opacity set with [`WindowContext::with_element_opacity`] into `color`.
+    fn apply_element_opacity(&self, mut color: Hsla) -> Hsla {
+        if let Some(opacity) = self.window.element_opacity {
+            color.a *= opacity;
+        }
+        color
+    }
+
     /// Updates the global element offset relative to the current offset. This is used to implement
     /// scrolling. This method should only be called during the prepaint phase of element drawing.
     pub fn with_element_offset<R>(
@@ -1816,6 +1862,31 @@ impl<'a> WindowContext<'a> {
         result
     }
 
+    /// Invoke the given function with the given bounds as the containing block of the
+    /// elements it prepaints. Sticky elements never leave their containing block. This
+    /// method should only be called during the prepaint phase of element drawing.
+    pub(crate) fn with_containing_block<R>(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        debug_assert_eq!(
+            self.window.draw_phase,
+            DrawPhase::Prepaint,
+            "this method can only be called during prepaint"
+        );
+        self.window_mut().containing_block_stack.push(bounds);
+        let result = f(self);
+        self.window_mut().containing_block_stack.pop();
+        result
+    }
+
+    /// Obtain the bounds of the current containing block, if any. This method should only
+    /// be called during the prepaint phase of element drawing.
+    pub(crate) fn containing_block(&self) -> Option<Bounds<Pixels>> {
+        self.window().containing_block_stack.last().copied()
+    }
+
     /// Perform prepaint on child elements in a "retryable" manner, so that any side effects
     /// of prepaints can be discarded before prepainting again. This is used to support autoscroll
     /// where we need to prepaint children to detect the autoscroll bounds, then adjust the
@@ -2221,7 +2292,7 @@ impl<'a> WindowContext<'a> {
                 bounds: shadow_bounds.scale(scale_factor),
                 content_mask: content_mask.scale(scale_factor),
                 corner_radii: corner_radii.scale(scale_factor),
-                color: shadow.color,
+                color: self.apply_element_opacity(shadow.color),
             });
         }
     }
@@ -2245,8 +2316,8 @@ impl<'a> WindowContext<'a> {
             pad: 0,
             bounds: quad.bounds.scale(scale_factor),
             content_mask: content_mask.scale(scale_factor),
-            background: quad.background,
-            border_color: quad.border_color,
+            background: self.apply_element_opacity(quad.background),
+            border_color: self.apply_element_opacity(quad.border_color),
             corner_radii: quad.corner_radii.scale(scale_factor),
             border_widths: quad.border_widths.scale(scale_factor),
         });
@@ -2265,7 +2336,7 @@ impl<'a> WindowContext<'a> {
         let scale_factor = self.scale_factor();
         let content_mask = self.content_mask();
         path.content_mask = content_mask;
-        path.color = color.into();
+        path.color = self.apply_element_opacity(color.into());
         self.window
             .next_frame
             .scene
@@ -2304,7 +2375,7 @@ impl<'a> WindowContext<'a> {
             pad: 0,
             bounds: bounds.scale(scale_factor),
             content_mask: content_mask.scale(scale_factor),
-            color: style.color.unwrap_or_default(),
+            color: self.apply_element_opacity(style.color.unwrap_or_default()),
             thickness: style.thickness.scale(scale_factor),
             wavy: style.wavy,
         });
@@ -2339,7 +2410,7 @@ impl<'a> WindowContext<'a> {
             bounds: bounds.scale(scale_factor),
             content_mask: content_mask.scale(scale_factor),
             thickness: style.thickness.scale(scale_factor),
-            color: style.color.unwrap_or_default(),
+            color: self.apply_element_opacity(style.color.unwrap_or_default()),
             wavy: false,
         });
     }
@@ -2404,7 +2475,7 @@ impl<'a> WindowContext<'a> {
                     pad: 0,
                     bounds,
                     content_mask,
-                    color,
+                    color: self.apply_element_opacity(color),
                     tile,
                     transformation: TransformationMatrix::unit(),
                 });
@@ -2468,6 +2539,8 @@ impl<'a> WindowContext<'a> {
                 .insert_primitive(PolychromeSprite {
                     order: 0,
                     grayscale: false,
+                    opacity: self.window.element_opacity.unwrap_or(1.),
+                    pad: 0,
                     bounds,
                     corner_radii: Default::default(),
                     content_mask,
@@ -2525,7 +2598,7 @@ impl<'a> WindowContext<'a> {
                 pad: 0,
                 bounds,
                 content_mask,
-                color,
+                color: self.apply_element_opacity(color),
                 tile,
                 transformation,
             });
@@ -2569,6 +2642,8 @@ impl<'a> WindowContext<'a> {
             .insert_primitive(PolychromeSprite {
                 order: 0,
                 grayscale,
+                opacity: self.window.element_opacity.unwrap_or(1.),
+                pad: 0,
                 bounds,
                 content_mask,
                 corner_radii,
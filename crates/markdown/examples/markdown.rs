@@ -91,7 +91,10 @@ pub fn main() {
         SettingsStore::update(cx, |store, cx| {
             store.update_user_settings::<AllLanguageSettings>(cx, |_| {});
         });
-        cx.bind_keys([KeyBinding::new("cmd-c", markdown::Copy, None)]);
+        cx.bind_keys([
+            KeyBinding::new("cmd-c", markdown::Copy, None),
+            KeyBinding::new("cmd-a", markdown::SelectAll, None),
+        ]);
 
         let node_runtime = FakeNodeRuntime::new();
         let language_registry = Arc::new(LanguageRegistry::new(
@@ -123,6 +126,7 @@ pub fn main() {
                         },
                         rule_color: Color::Muted.color(cx),
                         block_quote_border_color: Color::Muted.color(cx),
+                        table_border_color: Color::Muted.color(cx),
                         block_quote: gpui::TextStyleRefinement {
                             color: Some(Color::Muted.color(cx)),
                             ..Default::default()
@@ -142,6 +146,8 @@ pub fn main() {
                             selection.fade_out(0.7);
                             selection
                         },
+                        cursor_color: cx.theme().players().local().cursor,
+                        ..Default::default()
                     },
                     language_registry,
                     cx,
@@ -1,19 +1,35 @@
-mod parser;
+pub mod parser;
 
 use crate::parser::CodeBlockKind;
-use futures::FutureExt;
+use futures::{future, AsyncReadExt, FutureExt};
 use gpui::{
-    actions, point, quad, AnyElement, AppContext, Bounds, ClipboardItem, CursorStyle,
-    DispatchPhase, Edges, FocusHandle, FocusableView, FontStyle, FontWeight, GlobalElementId,
-    Hitbox, Hsla, KeyContext, MouseDownEvent, MouseEvent, MouseMoveEvent, MouseUpEvent, Point,
-    Render, StrikethroughStyle, Style, StyledText, Task, TextLayout, TextRun, TextStyle,
-    TextStyleRefinement, View,
+    actions, fill, hsla, img, point, quad, size, svg, AnyElement, AnyView, AppContext, Bounds,
+    ClickEvent, ClipboardItem, ContentMask, CursorStyle, DismissEvent, DispatchPhase, Edges,
+    FocusHandle, FocusableView, FontStyle, FontWeight, GlobalElementId, Hitbox, Hsla, ImageData,
+    ImageSource, KeyContext, LayoutId, MouseButton, MouseDownEvent, MouseEvent, MouseMoveEvent,
+    MouseUpEvent, Overflow, Point, Render, StrikethroughStyle, Style, StyledText, Subscription,
+    Task, TextLayout, TextRun, TextStyle, TextStyleRefinement, TransformationMatrix, View,
+    WhiteSpace,
 };
+use http::HttpClient;
 use language::{Language, LanguageRegistry, Rope};
-use parser::{parse_markdown, MarkdownEvent, MarkdownTag, MarkdownTagEnd};
-use std::{iter, mem, ops::Range, rc::Rc, sync::Arc};
+use parser::{
+    parse_appended_markdown, parse_edited_markdown, parse_markdown, MarkdownEvent, MarkdownTag,
+    MarkdownTagEnd, MetadataBlockKind, ParseOptions,
+};
+use pulldown_cmark::{Alignment, LinkType};
+use std::{
+    collections::{HashMap, HashSet},
+    iter, mem,
+    ops::Range,
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
 use theme::SyntaxTheme;
-use ui::prelude::*;
+use ui::{prelude::*, Checkbox, ContextMenu, Disclosure, LinkPreview};
+use unicode_segmentation::UnicodeSegmentation;
 use util::{ResultExt, TryFutureExt};
 
 #[derive(Clone)]
@@ -22,10 +38,285 @@ pub struct MarkdownStyle {
     pub inline_code: TextStyleRefinement,
     pub block_quote: TextStyleRefinement,
     pub link: TextStyleRefinement,
+    pub footnote: TextStyleRefinement,
+    /// The language used to syntax highlight inline code, e.g. `Rust` for API docs.
+    pub inline_code_language: Option<String>,
+    /// Paint the background of `inline_code` as a rounded rectangle that extends
+    /// this far past each side of the code, instead of behind the glyphs only.
+    pub inline_code_padding: Pixels,
     pub rule_color: Hsla,
     pub block_quote_border_color: Hsla,
+    /// The border colors of block quotes, cycled through by nesting depth. When
+    /// empty, quotes use `block_quote_border_color`.
+    pub block_quote_border_colors: Vec<Hsla>,
+    /// The text colors of block quotes, cycled through by nesting depth. When
+    /// empty, quotes use the color from `block_quote`.
+    pub block_quote_text_colors: Vec<Hsla>,
+    /// Indent the rows that a line of a block quote wraps onto by this much, so
+    /// that they stand out from the quote's border.
+    pub block_quote_hanging_indent: Option<Pixels>,
+    /// The colors of GitHub-style alerts like `> [!NOTE]`, used for their border,
+    /// icon and title, and to tint their background.
+    pub callout_note_color: Hsla,
+    pub callout_tip_color: Hsla,
+    pub callout_important_color: Hsla,
+    pub callout_warning_color: Hsla,
+    pub callout_caution_color: Hsla,
+    /// The opacity of the background tint of alerts, relative to their color.
+    pub callout_background_opacity: f32,
     pub syntax: Arc<SyntaxTheme>,
     pub selection_background_color: Hsla,
+    /// The color of selected text, for when the selection background is too close
+    /// to the text color. Selected text keeps its own color if this isn't set.
+    pub selection_text_color: Option<Hsla>,
+    /// The background of the rendered line under the mouse, like a paragraph or
+    /// the text of a list item. Lines aren't highlighted if this isn't set.
+    pub hovered_line_background: Option<Hsla>,
+    /// The color of the blinking caret shown at an empty selection while the
+    /// markdown is focused.
+    pub cursor_color: Hsla,
+    /// The background of text matching [`Markdown::set_search_query`], painted
+    /// over the text like the selection.
+    pub search_match_background: Hsla,
+    /// Prefix headings with their outline number (e.g. `1.2.1`).
+    pub number_headings: bool,
+    /// Whether generated heading numbers are included when copying text.
+    pub copy_heading_numbers: bool,
+    /// An icon rendered after the text of links to external (`http(s)://`) URLs.
+    pub external_link_icon: Option<IconName>,
+    /// Render a disclosure next to top-level headings that collapses the content
+    /// up to the next heading of the same or a higher level.
+    pub collapsible_headings: bool,
+    /// The maximum number of characters rendered for each run of link text,
+    /// including the ellipsis.
+    pub max_link_len: Option<usize>,
+    /// Where the ellipsis goes in link text truncated by `max_link_len`.
+    pub link_ellipsis: EllipsisMode,
+    /// Padding that insets all blocks from the edges of the markdown element.
+    pub content_padding: Edges<Pixels>,
+    /// Whether top-level rules and code blocks extend horizontally into
+    /// `content_padding`, spanning the full width of the element.
+    pub full_bleed_blocks: bool,
+    /// Caps the width of the content column to keep long lines readable.
+    pub max_content_width: Option<Pixels>,
+    /// Whether a content column narrower than the element is centered rather
+    /// than left-aligned.
+    pub center_content: bool,
+    /// Whether code blocks span the element's full width instead of being
+    /// capped by `max_content_width` along with the text.
+    pub full_width_code_blocks: bool,
+    /// Report hovered footnote references and their definitions to the callback
+    /// registered with [`Markdown::on_footnote_hover`].
+    pub footnote_hover_preview: bool,
+    /// Multiplies all font sizes and rem-based spacing, e.g. to zoom a document.
+    pub font_scale: f32,
+    pub table_border_color: Hsla,
+    pub table_header_background_color: Option<Hsla>,
+    /// Render a bar above fenced code blocks with their language and a copy button.
+    pub code_block_header: bool,
+    /// Soft-wrap long lines in code blocks at the width of the block instead of
+    /// letting them overflow it.
+    pub wrap_code_blocks: bool,
+    /// Strip the leading whitespace common to all non-blank lines of each code
+    /// block. Copying the block's text yields it dedented, as shown, while the
+    /// header's copy button still copies the source.
+    pub dedent_code_blocks: bool,
+    /// The number of spaces each tab in a code block is rendered as. Copying the
+    /// block's text yields the spaces, while the header's copy button copies the
+    /// tabs from the source.
+    pub code_tab_size: usize,
+    /// Hide soft hyphens (`&shy;`), painting a hyphen only where a line wraps at
+    /// one. Lines wrap after soft hyphens, and never at non-breaking spaces,
+    /// either way.
+    pub soft_hyphens: bool,
+    /// Render front matter as a collapsible block showing its source, instead of
+    /// hiding it.
+    pub show_metadata: bool,
+    pub image_max_width: Option<Pixels>,
+    /// The most bytes fetched for each remote image. Larger images aren't
+    /// downloaded, and show a placeholder that loads them when clicked.
+    pub image_max_bytes: Option<usize>,
+    /// The color that content fades into where it's cut off by
+    /// [`Markdown::max_height`], usually the background behind the markdown.
+    pub truncation_fade_color: Hsla,
+    /// Show the destination of the hovered link in a tooltip.
+    pub link_tooltips: bool,
+    /// How long the mouse has to rest on a link before its tooltip is shown.
+    pub link_tooltip_delay: Duration,
+    /// The line drawn through `~~struck~~` text. Without a color, it matches the text.
+    pub strikethrough: StrikethroughStyle,
+    /// The font sizes of headings, from `#` to `######`.
+    pub heading_sizes: [Rems; 6],
+    /// The bullets of unordered list items, cycled through by list nesting depth.
+    pub unordered_bullets: Vec<SharedString>,
+    /// The space below paragraphs and other blocks, like code blocks and tables.
+    pub paragraph_spacing: Rems,
+    /// The space below headings.
+    pub heading_spacing: Rems,
+    /// The space below each list item.
+    pub list_item_spacing: Rems,
+    /// The line height of paragraphs and list items. Headings scale it by their
+    /// font size relative to the root font size.
+    pub line_height: Rems,
+    /// Render a few inline HTML tags, like `<br>`, `<b>`, and `<code>`, as the
+    /// formatting they stand for. Other HTML is shown verbatim.
+    pub render_inline_html: bool,
+    /// Render straight quotes as curly quotes, `--` and `---` as en and em
+    /// dashes, and `...` as an ellipsis.
+    pub smart_punctuation: bool,
+    /// Parse `$...$` and `$$...$$` spans as TeX formulas, which are rendered
+    /// with [`Markdown::math_renderer`].
+    pub math: bool,
+}
+
+impl MarkdownStyle {
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            smart_punctuation: self.smart_punctuation,
+            math: self.math,
+        }
+    }
+
+    fn callout_color(&self, kind: CalloutKind) -> Hsla {
+        match kind {
+            CalloutKind::Note => self.callout_note_color,
+            CalloutKind::Tip => self.callout_tip_color,
+            CalloutKind::Important => self.callout_important_color,
+            CalloutKind::Warning => self.callout_warning_color,
+            CalloutKind::Caution => self.callout_caution_color,
+        }
+    }
+
+    fn html_element_text_style(&self, element: HtmlElement) -> TextStyleRefinement {
+        match element {
+            HtmlElement::Bold => TextStyleRefinement {
+                font_weight: Some(FontWeight::BOLD),
+                ..Default::default()
+            },
+            HtmlElement::Italic => TextStyleRefinement {
+                font_style: Some(FontStyle::Italic),
+                ..Default::default()
+            },
+            HtmlElement::Code => self.inline_code.clone(),
+            // Text can't be raised or lowered off the baseline, so it's only shrunk.
+            HtmlElement::Subscript | HtmlElement::Superscript => TextStyleRefinement {
+                font_size: Some(rems(0.75).into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn nested_block_quote_border_color(&self, depth: usize) -> Hsla {
+        cycle(&self.block_quote_border_colors, depth).unwrap_or(self.block_quote_border_color)
+    }
+
+    fn block_quote_text_style(&self, depth: usize) -> TextStyleRefinement {
+        let mut style = self.block_quote.clone();
+        if let Some(color) = cycle(&self.block_quote_text_colors, depth) {
+            style.color = Some(color);
+        }
+        style
+    }
+
+    fn strikethrough_text_style(&self) -> TextStyleRefinement {
+        TextStyleRefinement {
+            strikethrough: Some(self.strikethrough),
+            ..Default::default()
+        }
+    }
+}
+
+fn cycle(colors: &[Hsla], depth: usize) -> Option<Hsla> {
+    (!colors.is_empty()).then(|| colors[depth % colors.len()])
+}
+
+/// Where an ellipsis is placed when truncating text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EllipsisMode {
+    /// Keep the end of the text, e.g. `…/path/to/file`.
+    Start,
+    /// Keep both ends of the text, e.g. `https://host/…/file`.
+    #[default]
+    Middle,
+    /// Keep the start of the text, e.g. `A long sentenc…`.
+    End,
+}
+
+impl Default for MarkdownStyle {
+    fn default() -> Self {
+        Self {
+            code_block: Default::default(),
+            inline_code: Default::default(),
+            block_quote: Default::default(),
+            link: Default::default(),
+            footnote: Default::default(),
+            inline_code_language: None,
+            inline_code_padding: Pixels::ZERO,
+            rule_color: Default::default(),
+            block_quote_border_color: Default::default(),
+            block_quote_border_colors: Vec::new(),
+            block_quote_text_colors: Vec::new(),
+            block_quote_hanging_indent: None,
+            callout_note_color: gpui::blue(),
+            callout_tip_color: gpui::green(),
+            callout_important_color: hsla(0.75, 0.6, 0.6, 1.),
+            callout_warning_color: gpui::yellow(),
+            callout_caution_color: gpui::red(),
+            callout_background_opacity: 0.1,
+            syntax: Arc::new(SyntaxTheme::default()),
+            selection_background_color: Default::default(),
+            selection_text_color: None,
+            hovered_line_background: None,
+            cursor_color: Default::default(),
+            search_match_background: Default::default(),
+            number_headings: false,
+            copy_heading_numbers: false,
+            external_link_icon: None,
+            collapsible_headings: false,
+            max_link_len: None,
+            link_ellipsis: EllipsisMode::default(),
+            content_padding: Edges::default(),
+            full_bleed_blocks: false,
+            max_content_width: None,
+            center_content: false,
+            full_width_code_blocks: false,
+            footnote_hover_preview: false,
+            font_scale: 1.,
+            table_border_color: Default::default(),
+            table_header_background_color: None,
+            code_block_header: false,
+            wrap_code_blocks: false,
+            dedent_code_blocks: false,
+            code_tab_size: 4,
+            soft_hyphens: false,
+            show_metadata: false,
+            image_max_width: None,
+            image_max_bytes: None,
+            truncation_fade_color: Default::default(),
+            link_tooltips: false,
+            link_tooltip_delay: Duration::from_millis(500),
+            strikethrough: StrikethroughStyle {
+                thickness: px(1.),
+                color: None,
+            },
+            unordered_bullets: vec!["•".into()],
+            heading_sizes: [
+                rems(1.875),
+                rems(1.5),
+                rems(1.25),
+                rems(1.125),
+                rems(1.),
+                rems(0.875),
+            ],
+            paragraph_spacing: rems(0.5),
+            heading_spacing: rems(0.5),
+            list_item_spacing: rems(0.5),
+            line_height: rems(1.3),
+            render_inline_html: false,
+            smart_punctuation: false,
+            math: false,
+        }
+    }
 }
 
 pub struct Markdown {
@@ -37,11 +328,125 @@ pub struct Markdown {
     parsed_markdown: ParsedMarkdown,
     should_reparse: bool,
     pending_parse: Option<Task<Option<()>>>,
+    /// Incremented for every parse, so that a parse that completes after a newer
+    /// one was started is discarded.
+    parse_generation: usize,
     focus_handle: FocusHandle,
     language_registry: Option<Arc<LanguageRegistry>>,
+    /// Code block language names mapped to the languages they should load.
+    language_aliases: HashMap<String, String>,
+    rendered_text: Option<RenderedText>,
+    event_transform: Option<EventTransform>,
+    synchronous: bool,
+    /// Source ranges of the headings whose sections are collapsed.
+    collapsed_headings: HashSet<Range<usize>>,
+    /// Whether the front matter shown with `show_metadata` is collapsed.
+    metadata_collapsed: bool,
+    tasks_interactive: bool,
+    on_source_changed: Option<Box<dyn Fn(&str, &mut ViewContext<Self>)>>,
+    on_toggle_task: Option<Rc<dyn Fn(Range<usize>, bool)>>,
+    on_open_url: Option<Box<dyn Fn(&str, &mut ViewContext<Self>)>>,
+    on_link_clicked: Option<Box<dyn Fn(&str, Range<usize>, &mut ViewContext<Self>)>>,
+    math_renderer: Option<Rc<dyn Fn(&str, bool) -> AnyElement>>,
+    code_block_decorator: Option<Rc<dyn Fn(&str, &str, Div) -> Div>>,
+    /// Fenced code block languages mapped to the renderers that replace those blocks.
+    code_block_renderers: HashMap<String, CodeBlockRenderer>,
+    hovered_footnote: Option<Range<usize>>,
+    on_footnote_hover: Option<Box<dyn Fn(Option<FootnoteHover>, &mut ViewContext<Self>)>>,
+    on_selection_changed: Option<Box<dyn Fn(Option<Range<usize>>, &mut ViewContext<Self>)>>,
+    /// The selected range last passed to `on_selection_changed`.
+    reported_selection: Option<Range<usize>>,
+    max_height: Option<Rems>,
+    expanded: bool,
+    hovered_link: Option<RenderedLink>,
+    /// The tooltip of the hovered link and where it's anchored, once shown.
+    link_tooltip: Option<(AnyView, Point<Pixels>)>,
+    link_tooltip_task: Option<Task<()>>,
+    /// Extends the pending selection while a drag is held at the edge of the text.
+    drag_autoscroll: Option<(AutoscrollDirection, Task<()>)>,
+    search_query: String,
+    search_case_sensitive: bool,
+    /// The source ranges of the rendered text matching `search_query`.
+    search_matches: Vec<Range<usize>>,
+    active_search_match: Option<usize>,
+    /// Whether the caret is in the visible phase of its blink.
+    cursor_visible: bool,
+    cursor_blink_task: Option<Task<()>>,
+    /// Where the caret was last painted, while it's shown.
+    cursor_bounds: Option<Bounds<Pixels>>,
+    /// The source range of the rendered line under the mouse, tracked while
+    /// `hovered_line_background` is set.
+    hovered_line: Option<Range<usize>>,
+    /// Where the hovered line's background was last painted.
+    hovered_line_bounds: Option<Bounds<Pixels>>,
+    /// Where the padded backgrounds of inline code were last painted.
+    inline_code_background_bounds: Vec<Bounds<Pixels>>,
+    context_menu: Option<MarkdownContextMenu>,
+    /// The remote images fetched under `image_max_bytes`, by URL.
+    remote_images: HashMap<SharedString, RemoteImage>,
+}
+
+/// A remote image fetched by [`Markdown`] rather than `img`, so that its size
+/// can be checked against [`MarkdownStyle::image_max_bytes`] first.
+enum RemoteImage {
+    Loading {
+        _fetch: Task<()>,
+    },
+    Loaded(Arc<ImageData>),
+    /// Larger than `image_max_bytes`, so it's only loaded once clicked.
+    TooLarge,
+    /// Left to `img` to fetch in full, because it was clicked to load despite
+    /// its size or isn't in a raster format.
+    Uncapped,
+    Failed,
+}
+
+/// The menu deployed by right-clicking a selection or a link.
+struct MarkdownContextMenu {
+    menu: View<ContextMenu>,
+    position: Point<Pixels>,
+    entries: Vec<ContextMenuEntry>,
+    _subscription: Subscription,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum ContextMenuEntry {
+    Copy,
+    CopyAsMarkdown,
+    /// Opens the link with the given destination, as if it was clicked.
+    OpenLink(SharedString),
+}
+
+/// A footnote reference under the mouse, as reported to [`Markdown::on_footnote_hover`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FootnoteHover {
+    pub label: SharedString,
+    /// The rendered text of the footnote's definition.
+    pub definition: String,
+    /// The rectangles the reference was painted in, one per visual row.
+    pub reference_bounds: Vec<Bounds<Pixels>>,
 }
 
-actions!(markdown, [Copy]);
+type EventTransform = Arc<
+    dyn Fn(Vec<(Range<usize>, MarkdownEvent)>) -> Vec<(Range<usize>, MarkdownEvent)> + Send + Sync,
+>;
+
+type CodeBlockRenderer = Rc<dyn Fn(&str, Range<usize>, &mut ViewContext<Markdown>) -> AnyElement>;
+
+actions!(
+    markdown,
+    [
+        Copy,
+        CopyAsMarkdown,
+        SelectAll,
+        SelectLeft,
+        SelectRight,
+        SelectUp,
+        SelectDown,
+        SelectToBeginningOfLine,
+        SelectToEndOfLine
+    ]
+);
 
 impl Markdown {
     pub fn new(
@@ -49,6 +454,31 @@ impl Markdown {
         style: MarkdownStyle,
         language_registry: Option<Arc<LanguageRegistry>>,
         cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self::new_internal(source, style, language_registry, false, cx)
+    }
+
+    /// Like [`Markdown::new`], but parses on the calling thread instead of the
+    /// background executor, so the parsed result is available immediately.
+    ///
+    /// Parsing large documents this way blocks the caller, so prefer
+    /// [`Markdown::new`] for anything but short snippets, tests, or
+    /// environments without a background executor.
+    pub fn new_synchronous(
+        source: String,
+        style: MarkdownStyle,
+        language_registry: Option<Arc<LanguageRegistry>>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self::new_internal(source, style, language_registry, true, cx)
+    }
+
+    fn new_internal(
+        source: String,
+        style: MarkdownStyle,
+        language_registry: Option<Arc<LanguageRegistry>>,
+        synchronous: bool,
+        cx: &mut ViewContext<Self>,
     ) -> Self {
         let focus_handle = cx.focus_handle();
         let mut this = Self {
@@ -60,8 +490,45 @@ impl Markdown {
             should_reparse: false,
             parsed_markdown: ParsedMarkdown::default(),
             pending_parse: None,
+            parse_generation: 0,
             focus_handle,
             language_registry,
+            language_aliases: HashMap::default(),
+            rendered_text: None,
+            event_transform: None,
+            synchronous,
+            collapsed_headings: HashSet::default(),
+            metadata_collapsed: false,
+            tasks_interactive: false,
+            on_source_changed: None,
+            on_toggle_task: None,
+            on_open_url: None,
+            on_link_clicked: None,
+            math_renderer: None,
+            code_block_decorator: None,
+            code_block_renderers: HashMap::default(),
+            hovered_footnote: None,
+            on_footnote_hover: None,
+            on_selection_changed: None,
+            reported_selection: None,
+            max_height: None,
+            expanded: false,
+            hovered_link: None,
+            link_tooltip: None,
+            link_tooltip_task: None,
+            drag_autoscroll: None,
+            search_query: String::new(),
+            search_case_sensitive: false,
+            search_matches: Vec::new(),
+            active_search_match: None,
+            cursor_visible: true,
+            cursor_blink_task: None,
+            cursor_bounds: None,
+            hovered_line: None,
+            hovered_line_bounds: None,
+            inline_code_background_bounds: Vec::new(),
+            context_menu: None,
+            remote_images: HashMap::default(),
         };
         this.parse(cx);
         this
@@ -76,12 +543,56 @@ impl Markdown {
         if source == self.source() {
             return;
         }
+        // Keep the selection if the edit only changed text after it.
+        let common_prefix_len = self
+            .source
+            .bytes()
+            .zip(source.bytes())
+            .take_while(|(old, new)| old == new)
+            .count();
+        if self.selection.end > common_prefix_len {
+            self.selection = Selection::default();
+            self.report_selection(cx);
+        }
+        // Likewise keep headings collapsed if the edit didn't touch them,
+        // shifting those after it by the change in length.
+        let common_suffix_len = self.source.as_bytes()[common_prefix_len..]
+            .iter()
+            .rev()
+            .zip(source.as_bytes()[common_prefix_len..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let old_suffix_start = self.source.len() - common_suffix_len;
+        let new_suffix_start = source.len() - common_suffix_len;
+        self.collapsed_headings = mem::take(&mut self.collapsed_headings)
+            .into_iter()
+            .filter_map(|range| {
+                if range.end < common_prefix_len {
+                    Some(range)
+                } else if range.start > old_suffix_start {
+                    Some(
+                        range.start - old_suffix_start + new_suffix_start
+                            ..range.end - old_suffix_start + new_suffix_start,
+                    )
+                } else {
+                    None
+                }
+            })
+            .collect();
         self.source = source;
-        self.selection = Selection::default();
         self.autoscroll_request = None;
         self.pending_parse = None;
+        self.parse_generation += 1;
         self.should_reparse = false;
-        self.parsed_markdown = ParsedMarkdown::default();
+        // The previous document stays rendered until the edit is parsed, and
+        // its events are reused for the blocks the edit didn't touch.
+        if self.source.is_empty() {
+            self.parsed_markdown = ParsedMarkdown::default();
+        }
+        self.rendered_text = None;
+        self.hovered_line = None;
+        self.metadata_collapsed = false;
+        self.expanded = false;
         self.parse(cx);
     }
 
@@ -89,954 +600,6423 @@ impl Markdown {
         &self.source
     }
 
-    fn copy(&self, text: &RenderedText, cx: &mut ViewContext<Self>) {
-        let text = text.text_for_range(self.selection.start..self.selection.end);
-        cx.write_to_clipboard(ClipboardItem::new(text));
+    /// Sets a function that post-processes the parsed events before they're
+    /// rendered, e.g. to drop images or demote headings. It runs on a background
+    /// thread, and the ranges it returns must remain valid offsets into the source.
+    pub fn set_event_transform(
+        &mut self,
+        transform: impl Fn(Vec<(Range<usize>, MarkdownEvent)>) -> Vec<(Range<usize>, MarkdownEvent)>
+            + Send
+            + Sync
+            + 'static,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.event_transform = Some(Arc::new(transform));
+        self.parse(cx);
     }
 
-    fn parse(&mut self, cx: &mut ViewContext<Self>) {
-        if self.source.is_empty() {
-            return;
-        }
+    /// Loads the given code block languages ahead of time, so that documents using
+    /// them are highlighted on first paint instead of after loading completes.
+    pub fn preload_languages(&self, names: &[&str], cx: &mut ViewContext<Self>) -> Task<()> {
+        let Some(language_registry) = self.language_registry.as_ref() else {
+            return Task::ready(());
+        };
 
-        if self.pending_parse.is_some() {
-            self.should_reparse = true;
-            return;
-        }
+        let languages = names
+            .iter()
+            .map(|name| language_registry.language_for_name(self.resolve_language_alias(name)))
+            .collect::<Vec<_>>();
+        cx.spawn(|this, mut cx| async move {
+            for language in future::join_all(languages).await {
+                language.log_err();
+            }
+            this.update(&mut cx, |_, cx| cx.notify()).ok();
+        })
+    }
 
-        let text = self.source.clone();
-        let parsed = cx.background_executor().spawn(async move {
-            let text = SharedString::from(text);
-            let events = Arc::from(parse_markdown(text.as_ref()));
-            anyhow::Ok(ParsedMarkdown {
-                source: text,
-                events,
-            })
-        });
+    /// Maps code block language names, e.g. `zsh` or `js`, to the names of the
+    /// languages used to highlight them, e.g. `bash` or `javascript`.
+    pub fn set_language_aliases(
+        &mut self,
+        aliases: HashMap<String, String>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.language_aliases = aliases;
+        cx.notify();
+    }
 
-        self.should_reparse = false;
-        self.pending_parse = Some(cx.spawn(|this, mut cx| {
-            async move {
-                let parsed = parsed.await?;
-                this.update(&mut cx, |this, cx| {
-                    this.parsed_markdown = parsed;
-                    this.pending_parse.take();
-                    if this.should_reparse {
-                        this.parse(cx);
-                    }
-                    cx.notify();
-                })
-                .ok();
-                anyhow::Ok(())
-            }
-            .log_err()
-        }));
+    fn resolve_language_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.language_aliases.get(name).map_or(name, String::as_str)
     }
-}
 
-impl Render for Markdown {
-    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        MarkdownElement::new(
-            cx.view().clone(),
-            self.style.clone(),
-            self.language_registry.clone(),
-        )
+    /// Scales the font size of the whole document, see [`MarkdownStyle::font_scale`].
+    pub fn set_font_scale(&mut self, font_scale: f32, cx: &mut ViewContext<Self>) {
+        self.style.font_scale = font_scale;
+        cx.notify();
     }
-}
 
-impl FocusableView for Markdown {
-    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
-        self.focus_handle.clone()
+    /// Sets whether clicking a task list checkbox toggles it in the source.
+    pub fn set_tasks_interactive(&mut self, interactive: bool, cx: &mut ViewContext<Self>) {
+        self.tasks_interactive = interactive;
+        cx.notify();
     }
-}
 
-#[derive(Copy, Clone, Default, Debug)]
-struct Selection {
-    start: usize,
-    end: usize,
-    reversed: bool,
-    pending: bool,
-}
+    /// Registers a callback that's invoked with the new source whenever it's
+    /// edited from the rendered markdown, e.g. by toggling a task.
+    pub fn on_source_changed(&mut self, callback: impl Fn(&str, &mut ViewContext<Self>) + 'static) {
+        self.on_source_changed = Some(Box::new(callback));
+    }
 
-impl Selection {
-    fn set_head(&mut self, head: usize) {
-        if head < self.tail() {
-            if !self.reversed {
-                self.end = self.start;
-                self.reversed = true;
-            }
-            self.start = head;
-        } else {
-            if self.reversed {
-                self.start = self.end;
-                self.reversed = false;
-            }
-            self.end = head;
-        }
+    /// Registers a callback that's invoked with the source range of a task list
+    /// item and its new checked state whenever its checkbox is clicked.
+    /// Checkboxes are clickable while a callback is registered, even if tasks
+    /// aren't interactive.
+    pub fn on_toggle_task(&mut self, callback: impl Fn(Range<usize>, bool) + 'static) {
+        self.on_toggle_task = Some(Rc::new(callback));
     }
 
-    fn tail(&self) -> usize {
-        if self.reversed {
-            self.end
-        } else {
-            self.start
-        }
+    /// Registers a handler for clicked links, which is invoked with the link's
+    /// exact destination instead of opening it with the system.
+    pub fn on_open_url(&mut self, handler: impl Fn(&str, &mut ViewContext<Self>) + 'static) {
+        self.on_open_url = Some(Box::new(handler));
     }
-}
 
-#[derive(Clone)]
-struct ParsedMarkdown {
-    source: SharedString,
-    events: Arc<[(Range<usize>, MarkdownEvent)]>,
-}
+    /// Registers a handler for clicked links, which is invoked with the link's
+    /// destination and the source range of the whole link instead of opening it.
+    /// Takes precedence over [`Markdown::on_open_url`].
+    pub fn on_link_clicked(
+        &mut self,
+        handler: impl Fn(&str, Range<usize>, &mut ViewContext<Self>) + 'static,
+    ) {
+        self.on_link_clicked = Some(Box::new(handler));
+    }
 
-impl Default for ParsedMarkdown {
-    fn default() -> Self {
-        Self {
-            source: SharedString::default(),
-            events: Arc::from([]),
-        }
+    /// Renders `$...$` and `$$...$$` TeX formulas with the given function, which
+    /// is passed the TeX and whether the formula is displayed on its own. Without
+    /// a renderer, the TeX is shown like inline code. Formulas are only parsed
+    /// when [`MarkdownStyle::math`] is set.
+    pub fn math_renderer(&mut self, renderer: impl Fn(&str, bool) -> AnyElement + 'static) {
+        self.math_renderer = Some(Rc::new(renderer));
     }
-}
 
-pub struct MarkdownElement {
-    markdown: View<Markdown>,
-    style: MarkdownStyle,
-    language_registry: Option<Arc<LanguageRegistry>>,
-}
+    /// Restyles the container of each fenced code block with the given function,
+    /// which is passed the block's language and the metadata following it in the
+    /// info string, e.g. `{1,3-4}` for ```` ```js {1,3-4} ````.
+    pub fn code_block_decorator(&mut self, decorator: impl Fn(&str, &str, Div) -> Div + 'static) {
+        self.code_block_decorator = Some(Rc::new(decorator));
+    }
 
-impl MarkdownElement {
-    fn new(
-        markdown: View<Markdown>,
-        style: MarkdownStyle,
-        language_registry: Option<Arc<LanguageRegistry>>,
-    ) -> Self {
-        Self {
-            markdown,
-            style,
-            language_registry,
+    /// Renders fenced code blocks of the given language, e.g. ```` ```run ````, with
+    /// the given function instead of as highlighted code. It's passed the code
+    /// inside the fences and the source range of the whole block.
+    pub fn register_code_block_renderer(
+        &mut self,
+        language: &str,
+        renderer: impl Fn(&str, Range<usize>, &mut ViewContext<Self>) -> AnyElement + 'static,
+    ) {
+        self.code_block_renderers
+            .insert(language.to_string(), Rc::new(renderer));
+    }
+
+    /// Registers a callback that's invoked when the mouse enters (`Some`) or
+    /// leaves (`None`) a footnote reference, if `footnote_hover_preview` is set.
+    pub fn on_footnote_hover(
+        &mut self,
+        callback: impl Fn(Option<FootnoteHover>, &mut ViewContext<Self>) + 'static,
+    ) {
+        self.on_footnote_hover = Some(Box::new(callback));
+    }
+
+    /// Registers a callback that's invoked with the selected source range whenever
+    /// it changes, or with `None` once nothing is selected.
+    pub fn on_selection_changed(
+        &mut self,
+        handler: impl Fn(Option<Range<usize>>, &mut ViewContext<Self>) + 'static,
+    ) {
+        self.on_selection_changed = Some(Box::new(handler));
+    }
+
+    /// Invokes the `on_selection_changed` callback if the selected range differs
+    /// from the one it was last invoked with.
+    fn report_selection(&mut self, cx: &mut ViewContext<Self>) {
+        let range = (self.selection.start < self.selection.end)
+            .then(|| self.selection.start..self.selection.end);
+        if range == self.reported_selection {
+            return;
+        }
+        self.reported_selection = range.clone();
+        if let Some(on_selection_changed) = self.on_selection_changed.as_ref() {
+            on_selection_changed(range, cx);
         }
     }
 
-    fn load_language(&self, name: &str, cx: &mut WindowContext) -> Option<Arc<Language>> {
-        let language = self
-            .language_registry
-            .as_ref()?
-            .language_for_name(name)
-            .map(|language| language.ok())
-            .shared();
+    /// Caps the rendered height, fading out the content beyond it behind a
+    /// "Show more" toggle until the markdown is expanded.
+    pub fn max_height(&mut self, max_height: Rems, cx: &mut ViewContext<Self>) {
+        self.max_height = Some(max_height);
+        cx.notify();
+    }
 
-        match language.clone().now_or_never() {
-            Some(language) => language,
-            None => {
-                let markdown = self.markdown.downgrade();
-                cx.spawn(|mut cx| async move {
-                    language.await;
-                    markdown.update(&mut cx, |_, cx| cx.notify())
+    /// Whether all of the content is shown despite [`Markdown::max_height`].
+    pub fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    pub fn set_expanded(&mut self, expanded: bool, cx: &mut ViewContext<Self>) {
+        self.expanded = expanded;
+        cx.notify();
+    }
+
+    /// Fetches a remote image, unless it's larger than `max_bytes`.
+    fn fetch_remote_image(
+        &mut self,
+        url: SharedString,
+        max_bytes: usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let fetch = cx.background_executor().spawn(fetch_capped_image(
+            cx.http_client(),
+            url.clone(),
+            max_bytes,
+        ));
+        let task = cx.spawn({
+            let url = url.clone();
+            |this, mut cx| async move {
+                let image = fetch.await.log_err().unwrap_or(RemoteImage::Failed);
+                this.update(&mut cx, |this, cx| {
+                    this.remote_images.insert(url, image);
+                    cx.notify();
                 })
-                .detach_and_log_err(cx);
-                None
+                .ok();
             }
+        });
+        self.remote_images
+            .insert(url, RemoteImage::Loading { _fetch: task });
+    }
+
+    /// Loads a remote image that's larger than `image_max_bytes` anyway.
+    fn load_remote_image_in_full(&mut self, url: SharedString, cx: &mut ViewContext<Self>) {
+        self.remote_images.insert(url, RemoteImage::Uncapped);
+        cx.notify();
+    }
+
+    /// Toggles the task list marker (`[ ]` or `[x]`) at the given source range,
+    /// which belongs to the list item at `item_range`.
+    fn toggle_task(
+        &mut self,
+        marker_range: Range<usize>,
+        item_range: Range<usize>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let checked_ix = marker_range.start + 1;
+        let (replacement, checked) = match self.source.get(marker_range) {
+            Some("[ ]") => ("x", true),
+            Some("[x]" | "[X]") => (" ", false),
+            // The source changed since the checkbox was rendered.
+            _ => return,
+        };
+        self.source
+            .replace_range(checked_ix..checked_ix + 1, replacement);
+        self.parse(cx);
+        if let Some(on_source_changed) = self.on_source_changed.as_ref() {
+            on_source_changed(&self.source, cx);
+        }
+        if let Some(on_toggle_task) = self.on_toggle_task.clone() {
+            on_toggle_task(item_range, checked);
         }
+        cx.notify();
     }
 
-    fn paint_selection(
+    /// Hides the tooltip of the previously hovered link and shows one for the
+    /// newly hovered link at `position` after `delay`.
+    fn update_link_tooltip(
         &mut self,
-        bounds: Bounds<Pixels>,
-        rendered_text: &RenderedText,
-        cx: &mut WindowContext,
+        position: Point<Pixels>,
+        delay: Duration,
+        cx: &mut ViewContext<Self>,
     ) {
-        let selection = self.markdown.read(cx).selection;
-        let selection_start = rendered_text.position_for_source_index(selection.start);
-        let selection_end = rendered_text.position_for_source_index(selection.end);
+        if self.link_tooltip.take().is_some() {
+            cx.notify();
+        }
+        self.link_tooltip_task = self.hovered_link.as_ref().map(|link| {
+            let destination_url = link.destination_url.clone();
+            cx.spawn(|this, mut cx| async move {
+                cx.background_executor().timer(delay).await;
+                this.update(&mut cx, |this, cx| {
+                    this.link_tooltip = Some((LinkPreview::new(&destination_url, cx), position));
+                    cx.notify();
+                })
+                .ok();
+            })
+        });
+    }
 
-        if let Some(((start_position, start_line_height), (end_position, end_line_height))) =
-            selection_start.zip(selection_end)
-        {
-            if start_position.y == end_position.y {
-                cx.paint_quad(quad(
-                    Bounds::from_corners(
-                        start_position,
-                        point(end_position.x, end_position.y + end_line_height),
-                    ),
-                    Pixels::ZERO,
-                    self.style.selection_background_color,
-                    Edges::default(),
-                    Hsla::transparent_black(),
-                ));
-            } else {
-                cx.paint_quad(quad(
-                    Bounds::from_corners(
-                        start_position,
-                        point(bounds.right(), start_position.y + start_line_height),
-                    ),
-                    Pixels::ZERO,
-                    self.style.selection_background_color,
-                    Edges::default(),
-                    Hsla::transparent_black(),
-                ));
-
-                if end_position.y > start_position.y + start_line_height {
-                    cx.paint_quad(quad(
-                        Bounds::from_corners(
-                            point(bounds.left(), start_position.y + start_line_height),
-                            point(bounds.right(), end_position.y),
-                        ),
-                        Pixels::ZERO,
-                        self.style.selection_background_color,
-                        Edges::default(),
-                        Hsla::transparent_black(),
-                    ));
-                }
+    /// Shows a menu at `position` for copying the selection, if there is one, and
+    /// for opening `link`, if the menu was deployed over one.
+    fn deploy_context_menu(
+        &mut self,
+        position: Point<Pixels>,
+        link: Option<RenderedLink>,
+        rendered_text: RenderedText,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let mut entries = Vec::new();
+        if self.selection.start != self.selection.end {
+            entries.extend([ContextMenuEntry::Copy, ContextMenuEntry::CopyAsMarkdown]);
+        }
+        if let Some(link) = link.as_ref() {
+            entries.push(ContextMenuEntry::OpenLink(link.destination_url.clone()));
+        }
+        if entries.is_empty() {
+            return;
+        }
 
-                cx.paint_quad(quad(
-                    Bounds::from_corners(
-                        point(bounds.left(), end_position.y),
-                        point(end_position.x, end_position.y + end_line_height),
+        let view = cx.view().downgrade();
+        let menu = ContextMenu::build(cx, |mut menu, _| {
+            for entry in entries.iter() {
+                let view = view.clone();
+                let rendered_text = rendered_text.clone();
+                menu = match entry {
+                    ContextMenuEntry::Copy => {
+                        menu.entry("Copy", Some(Box::new(crate::Copy)), move |cx| {
+                            view.update(cx, |markdown, cx| markdown.copy(&rendered_text, cx))
+                                .ok();
+                        })
+                    }
+                    ContextMenuEntry::CopyAsMarkdown => menu.entry(
+                        "Copy as Markdown",
+                        Some(Box::new(CopyAsMarkdown)),
+                        move |cx| {
+                            view.update(cx, |markdown, cx| markdown.copy_as_markdown(cx))
+                                .ok();
+                        },
                     ),
-                    Pixels::ZERO,
-                    self.style.selection_background_color,
-                    Edges::default(),
-                    Hsla::transparent_black(),
-                ));
+                    ContextMenuEntry::OpenLink(_) => {
+                        let link = link.clone().unwrap();
+                        menu.entry("Open Link", None, move |cx| {
+                            view.update(cx, |markdown, cx| {
+                                markdown.open_link(&link, &rendered_text, cx)
+                            })
+                            .ok();
+                        })
+                    }
+                };
             }
-        }
+            menu
+        });
+
+        let menu_focus = menu.focus_handle(cx);
+        cx.focus(&menu_focus);
+        let _subscription = cx.subscribe(&menu, move |this, menu, _: &DismissEvent, cx| {
+            // A right-click while the menu is open dismisses it and deploys a new one.
+            if this
+                .context_menu
+                .as_ref()
+                .map_or(false, |context_menu| context_menu.menu == menu)
+            {
+                this.context_menu = None;
+            }
+            if menu_focus.contains_focused(cx) {
+                cx.focus(&this.focus_handle);
+            }
+            cx.notify();
+        });
+        self.context_menu = Some(MarkdownContextMenu {
+            menu,
+            position,
+            entries,
+            _subscription,
+        });
+        cx.notify();
     }
 
-    fn paint_mouse_listeners(
+    /// Follows a clicked link: footnote references scroll to their definition,
+    /// other links go to the registered handlers or are opened directly.
+    fn open_link(
         &mut self,
-        hitbox: &Hitbox,
+        link: &RenderedLink,
         rendered_text: &RenderedText,
-        cx: &mut WindowContext,
+        cx: &mut ViewContext<Self>,
     ) {
-        let is_hovering_link = hitbox.is_hovered(cx)
-            && !self.markdown.read(cx).selection.pending
-            && rendered_text
-                .link_for_position(cx.mouse_position())
-                .is_some();
-
-        if is_hovering_link {
-            cx.set_cursor_style(CursorStyle::PointingHand, hitbox);
-        } else {
-            cx.set_cursor_style(CursorStyle::IBeam, hitbox);
+        if let Some(label) = link.destination_url.strip_prefix(FOOTNOTE_ANCHOR_PREFIX) {
+            if let Some(definition_range) = rendered_text.footnote_definition_range(label) {
+                self.autoscroll_request = Some(definition_range.start);
+                cx.notify();
+            }
+        } else if let Some(on_link_clicked) = self.on_link_clicked.as_ref() {
+            on_link_clicked(&link.destination_url, link.source_range.clone(), cx);
+        } else if let Some(on_open_url) = self.on_open_url.as_ref() {
+            on_open_url(&link.destination_url, cx);
+        } else if is_openable_url(&link.destination_url) {
+            cx.open_url(&link.destination_url);
         }
+    }
 
-        self.on_mouse_event(cx, {
-            let rendered_text = rendered_text.clone();
-            let hitbox = hitbox.clone();
-            move |markdown, event: &MouseDownEvent, phase, cx| {
-                if hitbox.is_hovered(cx) {
-                    if phase.bubble() {
-                        if let Some(link) = rendered_text.link_for_position(event.position) {
-                            markdown.pressed_link = Some(link.clone());
-                        } else {
-                            let source_index =
-                                match rendered_text.source_index_for_position(event.position) {
-                                    Ok(ix) | Err(ix) => ix,
-                                };
-                            let range = if event.click_count == 2 {
-                                rendered_text.surrounding_word_range(source_index)
-                            } else if event.click_count == 3 {
-                                rendered_text.surrounding_line_range(source_index)
-                            } else {
-                                source_index..source_index
-                            };
-                            markdown.selection = Selection {
-                                start: range.start,
-                                end: range.end,
-                                reversed: false,
-                                pending: true,
-                            };
-                            cx.focus(&markdown.focus_handle);
-                            cx.prevent_default()
-                        }
-
+    /// Starts extending the pending selection a row at a time in `direction`,
+    /// scrolling along with it, or stops if `direction` is `None`.
+    fn set_drag_autoscroll(
+        &mut self,
+        direction: Option<AutoscrollDirection>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if self
+            .drag_autoscroll
+            .as_ref()
+            .map(|(direction, _)| *direction)
+            == direction
+        {
+            return;
+        }
+        self.drag_autoscroll = direction.map(|direction| {
+            let task = cx.spawn(|this, mut cx| async move {
+                loop {
+                    cx.background_executor()
+                        .timer(DRAG_AUTOSCROLL_INTERVAL)
+                        .await;
+                    let result = this.update(&mut cx, |this, cx| {
+                        let Some(text) = this.rendered_text.as_ref() else {
+                            return;
+                        };
+                        let head = match direction {
+                            AutoscrollDirection::Up => {
+                                text.source_index_above(this.selection.head())
+                            }
+                            AutoscrollDirection::Down => {
+                                text.source_index_below(this.selection.head())
+                            }
+                        };
+                        this.selection.set_head(head);
+                        this.report_selection(cx);
+                        this.autoscroll_request = Some(head);
                         cx.notify();
+                    });
+                    if result.is_err() {
+                        break;
                     }
-                } else if phase.capture() {
-                    markdown.selection = Selection::default();
-                    markdown.pressed_link = None;
-                    cx.notify();
                 }
-            }
+            });
+            (direction, task)
         });
-        self.on_mouse_event(cx, {
-            let rendered_text = rendered_text.clone();
-            let hitbox = hitbox.clone();
-            let was_hovering_link = is_hovering_link;
-            move |markdown, event: &MouseMoveEvent, phase, cx| {
-                if phase.capture() {
-                    return;
-                }
+    }
 
-                if markdown.selection.pending {
-                    let source_index = match rendered_text.source_index_for_position(event.position)
-                    {
-                        Ok(ix) | Err(ix) => ix,
-                    };
-                    markdown.selection.set_head(source_index);
-                    markdown.autoscroll_request = Some(source_index);
-                    cx.notify();
-                } else {
-                    let is_hovering_link = hitbox.is_hovered(cx)
-                        && rendered_text.link_for_position(event.position).is_some();
-                    if is_hovering_link != was_hovering_link {
-                        cx.notify();
-                    }
-                }
+    /// Scrolls the nearest scrollable ancestor to reveal the given source offset,
+    /// e.g. a heading's start, on the next frame.
+    pub fn scroll_to_source_index(&mut self, source_index: usize, cx: &mut ViewContext<Self>) {
+        self.autoscroll_request = Some(source_index);
+        cx.notify();
+    }
+
+    /// Scrolls to the heading with the given slug, as returned by
+    /// [`ParsedMarkdown::headings`]. Returns whether such a heading exists.
+    pub fn scroll_to_heading(&mut self, slug: &str, cx: &mut ViewContext<Self>) -> bool {
+        let Some(heading) = self
+            .parsed_markdown
+            .headings()
+            .into_iter()
+            .find(|heading| heading.slug == slug)
+        else {
+            return false;
+        };
+        self.scroll_to_source_index(heading.source_range.start, cx);
+        true
+    }
+
+    /// Highlights the rendered text matching `query`, ignoring case unless
+    /// [`Markdown::set_search_case_sensitive`] is set. Matches are found once the
+    /// markdown is painted, and kept up to date as it changes.
+    pub fn set_search_query(&mut self, query: &str, cx: &mut ViewContext<Self>) {
+        self.search_query = query.to_string();
+        self.active_search_match = None;
+        self.update_search_matches();
+        cx.notify();
+    }
+
+    pub fn set_search_case_sensitive(&mut self, case_sensitive: bool, cx: &mut ViewContext<Self>) {
+        self.search_case_sensitive = case_sensitive;
+        self.update_search_matches();
+        cx.notify();
+    }
+
+    /// The source ranges of the text matching the search query, in order.
+    pub fn search_matches(&self) -> &[Range<usize>] {
+        &self.search_matches
+    }
+
+    /// Selects the next search match, wrapping around, and scrolls to it.
+    pub fn select_next_match(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(last_ix) = self.search_matches.len().checked_sub(1) else {
+            return;
+        };
+        let ix = match self.active_search_match {
+            Some(ix) if ix < last_ix => ix + 1,
+            _ => 0,
+        };
+        self.activate_search_match(ix, cx);
+    }
+
+    /// Selects the previous search match, wrapping around, and scrolls to it.
+    pub fn select_prev_match(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(last_ix) = self.search_matches.len().checked_sub(1) else {
+            return;
+        };
+        let ix = match self.active_search_match {
+            Some(ix) if ix > 0 => ix - 1,
+            _ => last_ix,
+        };
+        self.activate_search_match(ix, cx);
+    }
+
+    fn activate_search_match(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let range = self.search_matches[ix].clone();
+        self.active_search_match = Some(ix);
+        self.selection = Selection {
+            start: range.start,
+            end: range.end,
+            reversed: false,
+            pending: false,
+        };
+        self.report_selection(cx);
+        self.autoscroll_request = Some(range.start);
+        cx.notify();
+    }
+
+    fn update_search_matches(&mut self) {
+        self.search_matches = match self.rendered_text.as_ref() {
+            Some(text) if !self.search_query.is_empty() => {
+                text.find(&self.search_query, self.search_case_sensitive)
             }
-        });
-        self.on_mouse_event(cx, {
-            let rendered_text = rendered_text.clone();
-            move |markdown, event: &MouseUpEvent, phase, cx| {
-                if phase.bubble() {
-                    if let Some(pressed_link) = markdown.pressed_link.take() {
-                        if Some(&pressed_link) == rendered_text.link_for_position(event.position) {
-                            cx.open_url(&pressed_link.destination_url);
-                        }
-                    }
-                } else {
-                    if markdown.selection.pending {
-                        markdown.selection.pending = false;
-                        #[cfg(target_os = "linux")]
-                        {
-                            let text = rendered_text
-                                .text_for_range(markdown.selection.start..markdown.selection.end);
-                            cx.write_to_primary(ClipboardItem::new(text))
-                        }
-                        cx.notify();
-                    }
+            _ => Vec::new(),
+        };
+        if self
+            .active_search_match
+            .map_or(false, |ix| ix >= self.search_matches.len())
+        {
+            self.active_search_match = None;
+        }
+    }
+
+    /// Builds a table of contents listing the document's headings, indented by
+    /// level. Clicking an entry scrolls this markdown to the heading.
+    pub fn table_of_contents_element(&self, cx: &mut ViewContext<Self>) -> AnyElement {
+        let markdown = cx.view().clone();
+        v_flex()
+            .children(self.parsed_markdown.headings().into_iter().enumerate().map(
+                |(ix, heading)| {
+                    let markdown = markdown.clone();
+                    let heading_start = heading.source_range.start;
+                    div()
+                        .id(("markdown-toc-entry", ix))
+                        .pl(rems(0.75 * (heading.level as usize - 1) as f32))
+                        .cursor_pointer()
+                        .child(heading.text)
+                        .on_click(move |_, cx| {
+                            markdown.update(cx, |markdown, cx| {
+                                markdown.scroll_to_source_index(heading_start, cx)
+                            });
+                        })
+                },
+            ))
+            .into_any_element()
+    }
+
+    pub fn parsed_markdown(&self) -> &ParsedMarkdown {
+        &self.parsed_markdown
+    }
+
+    /// Returns the source offset of the text at the given window position, based
+    /// on the last time this markdown was painted.
+    pub fn source_index_at(&self, position: Point<Pixels>) -> Option<usize> {
+        let rendered_text = self.rendered_text.as_ref()?;
+        match rendered_text.source_index_for_position(position) {
+            Ok(ix) | Err(ix) => Some(ix),
+        }
+    }
+
+    /// Returns each link's destination along with the rectangles it was painted
+    /// in (one per visual row). Empty until the markdown is painted.
+    pub fn link_bounds(&self) -> Vec<(SharedString, Vec<Bounds<Pixels>>)> {
+        let Some(rendered_text) = self.rendered_text.as_ref() else {
+            return Vec::new();
+        };
+        rendered_text
+            .links
+            .iter()
+            .map(|link| {
+                (
+                    link.destination_url.clone(),
+                    rendered_text.bounds_for_source_range(link.source_range.clone()),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the rectangles the given source range was painted in, one per
+    /// visual row. `None` until the markdown is painted.
+    pub fn bounds_for_source_range(&self, range: Range<usize>) -> Option<Vec<Bounds<Pixels>>> {
+        let rendered_text = self.rendered_text.as_ref()?;
+        Some(rendered_text.bounds_for_source_range(range))
+    }
+
+    /// Returns the source range and painted bounds of each rendered line. Empty
+    /// until the markdown is painted.
+    pub fn rendered_lines(&self) -> Vec<RenderedLineInfo> {
+        let Some(rendered_text) = self.rendered_text.as_ref() else {
+            return Vec::new();
+        };
+        rendered_text
+            .lines
+            .iter()
+            .map(|line| RenderedLineInfo {
+                source_range: line.source_range(),
+                bounds: line.layout.bounds(),
+                line_height: line.layout.line_height(),
+            })
+            .collect()
+    }
+
+    /// Returns the rendered plain text for the given source range, or for the
+    /// whole document if `range` is `None`. Empty until the markdown is painted.
+    pub fn export_plain_text(&self, range: Option<Range<usize>>) -> String {
+        let Some(rendered_text) = self.rendered_text.as_ref() else {
+            return String::new();
+        };
+        let range = range.unwrap_or(0..self.source.len());
+        rendered_text.text_for_range(range)
+    }
+
+    /// Returns the rendered plain text of the selection, or `None` if nothing
+    /// is selected. Like [`Markdown::export_plain_text`], this is only
+    /// available once the markdown is painted.
+    pub fn selected_text(&self) -> Option<String> {
+        if self.selection.start == self.selection.end {
+            return None;
+        }
+        let rendered_text = self.rendered_text.as_ref()?;
+        Some(rendered_text.text_for_range(self.selection.start..self.selection.end))
+    }
+
+    fn copy(&self, text: &RenderedText, cx: &mut ViewContext<Self>) {
+        let text = text.text_for_range(self.selection.start..self.selection.end);
+        cx.write_to_clipboard(ClipboardItem::new(text));
+    }
+
+    /// Copies the selected source rather than its rendered text, keeping the
+    /// markdown syntax of e.g. list markers and emphasis.
+    fn copy_as_markdown(&self, cx: &mut ViewContext<Self>) {
+        let mut start = self.selection.start.min(self.source.len());
+        while !self.source.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = self.selection.end.clamp(start, self.source.len());
+        while !self.source.is_char_boundary(end) {
+            end += 1;
+        }
+        cx.write_to_clipboard(ClipboardItem::new(self.source[start..end].to_string()));
+    }
+
+    fn select_all(&mut self, text: &RenderedText, cx: &mut ViewContext<Self>) {
+        let end = text.lines.last().map_or(0, |line| line.source_end);
+        self.selection = Selection {
+            start: 0,
+            end,
+            reversed: false,
+            pending: false,
+        };
+        self.report_selection(cx);
+        cx.notify();
+    }
+
+    /// Moves the head of the selection to the source index returned by `move_head`,
+    /// keeping its tail in place.
+    fn move_selection_head(
+        &mut self,
+        text: &RenderedText,
+        move_head: fn(&RenderedText, usize) -> usize,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let head = move_head(text, self.selection.head());
+        self.selection.set_head(head);
+        self.report_selection(cx);
+        self.restart_cursor_blink(cx);
+        cx.notify();
+    }
+
+    /// Shows the caret and keeps it visible for a full blink interval, so that it
+    /// doesn't disappear right after moving.
+    fn restart_cursor_blink(&mut self, cx: &mut ViewContext<Self>) {
+        self.cursor_visible = true;
+        self.cursor_blink_task = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                cx.background_executor().timer(CURSOR_BLINK_INTERVAL).await;
+                let result = this.update(&mut cx, |this, cx| {
+                    this.cursor_visible = !this.cursor_visible;
+                    cx.notify();
+                });
+                if result.is_err() {
+                    break;
                 }
             }
-        });
+        }));
     }
 
-    fn autoscroll(&mut self, rendered_text: &RenderedText, cx: &mut WindowContext) -> Option<()> {
-        let autoscroll_index = self
-            .markdown
-            .update(cx, |markdown, _| markdown.autoscroll_request.take())?;
-        let (position, line_height) = rendered_text.position_for_source_index(autoscroll_index)?;
+    fn stop_cursor_blink(&mut self) {
+        self.cursor_visible = true;
+        self.cursor_blink_task = None;
+    }
 
-        let text_style = cx.text_style();
-        let font_id = cx.text_system().resolve_font(&text_style.font());
-        let font_size = text_style.font_size.to_pixels(cx.rem_size());
-        let em_width = cx
-            .text_system()
-            .typographic_bounds(font_id, font_size, 'm')
-            .unwrap()
-            .size
-            .width;
-        cx.request_autoscroll(Bounds::from_corners(
-            point(position.x - 3. * em_width, position.y - 3. * line_height),
-            point(position.x + 3. * em_width, position.y + 3. * line_height),
-        ));
-        Some(())
+    /// The bounds of the caret shown at an empty selection while the markdown is
+    /// focused, as of the last paint. `None` while no caret is shown.
+    pub fn cursor_bounds(&self) -> Option<Bounds<Pixels>> {
+        self.cursor_bounds
+    }
+
+    /// The bounds of the background painted behind the line under the mouse, as
+    /// of the last paint. `None` unless `hovered_line_background` is set.
+    pub fn hovered_line_bounds(&self) -> Option<Bounds<Pixels>> {
+        self.hovered_line_bounds
+    }
+
+    /// The bounds of the backgrounds painted behind inline code, as of the last
+    /// paint. Empty unless `inline_code_padding` is set.
+    pub fn inline_code_background_bounds(&self) -> &[Bounds<Pixels>] {
+        &self.inline_code_background_bounds
+    }
+
+    fn parse(&mut self, cx: &mut ViewContext<Self>) {
+        if self.source.is_empty() {
+            return;
+        }
+
+        if self.pending_parse.is_some() {
+            self.should_reparse = true;
+            return;
+        }
+
+        let text = self.source.clone();
+        let event_transform = self.event_transform.clone();
+        let previous = self.parsed_markdown.clone();
+        let options = self.style.parse_options();
+        let parse = move || {
+            if let Some(event_transform) = event_transform {
+                let mut parsed = ParsedMarkdown::parse(text.into(), options);
+                parsed.events = Arc::from(event_transform(parsed.events.to_vec()));
+                parsed
+            } else {
+                // Text streamed in with `append`, or edited with `reset`, only
+                // needs the blocks around the change reparsed.
+                previous.edit(text.into(), options)
+            }
+        };
+
+        self.parse_generation += 1;
+        if self.synchronous {
+            self.parsed_markdown = parse();
+            cx.notify();
+            return;
+        }
+
+        let parsed = cx
+            .background_executor()
+            .spawn(async move { anyhow::Ok(parse()) });
+
+        self.should_reparse = false;
+        let generation = self.parse_generation;
+        self.pending_parse = Some(cx.spawn(|this, mut cx| {
+            async move {
+                let parsed = parsed.await?;
+                this.update(&mut cx, |this, cx| {
+                    // The source was reset while this parse was running.
+                    if this.parse_generation != generation {
+                        return;
+                    }
+                    this.parsed_markdown = parsed;
+                    this.pending_parse.take();
+                    if this.should_reparse {
+                        this.parse(cx);
+                    }
+                    cx.notify();
+                })
+                .ok();
+                anyhow::Ok(())
+            }
+            .log_err()
+        }));
+    }
+}
+
+impl Render for Markdown {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        MarkdownElement::new(
+            cx.view().clone(),
+            self.style.clone(),
+            self.language_registry.clone(),
+        )
+    }
+}
+
+impl FocusableView for Markdown {
+    fn focus_handle(&self, _cx: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug)]
+struct Selection {
+    start: usize,
+    end: usize,
+    reversed: bool,
+    pending: bool,
+}
+
+impl Selection {
+    fn set_head(&mut self, head: usize) {
+        if head < self.tail() {
+            if !self.reversed {
+                self.end = self.start;
+                self.reversed = true;
+            }
+            self.start = head;
+        } else {
+            if self.reversed {
+                self.start = self.end;
+                self.reversed = false;
+            }
+            self.end = head;
+        }
+    }
+
+    fn head(&self) -> usize {
+        if self.reversed {
+            self.start
+        } else {
+            self.end
+        }
+    }
+
+    fn tail(&self) -> usize {
+        if self.reversed {
+            self.end
+        } else {
+            self.start
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ParsedMarkdown {
+    source: SharedString,
+    events: Arc<[(Range<usize>, MarkdownEvent)]>,
+    options: ParseOptions,
+}
+
+impl ParsedMarkdown {
+    pub fn new(source: SharedString) -> Self {
+        Self::parse(source, ParseOptions::default())
+    }
+
+    /// Parses `source` with the syntax extensions enabled in `options`.
+    pub fn parse(source: SharedString, options: ParseOptions) -> Self {
+        let events = Arc::from(parse_markdown(source.as_ref(), options));
+        Self {
+            source,
+            events,
+            options,
+        }
+    }
+
+    pub fn source(&self) -> &SharedString {
+        &self.source
+    }
+
+    /// Returns the kind and raw source of the document's front matter, without
+    /// its `---` or `+++` fences, so that embedders can parse it themselves.
+    pub fn metadata(&self) -> Option<(MetadataBlockKind, String)> {
+        let Some((_, MarkdownEvent::Start(MarkdownTag::MetadataBlock(kind)))) = self.events.first()
+        else {
+            return None;
+        };
+        let contents = self.events[1..]
+            .iter()
+            .take_while(|(_, event)| !matches!(event, MarkdownEvent::End(_)))
+            .map(|(range, _)| range.clone())
+            .reduce(|contents, range| contents.start..range.end)
+            .map_or(String::new(), |contents| self.source[contents].to_string());
+        Some((*kind, contents))
+    }
+
+    /// Returns the source range, destination, and text of every link, including
+    /// reference links and bare URLs.
+    pub fn links(&self) -> Vec<(Range<usize>, String, String)> {
+        let mut links = Vec::new();
+        let mut current_link: Option<(Range<usize>, String, String)> = None;
+        for (range, event) in self.events.iter() {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::Link { dest_url, .. }) => {
+                    current_link = Some((range.clone(), dest_url.to_string(), String::new()));
+                }
+                MarkdownEvent::End(MarkdownTagEnd::Link) => links.extend(current_link.take()),
+                _ => {
+                    let Some((_, _, text)) = current_link.as_mut() else {
+                        continue;
+                    };
+                    match event {
+                        MarkdownEvent::Text | MarkdownEvent::Code => {
+                            text.push_str(&self.source[range.clone()])
+                        }
+                        MarkdownEvent::SubstitutedText(substitution) => text.push_str(substitution),
+                        MarkdownEvent::SoftBreak | MarkdownEvent::HardBreak => text.push(' '),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        links
+    }
+
+    /// Returns the constructs in the document that aren't rendered as written, in
+    /// source order.
+    pub fn diagnostics(&self) -> Vec<MarkdownDiagnostic> {
+        let mut diagnostics = Vec::new();
+        for (range, event) in self.events.iter() {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::Image {
+                    link_type:
+                        LinkType::ReferenceUnknown
+                        | LinkType::CollapsedUnknown
+                        | LinkType::ShortcutUnknown,
+                    ..
+                }) => diagnostics.push(MarkdownDiagnostic {
+                    kind: MarkdownDiagnosticKind::UnresolvedImageReference,
+                    range: range.clone(),
+                }),
+                MarkdownEvent::Html | MarkdownEvent::InlineHtml => {
+                    diagnostics.extend(
+                        html_tags(&self.source[range.clone()])
+                            .into_iter()
+                            .filter(|(_, tag)| *tag == HtmlTag::Unsupported)
+                            .map(|(tag_range, _)| MarkdownDiagnostic {
+                                kind: MarkdownDiagnosticKind::UnsupportedHtmlTag,
+                                range: range.start + tag_range.start..range.start + tag_range.end,
+                            }),
+                    );
+                }
+                _ => {}
+            }
+        }
+        diagnostics
+    }
+
+    /// Parses `source`, reusing the events of this document if `source` extends
+    /// it and the appended text can't change how its earlier blocks parse.
+    pub fn append(&self, source: SharedString, options: ParseOptions) -> Self {
+        if !self.source.is_empty()
+            && self.options == options
+            && source.starts_with(self.source.as_ref())
+        {
+            if let Some(events) = parse_appended_markdown(&source, &self.events, options) {
+                return Self {
+                    source,
+                    events: events.into(),
+                    options,
+                };
+            }
+        }
+        Self::parse(source, options)
+    }
+
+    /// Parses `source`, an arbitrary edit of this document, reusing the events
+    /// of the blocks the edit can't have affected. Falls back to a full parse
+    /// when it isn't clear which blocks those are.
+    pub fn edit(&self, source: SharedString, options: ParseOptions) -> Self {
+        if self.source.is_empty()
+            || self.options != options
+            || source.starts_with(self.source.as_ref())
+        {
+            return self.append(source, options);
+        }
+        if let Some(events) = parse_edited_markdown(&self.source, &source, &self.events, options) {
+            return Self {
+                source,
+                events: events.into(),
+                options,
+            };
+        }
+        Self::parse(source, options)
+    }
+
+    /// Computes which top-level blocks were added, removed, or left unchanged
+    /// going from this document to `other`.
+    pub fn block_diff(&self, other: &ParsedMarkdown) -> Vec<BlockDiff> {
+        let old_blocks = self.block_ranges();
+        let new_blocks = other.block_ranges();
+        let old_text = old_blocks
+            .iter()
+            .map(|range| self.source[range.clone()].trim_end())
+            .collect::<Vec<_>>();
+        let new_text = new_blocks
+            .iter()
+            .map(|range| other.source[range.clone()].trim_end())
+            .collect::<Vec<_>>();
+
+        // Longest common subsequence of blocks, computed back to front.
+        let mut lcs = vec![vec![0; new_text.len() + 1]; old_text.len() + 1];
+        for i in (0..old_text.len()).rev() {
+            for j in (0..new_text.len()).rev() {
+                lcs[i][j] = if old_text[i] == new_text[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut diff = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old_text.len() && j < new_text.len() {
+            if old_text[i] == new_text[j] {
+                diff.push(BlockDiff::Unchanged {
+                    old: old_blocks[i].clone(),
+                    new: new_blocks[j].clone(),
+                });
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                diff.push(BlockDiff::Removed(old_blocks[i].clone()));
+                i += 1;
+            } else {
+                diff.push(BlockDiff::Added(new_blocks[j].clone()));
+                j += 1;
+            }
+        }
+        diff.extend(old_blocks[i..].iter().cloned().map(BlockDiff::Removed));
+        diff.extend(new_blocks[j..].iter().cloned().map(BlockDiff::Added));
+        diff
+    }
+
+    /// Returns the document's headings in order, with their rendered text and a
+    /// unique GitHub-style slug.
+    pub fn headings(&self) -> Vec<MarkdownHeading> {
+        let mut headings: Vec<MarkdownHeading> = Vec::new();
+        let mut current_heading: Option<MarkdownHeading> = None;
+        for (range, event) in self.events.iter() {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::Heading { level, .. }) => {
+                    current_heading = Some(MarkdownHeading {
+                        level: *level,
+                        text: String::new(),
+                        slug: String::new(),
+                        source_range: range.clone(),
+                    });
+                }
+                MarkdownEvent::End(MarkdownTagEnd::Heading(_)) => {
+                    if let Some(mut heading) = current_heading.take() {
+                        // Repeated slugs get a numeric suffix, e.g. `intro-1`.
+                        let slug = heading_slug(&heading.text);
+                        heading.slug = slug.clone();
+                        let mut suffix = 0;
+                        while headings.iter().any(|other| other.slug == heading.slug) {
+                            suffix += 1;
+                            heading.slug = format!("{slug}-{suffix}");
+                        }
+                        headings.push(heading);
+                    }
+                }
+                MarkdownEvent::Text | MarkdownEvent::Code => {
+                    if let Some(heading) = current_heading.as_mut() {
+                        heading.text.push_str(&self.source[range.clone()]);
+                    }
+                }
+                MarkdownEvent::SubstitutedText(text) => {
+                    if let Some(heading) = current_heading.as_mut() {
+                        heading.text.push_str(text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        headings
+    }
+
+    fn block_ranges(&self) -> Vec<Range<usize>> {
+        let mut blocks = Vec::new();
+        let mut depth = 0;
+        for (range, event) in self.events.iter() {
+            match event {
+                MarkdownEvent::Start(_) => {
+                    if depth == 0 {
+                        blocks.push(range.clone());
+                    }
+                    depth += 1;
+                }
+                MarkdownEvent::End(_) => depth -= 1,
+                _ => {
+                    if depth == 0 {
+                        blocks.push(range.clone());
+                    }
+                }
+            }
+        }
+        blocks
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkdownHeading {
+    pub level: pulldown_cmark::HeadingLevel,
+    pub text: String,
+    /// An anchor for the heading, e.g. `getting-started` for "Getting Started".
+    pub slug: String,
+    pub source_range: Range<usize>,
+}
+
+/// Lowercases the text, strips punctuation and turns spaces into hyphens, like
+/// GitHub does for heading anchors.
+fn heading_slug(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter_map(|ch| match ch {
+            ' ' => Some('-'),
+            '-' | '_' => Some(ch),
+            ch if ch.is_alphanumeric() => Some(ch),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// How a top-level block changed between two markdown documents. Ranges are
+/// byte offsets into the source of the respective document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockDiff {
+    Unchanged {
+        old: Range<usize>,
+        new: Range<usize>,
+    },
+    /// A block that only exists in the new document.
+    Added(Range<usize>),
+    /// A block that only exists in the old document.
+    Removed(Range<usize>),
+}
+
+/// A construct in a [`ParsedMarkdown`] document that isn't rendered as written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkdownDiagnostic {
+    pub kind: MarkdownDiagnosticKind,
+    /// The source range of the construct.
+    pub range: Range<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkdownDiagnosticKind {
+    /// An image like `![alt][label]` whose label isn't defined, so only its alt
+    /// text is shown.
+    UnresolvedImageReference,
+    /// An HTML tag that's shown as source text, because it's not one of the tags
+    /// rendered with [`MarkdownStyle::render_inline_html`].
+    UnsupportedHtmlTag,
+}
+
+impl Default for ParsedMarkdown {
+    fn default() -> Self {
+        Self {
+            source: SharedString::default(),
+            events: Arc::from([]),
+            options: ParseOptions::default(),
+        }
+    }
+}
+
+pub struct MarkdownElement {
+    /// `None` for markdown rendered with [`render_markdown_static`], which has
+    /// no view to keep the selection and other interactive state in.
+    markdown: Option<View<Markdown>>,
+    /// The markdown rendered when there's no view.
+    parsed_markdown: ParsedMarkdown,
+    style: MarkdownStyle,
+    language_registry: Option<Arc<LanguageRegistry>>,
+}
+
+impl MarkdownElement {
+    fn new(
+        markdown: View<Markdown>,
+        style: MarkdownStyle,
+        language_registry: Option<Arc<LanguageRegistry>>,
+    ) -> Self {
+        Self {
+            markdown: Some(markdown),
+            parsed_markdown: ParsedMarkdown::default(),
+            style,
+            language_registry,
+        }
+    }
+
+    fn load_language(&self, name: &str, cx: &mut WindowContext) -> Option<Arc<Language>> {
+        let markdown = self.markdown.as_ref()?;
+        let language = self
+            .language_registry
+            .as_ref()?
+            .language_for_name(markdown.read(cx).resolve_language_alias(name))
+            .map(|language| language.ok())
+            .shared();
+
+        match language.clone().now_or_never() {
+            Some(language) => language,
+            None => {
+                let markdown = markdown.downgrade();
+                cx.spawn(|mut cx| async move {
+                    language.await;
+                    markdown.update(&mut cx, |_, cx| cx.notify())
+                })
+                .detach_and_log_err(cx);
+                None
+            }
+        }
+    }
+
+    fn render_image(&self, source: ImageSource) -> AnyElement {
+        img(source)
+            .when_some(self.style.image_max_width, |img, width| img.max_w(width))
+            .into_any_element()
+    }
+
+    /// Renders a remote image whose size is capped by `image_max_bytes`, fetching
+    /// it first if needed. Returns `None` while it's loading or if it failed to,
+    /// so that its alt text is rendered instead.
+    fn render_remote_image(
+        &self,
+        url: &SharedString,
+        max_bytes: usize,
+        cx: &mut WindowContext,
+    ) -> Option<AnyElement> {
+        // Without a view, there's nowhere to keep the fetched image.
+        let markdown = self.markdown.clone()?;
+        let source = match markdown.read(cx).remote_images.get(url) {
+            None => {
+                markdown.update(cx, |markdown, cx| {
+                    markdown.fetch_remote_image(url.clone(), max_bytes, cx)
+                });
+                return None;
+            }
+            Some(RemoteImage::Loading { .. } | RemoteImage::Failed) => return None,
+            Some(RemoteImage::Loaded(data)) => ImageSource::Data(data.clone()),
+            Some(RemoteImage::Uncapped) => ImageSource::from(url.to_string()),
+            Some(RemoteImage::TooLarge) => {
+                let url = url.clone();
+                return Some(
+                    div()
+                        .debug_selector(|| format!("large-image-{url}"))
+                        .child(
+                            Button::new(
+                                ElementId::Name(format!("markdown-large-image-{url}").into()),
+                                "Image too large to load automatically. Click to load",
+                            )
+                            .label_size(LabelSize::Small)
+                            .on_click(move |_, cx| {
+                                markdown.update(cx, |markdown, cx| {
+                                    markdown.load_remote_image_in_full(url.clone(), cx)
+                                })
+                            }),
+                        )
+                        .into_any_element(),
+                );
+            }
+        };
+        Some(self.render_image(source))
+    }
+
+    /// Paints `hovered_line_background` behind the line under the mouse, and
+    /// returns where it was painted.
+    fn paint_hovered_line(
+        &mut self,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) -> Option<Bounds<Pixels>> {
+        let color = self.style.hovered_line_background?;
+        let hovered_line = self.markdown.as_ref()?.read(cx).hovered_line.clone()?;
+        let line = rendered_text
+            .lines
+            .iter()
+            .find(|line| line.source_range() == hovered_line)?;
+        let bounds = line.layout.bounds();
+        cx.paint_quad(fill(bounds, color));
+        Some(bounds)
+    }
+
+    /// Paints the background of `inline_code` behind each row of inline code,
+    /// padded by `inline_code_padding`, and returns where it was painted.
+    fn paint_inline_code_backgrounds(
+        &mut self,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) -> Vec<Bounds<Pixels>> {
+        let padding = self.style.inline_code_padding;
+        let Some(color) = self
+            .style
+            .inline_code
+            .background_color
+            .filter(|_| padding > Pixels::ZERO)
+        else {
+            return Vec::new();
+        };
+        let mut painted = Vec::new();
+        for range in rendered_text.inline_code.iter() {
+            for bounds in rendered_text.bounds_for_source_range(range.clone()) {
+                let bounds = Bounds::new(
+                    point(bounds.left() - padding, bounds.top()),
+                    size(bounds.size.width + padding * 2., bounds.size.height),
+                );
+                cx.paint_quad(fill(bounds, color).corner_radii(padding));
+                painted.push(bounds);
+            }
+        }
+        painted
+    }
+
+    fn paint_selection(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) {
+        let Some(markdown) = self.markdown.as_ref() else {
+            return;
+        };
+        let selection = markdown.read(cx).selection;
+        self.paint_range_background(
+            bounds,
+            selection.start..selection.end,
+            self.style.selection_background_color,
+            rendered_text,
+            cx,
+        );
+    }
+
+    /// Paints the caret at the head of an empty selection while focused, and
+    /// returns its bounds whether or not it's in the visible phase of its blink.
+    fn paint_cursor(
+        &mut self,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) -> Option<Bounds<Pixels>> {
+        let view = self.markdown.clone()?;
+        let markdown = view.read(cx);
+        let selection = markdown.selection;
+        let show_cursor = markdown.focus_handle.is_focused(cx) && selection.start == selection.end;
+        let has_blink_task = markdown.cursor_blink_task.is_some();
+        let cursor_visible = markdown.cursor_visible;
+        if !show_cursor {
+            if has_blink_task {
+                view.update(cx, |markdown, _| markdown.stop_cursor_blink());
+            }
+            return None;
+        }
+        if !has_blink_task {
+            view.update(cx, |markdown, cx| markdown.restart_cursor_blink(cx));
+        }
+
+        let (position, line_height) = rendered_text.position_for_source_index(selection.head())?;
+        let cursor_bounds = Bounds::new(position, size(CURSOR_WIDTH, line_height));
+        if cursor_visible {
+            cx.paint_quad(fill(cursor_bounds, self.style.cursor_color));
+        }
+        Some(cursor_bounds)
+    }
+
+    fn paint_search_matches(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) {
+        let Some(markdown) = self.markdown.as_ref() else {
+            return;
+        };
+        let search_matches = markdown.read(cx).search_matches.clone();
+        for range in search_matches {
+            self.paint_range_background(
+                bounds,
+                range,
+                self.style.search_match_background,
+                rendered_text,
+                cx,
+            );
+        }
+    }
+
+    /// Fills the rows spanned by the given source range, up to the edges of
+    /// `bounds` for the rows it continues onto or from.
+    fn paint_range_background(
+        &mut self,
+        bounds: Bounds<Pixels>,
+        range: Range<usize>,
+        color: Hsla,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) {
+        let range_start = rendered_text.position_for_source_index(range.start);
+        let range_end = rendered_text.position_for_source_index(range.end);
+
+        if let Some(((start_position, start_line_height), (end_position, end_line_height))) =
+            range_start.zip(range_end)
+        {
+            if start_position.y == end_position.y {
+                cx.paint_quad(quad(
+                    Bounds::from_corners(
+                        start_position,
+                        point(end_position.x, end_position.y + end_line_height),
+                    ),
+                    Pixels::ZERO,
+                    color,
+                    Edges::default(),
+                    Hsla::transparent_black(),
+                ));
+            } else {
+                cx.paint_quad(quad(
+                    Bounds::from_corners(
+                        start_position,
+                        point(bounds.right(), start_position.y + start_line_height),
+                    ),
+                    Pixels::ZERO,
+                    color,
+                    Edges::default(),
+                    Hsla::transparent_black(),
+                ));
+
+                if end_position.y > start_position.y + start_line_height {
+                    cx.paint_quad(quad(
+                        Bounds::from_corners(
+                            point(bounds.left(), start_position.y + start_line_height),
+                            point(bounds.right(), end_position.y),
+                        ),
+                        Pixels::ZERO,
+                        color,
+                        Edges::default(),
+                        Hsla::transparent_black(),
+                    ));
+                }
+
+                cx.paint_quad(quad(
+                    Bounds::from_corners(
+                        point(bounds.left(), end_position.y),
+                        point(end_position.x, end_position.y + end_line_height),
+                    ),
+                    Pixels::ZERO,
+                    color,
+                    Edges::default(),
+                    Hsla::transparent_black(),
+                ));
+            }
+        }
+    }
+
+    fn render_task_checkbox(
+        &self,
+        marker_range: Range<usize>,
+        item_range: Range<usize>,
+        checked: bool,
+        interactive: bool,
+    ) -> AnyElement {
+        let selection = if checked {
+            ui::Selection::Selected
+        } else {
+            ui::Selection::Unselected
+        };
+        Checkbox::new(("markdown-task", marker_range.start), selection)
+            .when_some(
+                self.markdown.clone().filter(|_| interactive),
+                |checkbox, markdown| {
+                    checkbox.on_click(move |_, cx| {
+                        markdown.update(cx, |markdown, cx| {
+                            markdown.toggle_task(marker_range.clone(), item_range.clone(), cx)
+                        });
+                    })
+                },
+            )
+            .into_any_element()
+    }
+
+    /// The header of front matter shown with `show_metadata`, whose disclosure
+    /// collapses it.
+    fn render_metadata_header(&self, kind: MetadataBlockKind, is_open: bool) -> impl IntoElement {
+        let on_toggle = self.markdown.clone().map(|markdown| {
+            Arc::new(move |_: &ClickEvent, cx: &mut WindowContext| {
+                markdown.update(cx, |markdown, cx| {
+                    markdown.metadata_collapsed = !markdown.metadata_collapsed;
+                    cx.notify();
+                });
+            }) as Arc<dyn Fn(&ClickEvent, &mut WindowContext)>
+        });
+        let label = match kind {
+            MetadataBlockKind::YamlStyle => "YAML front matter",
+            MetadataBlockKind::PlusesStyle => "TOML front matter",
+        };
+        h_flex()
+            .gap_1()
+            .when(is_open, |header| header.mb_2())
+            .child(
+                Disclosure::new("markdown-metadata", is_open)
+                    .when_some(on_toggle, |disclosure, on_toggle| {
+                        disclosure.on_toggle(on_toggle)
+                    }),
+            )
+            .child(Label::new(label).size(LabelSize::Small).color(Color::Muted))
+    }
+
+    /// A bar above a fenced code block showing its language and a button that
+    /// copies its contents from the source.
+    fn render_code_block_header(
+        &self,
+        language: &str,
+        contents: Range<usize>,
+        source: SharedString,
+    ) -> impl IntoElement {
+        h_flex()
+            .justify_between()
+            .mb_2()
+            .child(
+                Label::new(language.to_string())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                IconButton::new(("copy-code-block", contents.start), IconName::Copy)
+                    .icon_size(IconSize::Small)
+                    .icon_color(Color::Muted)
+                    .on_click(move |_, cx| {
+                        let text = &source[contents.clone()];
+                        let text = text.strip_suffix('\n').unwrap_or(text);
+                        cx.write_to_clipboard(ClipboardItem::new(text.to_string()));
+                    }),
+            )
+    }
+
+    /// The button that expands markdown cut off by [`Markdown::max_height`],
+    /// which overlays the bottom of the content while it's collapsed.
+    fn render_expansion_toggle(&self, collapsed: bool) -> AnyElement {
+        let label = if collapsed { "Show more" } else { "Show less" };
+        h_flex()
+            .w_full()
+            .justify_center()
+            .when(collapsed, |row| row.absolute().bottom_0().left_0())
+            .child(
+                Button::new("markdown-expansion-toggle", label)
+                    .label_size(LabelSize::Small)
+                    .when_some(self.markdown.clone(), |button, markdown| {
+                        button.on_click(move |_, cx| {
+                            markdown.update(cx, |markdown, cx| markdown.set_expanded(collapsed, cx))
+                        })
+                    }),
+            )
+            .into_any_element()
+    }
+
+    /// Fades the bottom of collapsed content into `truncation_fade_color`.
+    fn paint_truncation_fade(&self, bounds: Bounds<Pixels>, cx: &mut WindowContext) {
+        const FADE_STEPS: usize = 8;
+
+        let fade_height = (cx.line_height() * 3.).min(bounds.size.height / 2.);
+        let step_height = fade_height / FADE_STEPS as f32;
+        let color = self.style.truncation_fade_color;
+        for step in 0..FADE_STEPS {
+            let top = bounds.bottom() - fade_height + step_height * step as f32;
+            let opacity = (step + 1) as f32 / FADE_STEPS as f32;
+            cx.paint_quad(fill(
+                Bounds::new(
+                    point(bounds.left(), top),
+                    size(bounds.size.width, step_height),
+                ),
+                Hsla {
+                    a: color.a * opacity,
+                    ..color
+                },
+            ));
+        }
+    }
+
+    /// The div containing all blocks, which applies the content padding and width.
+    fn root_div(&self) -> Div {
+        let padding = self.style.content_padding;
+        div()
+            .debug_selector(|| "inner".into())
+            .pt(padding.top)
+            .pr(padding.right)
+            .pb(padding.bottom)
+            .pl(padding.left)
+            .when(!self.style.full_width_code_blocks, |div| {
+                self.content_column(div)
+            })
+    }
+
+    /// Caps the width of the given div to `max_content_width`.
+    fn content_column(&self, div: Div) -> Div {
+        div.when_some(self.style.max_content_width, |div, max_width| {
+            div.max_w(max_width)
+                .when(self.style.center_content, |div| div.mx_auto())
+        })
+    }
+
+    /// Extends a top-level block horizontally over the root's `content_padding`.
+    fn bleed_into_padding(&self, block: Div) -> Div {
+        let padding = self.style.content_padding;
+        block.ml(-padding.left).mr(-padding.right)
+    }
+
+    fn paint_link_underlines(&mut self, rendered_text: &RenderedText, cx: &mut WindowContext) {
+        let Some(underline) = self.style.link.underline else {
+            return;
+        };
+        let color = underline
+            .color
+            .or(self.style.link.color)
+            .unwrap_or(cx.text_style().color);
+        for link in rendered_text.links.iter() {
+            for bounds in rendered_text.bounds_for_source_range(link.source_range.clone()) {
+                let underline_origin =
+                    point(bounds.left(), bounds.bottom() - bounds.size.height * 0.15);
+                cx.paint_quad(fill(
+                    Bounds::new(
+                        underline_origin,
+                        size(bounds.size.width, underline.thickness),
+                    ),
+                    color,
+                ));
+            }
+        }
+    }
+
+    fn paint_icons(&mut self, rendered_text: &RenderedText, cx: &mut WindowContext) {
+        let color = self.style.link.color.unwrap_or(cx.text_style().color);
+        for line in rendered_text.lines.iter() {
+            let line_height = line.layout.line_height();
+            for (range, icon) in &line.icons {
+                let Some(start) = line.layout.position_for_index(range.start) else {
+                    continue;
+                };
+                let icon_size = line_height * 0.6;
+                let gap_width = line
+                    .layout
+                    .position_for_index(range.end)
+                    .filter(|end| end.y == start.y)
+                    .map_or(icon_size, |end| end.x - start.x);
+                let origin = point(
+                    start.x + (gap_width - icon_size).max(Pixels::ZERO) * 0.5,
+                    start.y + (line_height - icon_size) * 0.5,
+                );
+                cx.paint_svg(
+                    Bounds::new(origin, size(icon_size, icon_size)),
+                    icon.path().into(),
+                    TransformationMatrix::unit(),
+                    color,
+                )
+                .log_err();
+            }
+        }
+    }
+
+    fn paint_soft_hyphens(&mut self, rendered_text: &RenderedText, cx: &mut WindowContext) {
+        for line in rendered_text.lines.iter() {
+            let line_height = line.layout.line_height();
+            for soft_hyphen in &line.soft_hyphens {
+                let (Some(position), Some(next_position)) = (
+                    line.layout.position_for_index(soft_hyphen.index),
+                    line.layout.position_for_index(soft_hyphen.next_char_end),
+                ) else {
+                    continue;
+                };
+                if next_position.y <= position.y {
+                    continue;
+                }
+
+                let font_size = soft_hyphen.style.font_size.to_pixels(cx.rem_size());
+                let run = soft_hyphen.style.to_run(1);
+                if let Some(hyphen) = cx
+                    .text_system()
+                    .shape_line("-".into(), font_size, &[run])
+                    .log_err()
+                {
+                    hyphen.paint(position, line_height, cx).log_err();
+                }
+            }
+        }
+    }
+
+    fn on_move_selection_head<A: gpui::Action>(
+        &self,
+        rendered_text: &RenderedText,
+        move_head: fn(&RenderedText, usize) -> usize,
+        cx: &mut WindowContext,
+    ) {
+        let Some(view) = self.markdown.clone() else {
+            return;
+        };
+        let text = rendered_text.clone();
+        cx.on_action(std::any::TypeId::of::<A>(), move |_, phase, cx| {
+            if phase == DispatchPhase::Bubble {
+                view.update(cx, |markdown, cx| {
+                    markdown.move_selection_head(&text, move_head, cx)
+                })
+            }
+        });
+    }
+
+    fn paint_mouse_listeners(
+        &mut self,
+        hitbox: &Hitbox,
+        rendered_text: &RenderedText,
+        cx: &mut WindowContext,
+    ) {
+        let Some(view) = self.markdown.as_ref() else {
+            return;
+        };
+        let is_hovering_link = hitbox.is_hovered(cx)
+            && !view.read(cx).selection.pending
+            && rendered_text
+                .link_for_position(cx.mouse_position())
+                .is_some();
+
+        if is_hovering_link {
+            cx.set_cursor_style(CursorStyle::PointingHand, hitbox);
+        } else {
+            cx.set_cursor_style(CursorStyle::IBeam, hitbox);
+        }
+
+        self.on_mouse_event(cx, {
+            let rendered_text = rendered_text.clone();
+            let hitbox = hitbox.clone();
+            move |markdown, event: &MouseDownEvent, phase, cx| {
+                if hitbox.is_hovered(cx) {
+                    if phase.bubble() && event.button == MouseButton::Right {
+                        let link = rendered_text.link_for_position(event.position).cloned();
+                        markdown.deploy_context_menu(
+                            event.position,
+                            link,
+                            rendered_text.clone(),
+                            cx,
+                        );
+                    } else if phase.bubble() {
+                        if let Some(link) = rendered_text.link_for_position(event.position) {
+                            markdown.pressed_link = Some(link.clone());
+                        } else {
+                            let source_index =
+                                match rendered_text.source_index_for_position(event.position) {
+                                    Ok(ix) | Err(ix) => ix,
+                                };
+                            let range = if event.click_count == 2 {
+                                rendered_text.surrounding_word_range(source_index)
+                            } else if event.click_count == 3 {
+                                rendered_text.surrounding_line_range(source_index)
+                            } else if event.click_count == 4 {
+                                rendered_text.surrounding_block_range(source_index)
+                            } else {
+                                source_index..source_index
+                            };
+                            markdown.selection = Selection {
+                                start: range.start,
+                                end: range.end,
+                                reversed: false,
+                                pending: true,
+                            };
+                            markdown.report_selection(cx);
+                            markdown.restart_cursor_blink(cx);
+                            cx.focus(&markdown.focus_handle);
+                            cx.prevent_default()
+                        }
+
+                        cx.notify();
+                    }
+                } else if phase.capture() && markdown.context_menu.is_none() {
+                    // Clicks in the context menu keep the selection it acts on.
+                    markdown.selection = Selection::default();
+                    markdown.report_selection(cx);
+                    markdown.pressed_link = None;
+                    markdown.drag_autoscroll = None;
+                    cx.notify();
+                }
+            }
+        });
+        self.on_mouse_event(cx, {
+            let rendered_text = rendered_text.clone();
+            let hitbox = hitbox.clone();
+            let was_hovering_link = is_hovering_link;
+            let footnote_hover_preview = self.style.footnote_hover_preview;
+            let link_tooltips = self.style.link_tooltips;
+            let link_tooltip_delay = self.style.link_tooltip_delay;
+            let highlight_hovered_line = self.style.hovered_line_background.is_some();
+            move |markdown, event: &MouseMoveEvent, phase, cx| {
+                if phase.capture() {
+                    return;
+                }
+
+                if markdown.selection.pending {
+                    let source_index = match rendered_text.source_index_for_position(event.position)
+                    {
+                        Ok(ix) | Err(ix) => ix,
+                    };
+                    markdown.selection.set_head(source_index);
+                    markdown.report_selection(cx);
+                    markdown.autoscroll_request = Some(source_index);
+
+                    // Mouse moves stop while the drag is held still, so keep scrolling
+                    // on a timer when it's at the top or bottom of the visible text.
+                    let visible_bounds = hitbox.bounds.intersect(&hitbox.content_mask.bounds);
+                    let direction = if event.position.y
+                        < visible_bounds.top() + DRAG_AUTOSCROLL_MARGIN
+                    {
+                        Some(AutoscrollDirection::Up)
+                    } else if event.position.y > visible_bounds.bottom() - DRAG_AUTOSCROLL_MARGIN {
+                        Some(AutoscrollDirection::Down)
+                    } else {
+                        None
+                    };
+                    markdown.set_drag_autoscroll(direction, cx);
+                    cx.notify();
+                } else {
+                    let is_hovering_link = hitbox.is_hovered(cx)
+                        && rendered_text.link_for_position(event.position).is_some();
+                    if is_hovering_link != was_hovering_link {
+                        cx.notify();
+                    }
+
+                    if highlight_hovered_line {
+                        let hovered_line = hitbox
+                            .is_hovered(cx)
+                            .then(|| rendered_text.line_for_position(event.position))
+                            .flatten()
+                            .map(RenderedLine::source_range);
+                        if hovered_line != markdown.hovered_line {
+                            markdown.hovered_line = hovered_line;
+                            cx.notify();
+                        }
+                    }
+
+                    if link_tooltips {
+                        let hovered_link = hitbox
+                            .is_hovered(cx)
+                            .then(|| rendered_text.link_for_position(event.position))
+                            .flatten();
+                        if hovered_link != markdown.hovered_link.as_ref() {
+                            markdown.hovered_link = hovered_link.cloned();
+                            markdown.update_link_tooltip(event.position, link_tooltip_delay, cx);
+                        }
+                    }
+
+                    if footnote_hover_preview {
+                        let footnote = hitbox
+                            .is_hovered(cx)
+                            .then(|| rendered_text.footnote_reference_for_position(event.position))
+                            .flatten();
+                        let hovered_footnote =
+                            footnote.map(|footnote| footnote.source_range.clone());
+                        if hovered_footnote != markdown.hovered_footnote {
+                            markdown.hovered_footnote = hovered_footnote;
+                            let hover = footnote.map(|footnote| FootnoteHover {
+                                label: footnote.label.clone(),
+                                definition: rendered_text.footnote_definition(&footnote.label),
+                                reference_bounds: rendered_text
+                                    .bounds_for_source_range(footnote.source_range.clone()),
+                            });
+                            if let Some(on_footnote_hover) = markdown.on_footnote_hover.as_ref() {
+                                on_footnote_hover(hover, cx);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        self.on_mouse_event(cx, {
+            let rendered_text = rendered_text.clone();
+            move |markdown, event: &MouseUpEvent, phase, cx| {
+                if phase.bubble() {
+                    if let Some(pressed_link) = markdown.pressed_link.take() {
+                        if Some(&pressed_link) == rendered_text.link_for_position(event.position) {
+                            markdown.open_link(&pressed_link, &rendered_text, cx);
+                        }
+                    }
+                } else {
+                    if markdown.selection.pending {
+                        markdown.selection.pending = false;
+                        markdown.drag_autoscroll = None;
+                        #[cfg(target_os = "linux")]
+                        if let Some(text) = markdown.selected_text() {
+                            cx.write_to_primary(ClipboardItem::new(text))
+                        }
+                        cx.notify();
+                    }
+                }
+            }
+        });
+    }
+
+    fn autoscroll(&mut self, rendered_text: &RenderedText, cx: &mut WindowContext) -> Option<()> {
+        let autoscroll_index = self
+            .markdown
+            .as_ref()?
+            .update(cx, |markdown, _| markdown.autoscroll_request.take())?;
+        let (position, line_height) = rendered_text.position_for_source_index(autoscroll_index)?;
+
+        let text_style = cx.text_style();
+        let font_id = cx.text_system().resolve_font(&text_style.font());
+        let font_size = text_style.font_size.to_pixels(cx.rem_size());
+        let em_width = cx
+            .text_system()
+            .typographic_bounds(font_id, font_size, 'm')
+            .unwrap()
+            .size
+            .width;
+        cx.request_autoscroll(Bounds::from_corners(
+            point(position.x - 3. * em_width, position.y - 3. * line_height),
+            point(position.x + 3. * em_width, position.y + 3. * line_height),
+        ));
+        Some(())
+    }
+
+    fn on_mouse_event<T: MouseEvent>(
+        &self,
+        cx: &mut WindowContext,
+        mut f: impl 'static + FnMut(&mut Markdown, &T, DispatchPhase, &mut ViewContext<Markdown>),
+    ) {
+        let Some(markdown) = self.markdown.as_ref().map(View::downgrade) else {
+            return;
+        };
+        cx.on_mouse_event(move |event, phase, cx| {
+            markdown
+                .update(cx, |markdown, cx| f(markdown, event, phase, cx))
+                .log_err();
+        });
+    }
+}
+
+impl Element for MarkdownElement {
+    type RequestLayoutState = RenderedMarkdown;
+    type PrepaintState = Hitbox;
+
+    fn id(&self) -> Option<ElementId> {
+        None
+    }
+
+    fn request_layout(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        cx: &mut WindowContext,
+    ) -> (LayoutId, Self::RequestLayoutState) {
+        with_font_scale(self.style.font_scale, cx, |cx| {
+            let mut builder = MarkdownElementBuilder::new(
+                cx.text_style(),
+                self.style.syntax.clone(),
+                self.root_div(),
+            );
+            let markdown = self.markdown.as_ref().map(|markdown| markdown.read(cx));
+            if let Some((color, markdown)) = self.style.selection_text_color.zip(markdown) {
+                let selection = markdown.selection;
+                if selection.start < selection.end {
+                    builder.selection_text_color = Some((selection.start..selection.end, color));
+                }
+            }
+            builder.soft_hyphens = self.style.soft_hyphens;
+            builder.block_quote_hanging_indent = self.style.block_quote_hanging_indent;
+            let parsed_markdown = markdown.map_or_else(
+                || self.parsed_markdown.clone(),
+                |markdown| markdown.parsed_markdown.clone(),
+            );
+            let collapsed_headings = markdown
+                .map(|markdown| markdown.collapsed_headings.clone())
+                .unwrap_or_default();
+            let metadata_collapsed = markdown.map_or(false, |markdown| markdown.metadata_collapsed);
+            let math_renderer = markdown.and_then(|markdown| markdown.math_renderer.clone());
+            let code_block_decorator =
+                markdown.and_then(|markdown| markdown.code_block_decorator.clone());
+            let code_block_renderers = markdown
+                .map(|markdown| markdown.code_block_renderers.clone())
+                .unwrap_or_default();
+            let tasks_interactive = markdown.map_or(false, |markdown| {
+                markdown.tasks_interactive || markdown.on_toggle_task.is_some()
+            });
+            let mut in_metadata_block = false;
+            let mut depth = 0;
+            let mut collapsing_heading = None;
+            let mut collapsed_level = None;
+            let mut heading_has_disclosure = false;
+            let mut current_link_url = None;
+            let mut in_image = false;
+            let mut in_rendered_code_block = false;
+            let mut callout_marker: Option<Range<usize>> = None;
+            let mut code_block_indent = 0;
+            for (ix, (range, event)) in parsed_markdown.events.iter().enumerate() {
+                let is_top_level = depth == 0;
+                match event {
+                    MarkdownEvent::Start(_) => depth += 1,
+                    MarkdownEvent::End(_) => depth -= 1,
+                    _ => {}
+                }
+
+                // Front matter isn't rendered, so it doesn't push the first visible
+                // block down or leave stray text at the top of the document.
+                if in_metadata_block {
+                    if let MarkdownEvent::End(MarkdownTagEnd::MetadataBlock(_)) = event {
+                        in_metadata_block = false;
+                    }
+                    continue;
+                }
+
+                // The alt text of a loaded image is only rendered as a fallback.
+                if in_image {
+                    if let MarkdownEvent::End(MarkdownTagEnd::Image) = event {
+                        in_image = false;
+                    }
+                    continue;
+                }
+
+                // The code of a block with a custom renderer was passed to it instead.
+                if in_rendered_code_block {
+                    if let MarkdownEvent::End(MarkdownTagEnd::CodeBlock) = event {
+                        in_rendered_code_block = false;
+                    }
+                    continue;
+                }
+
+                // An alert's `[!KIND]` marker is rendered as its title instead.
+                if let Some(marker) = callout_marker.as_ref() {
+                    if range.start >= marker.end {
+                        callout_marker = None;
+                    } else if !matches!(event, MarkdownEvent::Start(_) | MarkdownEvent::End(_)) {
+                        continue;
+                    }
+                }
+
+                // Skip the content of a collapsed section, which ends at the next
+                // top-level heading of the same or a higher level.
+                if let Some(level) = collapsed_level {
+                    match event {
+                        MarkdownEvent::Start(MarkdownTag::Heading {
+                            level: heading_level,
+                            ..
+                        }) if is_top_level && *heading_level <= level => collapsed_level = None,
+                        MarkdownEvent::Start(MarkdownTag::Heading {
+                            level: heading_level,
+                            ..
+                        }) => {
+                            // Keep numbering stable regardless of which sections are collapsed.
+                            if self.style.number_headings {
+                                builder.heading_numbering.next(*heading_level);
+                            }
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                }
+
+                if let MarkdownEvent::End(_) = event {
+                    builder.pop_html_elements(depth);
+                }
+
+                // When code blocks span the full width, the content column is applied
+                // to each of the other top-level blocks instead of to the root.
+                let per_block_column =
+                    self.style.full_width_code_blocks && self.style.max_content_width.is_some();
+                let opens_column = per_block_column
+                    && is_top_level
+                    && !matches!(
+                        event,
+                        MarkdownEvent::End(_)
+                            | MarkdownEvent::Start(
+                                MarkdownTag::CodeBlock(_) | MarkdownTag::MetadataBlock(_)
+                            )
+                    );
+                let closes_column = per_block_column
+                    && depth == 0
+                    && !matches!(
+                        event,
+                        MarkdownEvent::Start(_)
+                            | MarkdownEvent::End(
+                                MarkdownTagEnd::CodeBlock | MarkdownTagEnd::MetadataBlock(_)
+                            )
+                    );
+                if opens_column {
+                    builder.push_div(self.content_column(div()));
+                }
+
+                match event {
+                    MarkdownEvent::Start(tag) => {
+                        match tag {
+                            MarkdownTag::MetadataBlock(kind) => {
+                                if !self.style.show_metadata {
+                                    in_metadata_block = true;
+                                } else {
+                                    let block = div()
+                                        .w_full()
+                                        .rounded_lg()
+                                        .p_4()
+                                        .mb(self.style.paragraph_spacing)
+                                        .when_some(
+                                            self.style.code_block.background_color,
+                                            |div, color| div.bg(color),
+                                        )
+                                        .child(
+                                            self.render_metadata_header(*kind, !metadata_collapsed),
+                                        );
+                                    if metadata_collapsed {
+                                        builder.push_element(block.into_any());
+                                        in_metadata_block = true;
+                                    } else {
+                                        builder.push_code_block(None);
+                                        builder.push_text_style(self.style.code_block.clone());
+                                        builder.push_div(block);
+                                    }
+                                }
+                            }
+                            MarkdownTag::Paragraph => {
+                                builder.push_div(
+                                    div()
+                                        .mb(self.style.paragraph_spacing)
+                                        .line_height(self.style.line_height),
+                                );
+                            }
+                            MarkdownTag::Heading { level, .. } => {
+                                if self.style.number_headings {
+                                    let number = builder.heading_numbering.next(*level);
+                                    builder.push_decoration(
+                                        format!("{number} "),
+                                        self.style.copy_heading_numbers,
+                                    );
+                                }
+
+                                let heading = div()
+                                    .mb(self.style.heading_spacing)
+                                    .text_size(self.style.heading_sizes[*level as usize - 1])
+                                    .line_height(relative(self.style.line_height.0));
+
+                                let disclosure_markdown = self
+                                    .markdown
+                                    .clone()
+                                    .filter(|_| self.style.collapsible_headings && is_top_level);
+                                heading_has_disclosure = disclosure_markdown.is_some();
+                                if let Some(markdown) = disclosure_markdown {
+                                    let is_open = !collapsed_headings.contains(range);
+                                    if !is_open {
+                                        collapsing_heading = Some(*level);
+                                    }
+
+                                    let heading_range = range.clone();
+                                    let on_toggle: Arc<dyn Fn(&ClickEvent, &mut WindowContext)> =
+                                        Arc::new(move |_, cx| {
+                                            markdown.update(cx, |markdown, cx| {
+                                                if !markdown
+                                                    .collapsed_headings
+                                                    .remove(&heading_range)
+                                                {
+                                                    markdown
+                                                        .collapsed_headings
+                                                        .insert(heading_range.clone());
+                                                }
+                                                cx.notify();
+                                            });
+                                        });
+                                    builder.push_div(
+                                        heading.flex().items_center().gap_1().child(
+                                            Disclosure::new(
+                                                ("markdown-heading", range.start),
+                                                is_open,
+                                            )
+                                            .on_toggle(on_toggle),
+                                        ),
+                                    );
+                                    // Without `w_0`, text doesn't wrap to the width of the container.
+                                    builder.push_div(div().flex_1().w_0());
+                                } else {
+                                    builder.push_div(heading);
+                                }
+                            }
+                            MarkdownTag::BlockQuote => {
+                                builder.push_block(range.clone());
+                                let callout = match parsed_markdown.events.get(ix + 1) {
+                                    Some((
+                                        paragraph_range,
+                                        MarkdownEvent::Start(MarkdownTag::Paragraph),
+                                    )) => parse_callout_marker(
+                                        &parsed_markdown.source[paragraph_range.clone()],
+                                    )
+                                    .map(
+                                        |(kind, marker_len)| {
+                                            (
+                                                kind,
+                                                paragraph_range.start
+                                                    ..paragraph_range.start + marker_len,
+                                            )
+                                        },
+                                    ),
+                                    _ => None,
+                                };
+
+                                if let Some((kind, marker)) = callout {
+                                    let color = self.style.callout_color(kind);
+                                    let background = Hsla {
+                                        a: color.a * self.style.callout_background_opacity,
+                                        ..color
+                                    };
+                                    builder.push_text_style(TextStyleRefinement::default());
+                                    builder.push_div(
+                                        div()
+                                            .pl_4()
+                                            .py_1()
+                                            .mb(self.style.paragraph_spacing)
+                                            .border_l_4()
+                                            .border_color(color)
+                                            .bg(background),
+                                    );
+                                    builder.push_div(h_flex().gap_1().mb_1());
+                                    builder.push_element(
+                                        svg()
+                                            .path(kind.icon().path())
+                                            .size_4()
+                                            .flex_none()
+                                            .text_color(color)
+                                            .into_any_element(),
+                                    );
+                                    builder.push_text_style(TextStyleRefinement {
+                                        color: Some(color),
+                                        font_weight: Some(FontWeight::SEMIBOLD),
+                                        ..Default::default()
+                                    });
+                                    builder.push_text(kind.title(), marker.start);
+                                    builder.pop_text_style();
+                                    builder.pop_div();
+                                    callout_marker = Some(marker);
+                                } else {
+                                    let depth = builder.block_quote_depth;
+                                    builder
+                                        .push_text_style(self.style.block_quote_text_style(depth));
+                                    builder.push_div(
+                                        div()
+                                            .pl_4()
+                                            .mb(self.style.paragraph_spacing)
+                                            .border_l_4()
+                                            .border_color(
+                                                self.style.nested_block_quote_border_color(depth),
+                                            ),
+                                    );
+                                }
+                                builder.block_quote_depth += 1;
+                            }
+                            MarkdownTag::CodeBlock(CodeBlockKind::Fenced { language, .. })
+                                if code_block_renderers.contains_key(language.as_ref()) =>
+                            {
+                                let renderer = code_block_renderers[language.as_ref()].clone();
+                                let code = parsed_markdown.source
+                                    [code_block_contents(&parsed_markdown.events[ix + 1..])]
+                                .to_string();
+                                let block_range = range.clone();
+                                // Renderers are only registered on views.
+                                let element = self.markdown.as_ref().map(|markdown| {
+                                    markdown.update(cx, |_, cx| renderer(&code, block_range, cx))
+                                });
+                                builder.push_element(
+                                    div()
+                                        .w_full()
+                                        .mb(self.style.paragraph_spacing)
+                                        .children(element)
+                                        .into_any(),
+                                );
+                                in_rendered_code_block = true;
+                            }
+                            MarkdownTag::CodeBlock(kind) => {
+                                let language = if let CodeBlockKind::Fenced { language, .. } = kind
+                                {
+                                    self.load_language(language.as_ref(), cx)
+                                } else {
+                                    None
+                                };
+
+                                builder.push_code_block(language);
+                                if self.style.dedent_code_blocks {
+                                    code_block_indent = common_indent(
+                                        &parsed_markdown.source,
+                                        &parsed_markdown.events[ix + 1..],
+                                    );
+                                }
+                                let mut code_block_style = self.style.code_block.clone();
+                                code_block_style.white_space =
+                                    Some(if self.style.wrap_code_blocks {
+                                        WhiteSpace::Normal
+                                    } else {
+                                        WhiteSpace::Nowrap
+                                    });
+                                builder.push_text_style(code_block_style);
+                                let code_block = div()
+                                    .rounded_lg()
+                                    .p_4()
+                                    .mb(self.style.paragraph_spacing)
+                                    .when_some(
+                                        self.style.code_block.background_color,
+                                        |div, color| div.bg(color),
+                                    )
+                                    .when_some(
+                                        match kind {
+                                            CodeBlockKind::Fenced { language, .. }
+                                                if self.style.code_block_header =>
+                                            {
+                                                Some(language)
+                                            }
+                                            _ => None,
+                                        },
+                                        |div, language| {
+                                            div.child(self.render_code_block_header(
+                                                language,
+                                                code_block_contents(
+                                                    &parsed_markdown.events[ix + 1..],
+                                                ),
+                                                parsed_markdown.source.clone(),
+                                            ))
+                                        },
+                                    );
+                                let code_block = match (kind, code_block_decorator.as_ref()) {
+                                    (
+                                        CodeBlockKind::Fenced { language, metadata },
+                                        Some(decorator),
+                                    ) => decorator(language, metadata, code_block),
+                                    _ => code_block,
+                                };
+                                builder.push_div(if self.style.full_bleed_blocks && is_top_level {
+                                    self.bleed_into_padding(code_block)
+                                } else {
+                                    code_block.w_full()
+                                });
+                            }
+                            MarkdownTag::HtmlBlock => builder.push_div(div()),
+                            MarkdownTag::List(bullet_index) => {
+                                builder.push_list(*bullet_index);
+                                builder.push_div(div().pl_4());
+                            }
+                            MarkdownTag::Item => {
+                                builder.push_block(range.clone());
+                                // In loose lists, the task marker follows the paragraph start.
+                                let task_marker = parsed_markdown.events[ix + 1..]
+                                    .iter()
+                                    .take(2)
+                                    .find_map(|(range, event)| match event {
+                                        MarkdownEvent::TaskListMarker(checked) => {
+                                            Some((range.clone(), *checked))
+                                        }
+                                        _ => None,
+                                    });
+                                let bullet = builder.next_bullet(&self.style.unordered_bullets);
+                                let bullet = if let Some((marker_range, checked)) = task_marker {
+                                    self.render_task_checkbox(
+                                        marker_range,
+                                        range.clone(),
+                                        checked,
+                                        tasks_interactive,
+                                    )
+                                } else {
+                                    bullet.into_any_element()
+                                };
+                                builder.push_div(
+                                    div()
+                                        .h_flex()
+                                        .mb(self.style.list_item_spacing)
+                                        .line_height(self.style.line_height)
+                                        .items_start()
+                                        .gap_1()
+                                        .child(bullet),
+                                );
+                                // Without `w_0`, text doesn't wrap to the width of the container.
+                                builder.push_div(div().flex_1().w_0());
+                            }
+                            MarkdownTag::Emphasis => builder.push_text_style(TextStyleRefinement {
+                                font_style: Some(FontStyle::Italic),
+                                ..Default::default()
+                            }),
+                            MarkdownTag::Strong => builder.push_text_style(TextStyleRefinement {
+                                font_weight: Some(FontWeight::BOLD),
+                                ..Default::default()
+                            }),
+                            MarkdownTag::Strikethrough => {
+                                builder.push_text_style(self.style.strikethrough_text_style())
+                            }
+                            MarkdownTag::Link { dest_url, .. } => {
+                                if builder.code_block_stack.is_empty() {
+                                    // Links without a destination render as plain link-styled text.
+                                    if !dest_url.is_empty() {
+                                        builder.push_link(dest_url.clone(), range.clone());
+                                    }
+                                    current_link_url = Some(dest_url.clone());
+                                    // Underlines are painted per visual row in `paint_link_underlines`
+                                    // so that wrapped links don't underline the gap between rows.
+                                    let mut link_style = self.style.link.clone();
+                                    link_style.underline = None;
+                                    builder.push_text_style(link_style)
+                                }
+                            }
+                            // Images with an unsupported source render their alt text instead.
+                            MarkdownTag::Image { dest_url, .. } => {
+                                let image = match self.style.image_max_bytes {
+                                    Some(max_bytes) if is_external_url(dest_url) => {
+                                        self.render_remote_image(dest_url, max_bytes, cx)
+                                    }
+                                    _ => image_source(dest_url)
+                                        .map(|source| self.render_image(source)),
+                                };
+                                if let Some(image) = image {
+                                    builder.push_element(image);
+                                    in_image = true;
+                                }
+                            }
+                            MarkdownTag::FootnoteDefinition(label) => {
+                                builder.push_footnote_definition(label.clone(), range.clone());
+                                builder.push_decoration(format!("[{label}] "), false);
+                                builder.push_div(div().mb(self.style.paragraph_spacing).text_sm());
+                                builder.push_text_style(self.style.footnote.clone());
+                            }
+                            MarkdownTag::Table(alignments) => builder.push_table(
+                                alignments.clone(),
+                                div()
+                                    .v_flex()
+                                    .mb(self.style.paragraph_spacing)
+                                    .border_1()
+                                    .rounded_md()
+                                    .border_color(self.style.table_border_color),
+                            ),
+                            MarkdownTag::TableHead => {
+                                builder.push_table_row(
+                                    div()
+                                        .h_flex()
+                                        .border_b_1()
+                                        .border_color(self.style.table_border_color)
+                                        .when_some(
+                                            self.style.table_header_background_color,
+                                            |div, color| div.bg(color),
+                                        ),
+                                );
+                                builder.push_text_style(TextStyleRefinement {
+                                    font_weight: Some(FontWeight::BOLD),
+                                    ..Default::default()
+                                });
+                            }
+                            MarkdownTag::TableRow => builder.push_table_row(div().h_flex()),
+                            MarkdownTag::TableCell => {
+                                // Cells contain inline events, which are rendered like any
+                                // other paragraph content.
+                                builder.push_table_cell();
+                            }
+                        }
+                    }
+                    MarkdownEvent::End(tag) => match tag {
+                        MarkdownTagEnd::Paragraph => {
+                            builder.pop_div();
+                        }
+                        MarkdownTagEnd::Heading(_) => {
+                            builder.pending_decoration = None;
+                            if mem::take(&mut heading_has_disclosure) {
+                                builder.pop_div();
+                            }
+                            builder.pop_div();
+                            collapsed_level = collapsing_heading.take();
+                        }
+                        MarkdownTagEnd::BlockQuote => {
+                            builder.block_quote_depth -= 1;
+                            builder.pop_text_style();
+                            builder.pop_div()
+                        }
+                        MarkdownTagEnd::CodeBlock => {
+                            code_block_indent = 0;
+                            builder.trim_trailing_newline();
+                            builder.pop_div();
+                            builder.pop_text_style();
+                            builder.pop_code_block();
+                        }
+                        MarkdownTagEnd::MetadataBlock(_) => {
+                            builder.trim_trailing_newline();
+                            builder.pop_div();
+                            builder.pop_text_style();
+                            builder.pop_code_block();
+                        }
+                        MarkdownTagEnd::HtmlBlock => builder.pop_div(),
+                        MarkdownTagEnd::List(_) => {
+                            builder.pop_list();
+                            builder.pop_div();
+                        }
+                        MarkdownTagEnd::Item => {
+                            builder.pop_div();
+                            builder.pop_div();
+                        }
+                        MarkdownTagEnd::Emphasis => builder.pop_text_style(),
+                        MarkdownTagEnd::Strong => builder.pop_text_style(),
+                        MarkdownTagEnd::Strikethrough => builder.pop_text_style(),
+                        MarkdownTagEnd::Link => {
+                            if builder.code_block_stack.is_empty() {
+                                let url = current_link_url.take().unwrap_or_default();
+                                if let Some(icon) = self.style.external_link_icon {
+                                    if is_external_url(&url) {
+                                        builder.push_icon(icon);
+                                    }
+                                }
+                                builder.pop_text_style()
+                            }
+                        }
+                        MarkdownTagEnd::Image => {}
+                        MarkdownTagEnd::FootnoteDefinition => {
+                            builder.pending_decoration = None;
+                            builder.pop_text_style();
+                            builder.pop_div();
+                        }
+                        MarkdownTagEnd::Table => builder.pop_table(),
+                        MarkdownTagEnd::TableHead => {
+                            builder.pop_text_style();
+                            builder.pop_div();
+                        }
+                        MarkdownTagEnd::TableRow => builder.pop_div(),
+                        MarkdownTagEnd::TableCell => builder.pop_div(),
+                    },
+                    MarkdownEvent::Text => {
+                        let text = &parsed_markdown.source[range.clone()];
+                        match self.style.max_link_len {
+                            Some(max_chars) if current_link_url.is_some() => builder
+                                .push_truncated_text(
+                                    text,
+                                    range.start,
+                                    max_chars,
+                                    self.style.link_ellipsis,
+                                ),
+                            _ if code_block_indent > 0 => builder.push_dedented_text(
+                                text,
+                                range.start,
+                                code_block_indent,
+                                self.style.code_tab_size,
+                            ),
+                            _ if !builder.code_block_stack.is_empty() => {
+                                builder.push_code_text(text, range.start, self.style.code_tab_size)
+                            }
+                            _ => builder.push_text(text, range.start),
+                        }
+                    }
+                    MarkdownEvent::SubstitutedText(text) => {
+                        builder.push_substituted_text(text, range.clone());
+                    }
+                    MarkdownEvent::Code => {
+                        // Line endings inside inline code render as spaces. Replacing them
+                        // byte-for-byte keeps the source mapping intact.
+                        let code = parsed_markdown.source[range.clone()].replace(['\r', '\n'], " ");
+                        let language = self
+                            .style
+                            .inline_code_language
+                            .as_deref()
+                            .and_then(|language| self.load_language(language, cx));
+                        let mut code_style = self.style.inline_code.clone();
+                        if self.style.inline_code_padding > Pixels::ZERO {
+                            // The padded background is painted behind the text instead.
+                            code_style.background_color = None;
+                        }
+                        builder.push_inline_code(range.clone());
+                        builder.push_text_style(code_style);
+                        builder.push_highlighted_text(&code, range.start, language.as_ref());
+                        builder.pop_text_style();
+                    }
+                    MarkdownEvent::InlineMath | MarkdownEvent::DisplayMath => {
+                        let display = *event == MarkdownEvent::DisplayMath;
+                        let source = &parsed_markdown.source[range.clone()];
+                        let tex = source.trim();
+                        if let Some(math_renderer) = math_renderer.as_ref() {
+                            let element = math_renderer(tex, display);
+                            builder.push_element(if display {
+                                h_flex()
+                                    .w_full()
+                                    .justify_center()
+                                    .child(element)
+                                    .into_any_element()
+                            } else {
+                                element
+                            });
+                        } else {
+                            let tex_start = range.start + source.len() - source.trim_start().len();
+                            builder.push_text_style(self.style.inline_code.clone());
+                            builder.push_text(tex, tex_start);
+                            builder.pop_text_style();
+                        }
+                    }
+                    MarkdownEvent::Html | MarkdownEvent::InlineHtml
+                        if self.style.render_inline_html =>
+                    {
+                        builder.push_html(
+                            &parsed_markdown.source[range.clone()],
+                            range.start,
+                            depth,
+                            &self.style,
+                        );
+                    }
+                    MarkdownEvent::Html => {
+                        builder.push_text(&parsed_markdown.source[range.clone()], range.start);
+                    }
+                    MarkdownEvent::InlineHtml => {
+                        builder.push_text(&parsed_markdown.source[range.clone()], range.start);
+                    }
+                    MarkdownEvent::Rule => {
+                        let rule = div()
+                            .border_b_1()
+                            .my_2()
+                            .border_color(self.style.rule_color);
+                        builder.push_div(if self.style.full_bleed_blocks && is_top_level {
+                            self.bleed_into_padding(rule)
+                        } else {
+                            rule
+                        });
+                        builder.pop_div()
+                    }
+                    MarkdownEvent::SoftBreak => builder.push_text("\n", range.start),
+                    MarkdownEvent::HardBreak => builder.push_text("\n", range.start),
+                    MarkdownEvent::FootnoteReference => {
+                        // Render `[^label]` as `[label]`, keeping each piece mapped to the source.
+                        let source = &parsed_markdown.source[range.clone()];
+                        let label = source
+                            .strip_prefix("[^")
+                            .and_then(|source| source.strip_suffix(']'))
+                            .unwrap_or(source);
+                        builder.push_footnote_reference(label.into(), range.clone());
+                        // Clicking a reference scrolls to its definition.
+                        builder.push_link(
+                            format!("{FOOTNOTE_ANCHOR_PREFIX}{label}").into(),
+                            range.clone(),
+                        );
+                        builder.push_text_style(self.style.link.clone());
+                        builder.push_text("[", range.start);
+                        builder.push_text(label, range.start + 2);
+                        builder.push_text("]", range.end - 1);
+                        builder.pop_text_style();
+                    }
+                    // Rendered as a checkbox in place of the item's bullet.
+                    MarkdownEvent::TaskListMarker(_) => {}
+                }
+
+                if closes_column {
+                    builder.pop_div();
+                }
+            }
+
+            let mut rendered_markdown = builder.build();
+            let child_layout_id = rendered_markdown.element.request_layout(cx);
+            let expansion = self.markdown.as_ref().and_then(|markdown| {
+                let markdown = markdown.read(cx);
+                Some((markdown.max_height?, markdown.expanded))
+            });
+            let layout_id = if let Some((max_height, expanded)) = expansion {
+                let collapsed = !expanded;
+                let mut toggle = self.render_expansion_toggle(collapsed);
+                let toggle_layout_id = toggle.request_layout(cx);
+                let mut style = Style::default();
+                if collapsed {
+                    style.max_size.height = max_height.into();
+                    style.overflow.y = Overflow::Hidden;
+                }
+                rendered_markdown.expansion_toggle = Some(ExpansionToggle {
+                    element: toggle,
+                    content_layout_id: child_layout_id,
+                    collapsed,
+                    truncated: false,
+                });
+                cx.request_layout(style, [child_layout_id, toggle_layout_id])
+            } else {
+                cx.request_layout(Style::default(), [child_layout_id])
+            };
+            (layout_id, rendered_markdown)
+        })
+    }
+
+    fn prepaint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        bounds: Bounds<Pixels>,
+        rendered_markdown: &mut Self::RequestLayoutState,
+        cx: &mut WindowContext,
+    ) -> Self::PrepaintState {
+        with_font_scale(self.style.font_scale, cx, |cx| {
+            let hitbox = cx.insert_hitbox(bounds, false);
+            rendered_markdown.element.prepaint(cx);
+            if let Some(markdown) = self.markdown.as_ref() {
+                if let Some((tooltip, position)) = markdown.read(cx).link_tooltip.clone() {
+                    cx.defer_draw(tooltip.into_any_element(), position, 1);
+                }
+                if let Some(context_menu) = markdown.read(cx).context_menu.as_ref() {
+                    let (menu, position) = (context_menu.menu.clone(), context_menu.position);
+                    cx.defer_draw(menu.into_any_element(), position, 2);
+                }
+            }
+            if let Some(toggle) = rendered_markdown.expansion_toggle.as_mut() {
+                let content_height = cx.layout_bounds(toggle.content_layout_id).size.height;
+                toggle.truncated = toggle.collapsed && content_height > bounds.size.height;
+                if toggle.truncated || !toggle.collapsed {
+                    toggle.element.prepaint(cx);
+                }
+            }
+            self.autoscroll(&rendered_markdown.text, cx);
+            hitbox
+        })
+    }
+
+    fn paint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        bounds: Bounds<Pixels>,
+        rendered_markdown: &mut Self::RequestLayoutState,
+        hitbox: &mut Self::PrepaintState,
+        cx: &mut WindowContext,
+    ) {
+        with_font_scale(self.style.font_scale, cx, |cx| {
+            if let Some(view) = self.markdown.clone() {
+                let focus_handle = view.read(cx).focus_handle.clone();
+                cx.set_focus_handle(&focus_handle);
+
+                let mut context = KeyContext::default();
+                context.add("Markdown");
+                cx.set_key_context(context);
+                cx.on_action(std::any::TypeId::of::<crate::Copy>(), {
+                    let view = view.clone();
+                    let text = rendered_markdown.text.clone();
+                    move |_, phase, cx| {
+                        let text = text.clone();
+                        if phase == DispatchPhase::Bubble {
+                            view.update(cx, move |this, cx| this.copy(&text, cx))
+                        }
+                    }
+                });
+                cx.on_action(std::any::TypeId::of::<crate::CopyAsMarkdown>(), {
+                    let view = view.clone();
+                    move |_, phase, cx| {
+                        if phase == DispatchPhase::Bubble {
+                            view.update(cx, |this, cx| this.copy_as_markdown(cx))
+                        }
+                    }
+                });
+                self.on_move_selection_head::<SelectLeft>(
+                    &rendered_markdown.text,
+                    RenderedText::previous_source_index,
+                    cx,
+                );
+                self.on_move_selection_head::<SelectRight>(
+                    &rendered_markdown.text,
+                    RenderedText::next_source_index,
+                    cx,
+                );
+                self.on_move_selection_head::<SelectUp>(
+                    &rendered_markdown.text,
+                    RenderedText::source_index_above,
+                    cx,
+                );
+                self.on_move_selection_head::<SelectDown>(
+                    &rendered_markdown.text,
+                    RenderedText::source_index_below,
+                    cx,
+                );
+                self.on_move_selection_head::<SelectToBeginningOfLine>(
+                    &rendered_markdown.text,
+                    RenderedText::row_start_source_index,
+                    cx,
+                );
+                self.on_move_selection_head::<SelectToEndOfLine>(
+                    &rendered_markdown.text,
+                    RenderedText::row_end_source_index,
+                    cx,
+                );
+                cx.on_action(std::any::TypeId::of::<crate::SelectAll>(), {
+                    let view = view.clone();
+                    let text = rendered_markdown.text.clone();
+                    move |_, phase, cx| {
+                        if phase == DispatchPhase::Bubble {
+                            view.update(cx, |this, cx| this.select_all(&text, cx))
+                        }
+                    }
+                });
+
+                self.paint_mouse_listeners(hitbox, &rendered_markdown.text, cx);
+            }
+            let truncated = rendered_markdown
+                .expansion_toggle
+                .as_ref()
+                .map_or(false, |toggle| toggle.truncated);
+            let mut cursor_bounds = None;
+            let mut hovered_line_bounds = None;
+            let mut inline_code_background_bounds = Vec::new();
+            cx.with_content_mask(truncated.then_some(ContentMask { bounds }), |cx| {
+                // Painted first, so that the text is drawn over them.
+                hovered_line_bounds = self.paint_hovered_line(&rendered_markdown.text, cx);
+                inline_code_background_bounds =
+                    self.paint_inline_code_backgrounds(&rendered_markdown.text, cx);
+                rendered_markdown.element.paint(cx);
+                self.paint_link_underlines(&rendered_markdown.text, cx);
+                self.paint_icons(&rendered_markdown.text, cx);
+                self.paint_soft_hyphens(&rendered_markdown.text, cx);
+                self.paint_search_matches(bounds, &rendered_markdown.text, cx);
+                self.paint_selection(bounds, &rendered_markdown.text, cx);
+                cursor_bounds = self.paint_cursor(&rendered_markdown.text, cx);
+            });
+            if let Some(toggle) = rendered_markdown.expansion_toggle.as_mut() {
+                if toggle.truncated {
+                    self.paint_truncation_fade(bounds, cx);
+                }
+                if toggle.truncated || !toggle.collapsed {
+                    toggle.element.paint(cx);
+                }
+            }
+            if let Some(markdown) = self.markdown.as_ref() {
+                markdown.update(cx, |markdown, _| {
+                    markdown.rendered_text = Some(rendered_markdown.text.clone());
+                    markdown.cursor_bounds = cursor_bounds;
+                    markdown.hovered_line_bounds = hovered_line_bounds;
+                    markdown.inline_code_background_bounds = inline_code_background_bounds;
+                    markdown.update_search_matches();
+                });
+            }
+        })
+    }
+}
+
+/// Runs `f` with the rem size and the base font size multiplied by `scale`, so
+/// that all text and rem-based spacing scale proportionally.
+fn with_font_scale<R>(
+    scale: f32,
+    cx: &mut WindowContext,
+    f: impl FnOnce(&mut WindowContext) -> R,
+) -> R {
+    if scale == 1. {
+        return f(cx);
+    }
+
+    let (rem_size, font_size) = scaled_text_sizes(cx.rem_size(), cx.text_style().font_size, scale);
+    cx.with_rem_size(Some(rem_size), |cx| {
+        cx.with_text_style(
+            Some(TextStyleRefinement {
+                font_size: Some(font_size),
+                ..Default::default()
+            }),
+            f,
+        )
+    })
+}
+
+/// Returns the rem size and font size to use when scaling text by `scale`.
+/// Rem-based font sizes already scale with the rem size.
+fn scaled_text_sizes(
+    rem_size: Pixels,
+    font_size: AbsoluteLength,
+    scale: f32,
+) -> (Pixels, AbsoluteLength) {
+    let font_size = match font_size {
+        AbsoluteLength::Pixels(font_size) => AbsoluteLength::Pixels(font_size * scale),
+        AbsoluteLength::Rems(_) => font_size,
+    };
+    (rem_size * scale, font_size)
+}
+
+impl IntoElement for MarkdownElement {
+    type Element = Self;
+
+    fn into_element(self) -> Self::Element {
+        self
+    }
+}
+
+/// Renders markdown without a [`Markdown`] view, for one-off content like
+/// tooltips and list items. The source is parsed synchronously and laid out
+/// like a view's, using `style` with `syntax` in place of its syntax theme.
+///
+/// Nothing in the element is interactive: links aren't clickable, text can't
+/// be selected, headings and front matter can't be collapsed, and tasks can't
+/// be toggled. Without a language registry, code isn't syntax highlighted.
+pub fn render_markdown_static(
+    source: &str,
+    style: &MarkdownStyle,
+    syntax: Arc<SyntaxTheme>,
+) -> AnyElement {
+    let parsed_markdown = ParsedMarkdown::parse(
+        SharedString::from(source.to_string()),
+        style.parse_options(),
+    );
+    MarkdownElement {
+        markdown: None,
+        parsed_markdown,
+        style: MarkdownStyle {
+            syntax,
+            ..style.clone()
+        },
+        language_registry: None,
+    }
+    .into_any_element()
+}
+
+struct MarkdownElementBuilder {
+    div_stack: Vec<Div>,
+    rendered_lines: Vec<RenderedLine>,
+    pending_line: PendingLine,
+    rendered_links: Vec<RenderedLink>,
+    /// The source ranges of inline code.
+    rendered_inline_code: Vec<Range<usize>>,
+    rendered_footnote_references: Vec<RenderedFootnoteReference>,
+    footnote_definitions: Vec<(SharedString, Range<usize>)>,
+    /// The source ranges of list items and block quotes.
+    rendered_blocks: Vec<Range<usize>>,
+    current_source_index: usize,
+    base_text_style: TextStyle,
+    text_style_stack: Vec<TextStyleRefinement>,
+    code_block_stack: Vec<Option<Arc<Language>>>,
+    list_stack: Vec<ListStackEntry>,
+    heading_numbering: HeadingNumbering,
+    pending_decoration: Option<(String, bool)>,
+    table_stack: Vec<TableState>,
+    /// Inline HTML elements that are open, with the depth of their enclosing block.
+    html_element_stack: Vec<(HtmlElement, usize)>,
+    /// The number of block quotes, including callouts, that are open.
+    block_quote_depth: usize,
+    block_quote_hanging_indent: Option<Pixels>,
+    copy_separator: Option<String>,
+    syntax_theme: Arc<SyntaxTheme>,
+    /// The source range of the selection, and the color its text is rendered in.
+    selection_text_color: Option<(Range<usize>, Hsla)>,
+    soft_hyphens: bool,
+}
+
+#[derive(Default)]
+struct PendingLine {
+    text: String,
+    runs: Vec<TextRun>,
+    source_mappings: Vec<SourceMapping>,
+    decorations: Vec<Range<usize>>,
+    icons: Vec<(Range<usize>, IconName)>,
+    /// The rendered indices of hidden soft hyphens, with the style they're painted in.
+    soft_hyphens: Vec<(usize, TextStyle)>,
+}
+
+struct TableState {
+    alignments: Vec<Alignment>,
+    column: usize,
+}
+
+/// Tracks the outline number of each heading level, e.g. `1.2.1`.
+#[derive(Default)]
+struct HeadingNumbering {
+    counters: [u32; 6],
+    top_level: Option<usize>,
+}
+
+impl HeadingNumbering {
+    fn next(&mut self, level: pulldown_cmark::HeadingLevel) -> String {
+        let level = level as usize - 1;
+        let top_level = self
+            .top_level
+            .map_or(level, |top_level| top_level.min(level));
+        self.top_level = Some(top_level);
+
+        self.counters[level] += 1;
+        for counter in &mut self.counters[level + 1..] {
+            *counter = 0;
+        }
+        // Skipped levels (e.g. an H3 directly below an H1) are numbered as 1.
+        for counter in &mut self.counters[top_level..level] {
+            if *counter == 0 {
+                *counter = 1;
+            }
+        }
+
+        self.counters[top_level..=level]
+            .iter()
+            .map(|counter| counter.to_string())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+struct ListStackEntry {
+    bullet_index: Option<u64>,
+}
+
+impl MarkdownElementBuilder {
+    fn new(base_text_style: TextStyle, syntax_theme: Arc<SyntaxTheme>, root: Div) -> Self {
+        Self {
+            div_stack: vec![root],
+            rendered_lines: Vec::new(),
+            pending_line: PendingLine::default(),
+            rendered_links: Vec::new(),
+            rendered_inline_code: Vec::new(),
+            rendered_footnote_references: Vec::new(),
+            footnote_definitions: Vec::new(),
+            rendered_blocks: Vec::new(),
+            current_source_index: 0,
+            base_text_style,
+            text_style_stack: Vec::new(),
+            code_block_stack: Vec::new(),
+            list_stack: Vec::new(),
+            heading_numbering: HeadingNumbering::default(),
+            pending_decoration: None,
+            table_stack: Vec::new(),
+            html_element_stack: Vec::new(),
+            block_quote_depth: 0,
+            block_quote_hanging_indent: None,
+            copy_separator: None,
+            syntax_theme,
+            selection_text_color: None,
+            soft_hyphens: false,
+        }
+    }
+
+    fn push_text_style(&mut self, style: TextStyleRefinement) {
+        self.text_style_stack.push(style);
+    }
+
+    fn text_style(&self) -> TextStyle {
+        let mut style = self.base_text_style.clone();
+        for refinement in &self.text_style_stack {
+            style.refine(refinement);
+        }
+        style
+    }
+
+    fn pop_text_style(&mut self) {
+        self.text_style_stack.pop();
+    }
+
+    fn push_div(&mut self, div: Div) {
+        self.flush_text();
+        self.div_stack.push(div);
+    }
+
+    fn pop_div(&mut self) {
+        self.flush_text();
+        let div = self.div_stack.pop().unwrap().into_any();
+        self.div_stack.last_mut().unwrap().extend(iter::once(div));
+    }
+
+    /// Appends an element, such as an image, to the current div after the
+    /// preceding text.
+    fn push_element(&mut self, element: AnyElement) {
+        self.flush_text();
+        self.div_stack
+            .last_mut()
+            .unwrap()
+            .extend(iter::once(element));
+    }
+
+    fn push_list(&mut self, bullet_index: Option<u64>) {
+        self.list_stack.push(ListStackEntry { bullet_index });
+    }
+
+    /// Returns the bullet for the next item of the innermost list, e.g. `3.` for
+    /// the first item of an ordered list starting at 3. Unordered items take
+    /// their bullet from `unordered_bullets` by the list's nesting depth.
+    fn next_bullet(&mut self, unordered_bullets: &[SharedString]) -> SharedString {
+        let bullet_index = self.list_stack.last_mut().and_then(|entry| {
+            let item_index = entry.bullet_index.as_mut()?;
+            *item_index += 1;
+            Some(*item_index - 1)
+        });
+        match bullet_index {
+            Some(bullet_index) => format!("{}.", bullet_index).into(),
+            None if unordered_bullets.is_empty() => "•".into(),
+            None => {
+                let depth = self.list_stack.len().saturating_sub(1);
+                unordered_bullets[depth % unordered_bullets.len()].clone()
+            }
+        }
+    }
+
+    fn pop_list(&mut self) {
+        self.list_stack.pop();
+    }
+
+    fn push_code_block(&mut self, language: Option<Arc<Language>>) {
+        self.code_block_stack.push(language);
+    }
+
+    fn pop_code_block(&mut self) {
+        self.code_block_stack.pop();
+    }
+
+    fn push_link(&mut self, destination_url: SharedString, source_range: Range<usize>) {
+        self.rendered_links.push(RenderedLink {
+            source_range,
+            destination_url,
+        });
+    }
+
+    fn push_inline_code(&mut self, source_range: Range<usize>) {
+        self.rendered_inline_code.push(source_range);
+    }
+
+    /// Pushes HTML, styling the text between the tags that
+    /// [`MarkdownStyle::render_inline_html`] supports and showing other tags as
+    /// they are. Elements left open end with their enclosing block, at `depth`.
+    fn push_html(&mut self, html: &str, source_index: usize, depth: usize, style: &MarkdownStyle) {
+        let mut text_start = 0;
+        for (tag_range, tag) in html_tags(html) {
+            // Stray end tags are shown as they are, along with the text around them.
+            if let HtmlTag::End(element) = tag {
+                if self.html_element_stack.last().map(|(open, _)| *open) != Some(element) {
+                    continue;
+                }
+            }
+
+            if text_start < tag_range.start {
+                self.push_text(
+                    &html[text_start..tag_range.start],
+                    source_index + text_start,
+                );
+            }
+            match tag {
+                HtmlTag::LineBreak => self.push_text("\n", source_index + tag_range.start),
+                HtmlTag::Start(element) => {
+                    self.push_text_style(style.html_element_text_style(element));
+                    self.html_element_stack.push((element, depth));
+                }
+                HtmlTag::End(_) => {
+                    self.html_element_stack.pop();
+                    self.pop_text_style();
+                }
+                HtmlTag::Unsupported => {
+                    self.push_text(&html[tag_range.clone()], source_index + tag_range.start)
+                }
+            }
+            text_start = tag_range.end;
+        }
+        if text_start < html.len() {
+            self.push_text(&html[text_start..], source_index + text_start);
+        }
+    }
+
+    /// Ends the HTML elements left open within the block ending at `depth`.
+    fn pop_html_elements(&mut self, depth: usize) {
+        while self
+            .html_element_stack
+            .last()
+            .map_or(false, |(_, element_depth)| *element_depth > depth)
+        {
+            self.html_element_stack.pop();
+            self.pop_text_style();
+        }
+    }
+
+    fn push_footnote_reference(&mut self, label: SharedString, source_range: Range<usize>) {
+        self.rendered_footnote_references
+            .push(RenderedFootnoteReference {
+                source_range,
+                label,
+            });
+    }
+
+    fn push_footnote_definition(&mut self, label: SharedString, source_range: Range<usize>) {
+        self.footnote_definitions.push((label, source_range));
+    }
+
+    /// Records a list item or block quote, which quadruple-clicking selects.
+    fn push_block(&mut self, source_range: Range<usize>) {
+        self.rendered_blocks.push(source_range);
+    }
+
+    /// Queues text that isn't backed by the source (e.g. a heading number) to be
+    /// rendered in front of the next piece of text.
+    fn push_decoration(&mut self, text: String, include_in_copy: bool) {
+        self.pending_decoration = Some((text, include_in_copy));
+    }
+
+    /// Appends text that isn't backed by the source to the pending line, returning
+    /// its rendered range.
+    fn append_decoration(&mut self, text: &str, include_in_copy: bool) -> Range<usize> {
+        let start = self.pending_line.text.len();
+        self.pending_line.text.push_str(text);
+        self.pending_line
+            .runs
+            .push(self.text_style().to_run(text.len()));
+        let range = start..self.pending_line.text.len();
+        if !include_in_copy {
+            self.pending_line.decorations.push(range.clone());
+        }
+        range
+    }
+
+    /// Reserves space after the current text for an icon that's painted in place.
+    fn push_icon(&mut self, icon: IconName) {
+        let range = self.append_decoration("\u{2003}", false);
+        self.pending_line.icons.push((range, icon));
+    }
+
+    /// Pushes text that's rendered in place of the given source range, like a
+    /// decoded HTML entity. Selections treat it as a single unit.
+    fn push_substituted_text(&mut self, text: &str, source_range: Range<usize>) {
+        self.push_text(text, source_range.start);
+        if let Some(mapping) = self.pending_line.source_mappings.last_mut() {
+            mapping.substituted = true;
+        }
+        self.pending_line.source_mappings.push(SourceMapping {
+            rendered_index: self.pending_line.text.len(),
+            source_index: source_range.end,
+            substituted: false,
+        });
+        self.current_source_index = source_range.end;
+    }
+
+    fn push_text(&mut self, text: &str, source_index: usize) {
+        let language = self.code_block_stack.last().cloned().flatten();
+        self.push_highlighted_text(text, source_index, language.as_ref());
+    }
+
+    /// Pushes text, syntax highlighted on top of the current text style if a
+    /// language is given.
+    fn push_highlighted_text(
+        &mut self,
+        text: &str,
+        source_index: usize,
+        language: Option<&Arc<Language>>,
+    ) {
+        if let Some((decoration, include_in_copy)) = self.pending_decoration.take() {
+            self.append_decoration(&decoration, include_in_copy);
+        }
+
+        let rendered_start = self.pending_line.text.len();
+        self.pending_line.source_mappings.push(SourceMapping {
+            rendered_index: rendered_start,
+            source_index,
+            substituted: false,
+        });
+        self.pending_line.text.push_str(text);
+        self.current_source_index = source_index + text.len();
+
+        if let Some(language) = language {
+            let mut offset = 0;
+            for (range, highlight_id) in language.highlight_text(&Rope::from(text), 0..text.len()) {
+                if range.start > offset {
+                    self.pending_line
+                        .runs
+                        .push(self.text_style().to_run(range.start - offset));
+                }
+
+                let mut run_style = self.text_style();
+                if let Some(highlight) = highlight_id.style(&self.syntax_theme) {
+                    run_style = run_style.highlight(highlight);
+                }
+                self.pending_line.runs.push(run_style.to_run(range.len()));
+                offset = range.end;
+            }
+
+            if offset < text.len() {
+                self.pending_line
+                    .runs
+                    .push(self.text_style().to_run(text.len() - offset));
+            }
+        } else {
+            self.pending_line
+                .runs
+                .push(self.text_style().to_run(text.len()));
+        }
+
+        if let Some((selection, color)) = self.selection_text_color.clone() {
+            let source_range = source_index..source_index + text.len();
+            let start = selection.start.clamp(source_range.start, source_range.end);
+            let end = selection.end.clamp(source_range.start, source_range.end);
+            if start < end {
+                recolor_runs(
+                    &mut self.pending_line.runs,
+                    rendered_start + start - source_index..rendered_start + end - source_index,
+                    color,
+                );
+            }
+        }
+
+        // Soft hyphens stay in the text, where they let the line wrap, but are
+        // only painted if it does.
+        if self.soft_hyphens {
+            for (offset, soft_hyphen) in text.match_indices(SOFT_HYPHEN) {
+                let index = rendered_start + offset;
+                recolor_runs(
+                    &mut self.pending_line.runs,
+                    index..index + soft_hyphen.len(),
+                    Hsla::transparent_black(),
+                );
+                self.pending_line
+                    .soft_hyphens
+                    .push((index, self.text_style()));
+            }
+        }
+    }
+
+    /// Pushes code block text with each tab expanded to `tab_size` spaces. Like
+    /// substituted text, the spaces map to their tab in the source as a unit.
+    fn push_code_text(&mut self, text: &str, source_index: usize, tab_size: usize) {
+        if !text.contains('\t') {
+            self.push_text(text, source_index);
+            return;
+        }
+
+        let tab_size = tab_size.max(1);
+        self.push_text(&text.replace('\t', &" ".repeat(tab_size)), source_index);
+        let rendered_start = self
+            .pending_line
+            .source_mappings
+            .last()
+            .unwrap()
+            .rendered_index;
+        for (tab_ix, (offset, _)) in text.match_indices('\t').enumerate() {
+            let rendered_index = rendered_start + offset + tab_ix * (tab_size - 1);
+            match self.pending_line.source_mappings.last_mut() {
+                Some(mapping) if mapping.rendered_index == rendered_index => {
+                    mapping.substituted = true;
+                }
+                _ => self.pending_line.source_mappings.push(SourceMapping {
+                    rendered_index,
+                    source_index: source_index + offset,
+                    substituted: true,
+                }),
+            }
+            if offset + 1 < text.len() {
+                self.pending_line.source_mappings.push(SourceMapping {
+                    rendered_index: rendered_index + tab_size,
+                    source_index: source_index + offset + 1,
+                    substituted: false,
+                });
+            }
+        }
+    }
+
+    /// Pushes code block text with up to `indent` leading whitespace characters
+    /// removed from each line. The remaining text stays mapped to its source.
+    fn push_dedented_text(
+        &mut self,
+        text: &str,
+        source_index: usize,
+        indent: usize,
+        tab_size: usize,
+    ) {
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            let mut start = offset;
+            offset += line.len();
+            if self.pending_line.text.is_empty() || self.pending_line.text.ends_with('\n') {
+                start += line
+                    .bytes()
+                    .take(indent)
+                    .take_while(|byte| matches!(byte, b' ' | b'\t'))
+                    .count();
+            }
+            if start < offset {
+                self.push_code_text(&text[start..offset], source_index + start, tab_size);
+            }
+        }
+    }
+
+    /// Pushes text shortened to `max_chars` characters, with the visible parts
+    /// still mapped to their source offsets.
+    fn push_truncated_text(
+        &mut self,
+        text: &str,
+        source_index: usize,
+        max_chars: usize,
+        mode: EllipsisMode,
+    ) {
+        let Some((head, tail)) = truncated_ranges(text, max_chars, mode) else {
+            self.push_text(text, source_index);
+            return;
+        };
+
+        self.push_text(&text[head.clone()], source_index + head.start);
+        self.append_decoration("…", true);
+        if !tail.is_empty() {
+            self.push_text(&text[tail.clone()], source_index + tail.start);
+        }
+    }
+
+    fn trim_trailing_newline(&mut self) {
+        if self.pending_line.text.ends_with('\n') {
+            self.pending_line
+                .text
+                .truncate(self.pending_line.text.len() - 1);
+            self.pending_line.runs.last_mut().unwrap().len -= 1;
+            self.current_source_index -= 1;
+        }
+    }
+
+    fn push_table(&mut self, alignments: Vec<Alignment>, div: Div) {
+        self.table_stack.push(TableState {
+            alignments,
+            column: 0,
+        });
+        self.push_div(div);
+    }
+
+    fn pop_table(&mut self) {
+        self.pop_div();
+        self.table_stack.pop();
+        self.copy_separator = None;
+    }
+
+    fn push_table_row(&mut self, div: Div) {
+        self.push_div(div);
+        if let Some(table) = self.table_stack.last_mut() {
+            table.column = 0;
+        }
+        self.copy_separator = None;
+    }
+
+    /// Pushes a div for the next cell in the current row, aligned according to
+    /// its column. Returns that alignment.
+    fn push_table_cell(&mut self) -> Alignment {
+        let mut alignment = Alignment::None;
+        let mut is_first_column = true;
+        if let Some(table) = self.table_stack.last_mut() {
+            alignment = table
+                .alignments
+                .get(table.column)
+                .copied()
+                .unwrap_or(Alignment::None);
+            is_first_column = table.column == 0;
+            table.column += 1;
+        }
+
+        // Without `w_0`, text doesn't wrap to the width of the container.
+        let cell = div().flex_1().w_0().px_2().py_1();
+        self.push_div(match alignment {
+            Alignment::None | Alignment::Left => cell,
+            Alignment::Center => cell.flex().justify_center(),
+            Alignment::Right => cell.flex().justify_end(),
+        });
+
+        // Cells are copied as tab-separated text. Empty cells don't render a
+        // line, so their separators carry over to the next cell.
+        if !is_first_column {
+            self.copy_separator
+                .get_or_insert_with(String::new)
+                .push('\t');
+        }
+        alignment
+    }
+
+    fn flush_text(&mut self) {
+        let line = mem::take(&mut self.pending_line);
+        if line.text.is_empty() {
+            return;
+        }
+
+        let soft_hyphens = line
+            .soft_hyphens
+            .into_iter()
+            .filter_map(|(index, style)| {
+                let next_char_start = index + SOFT_HYPHEN.len_utf8();
+                let next_char = line.text[next_char_start..].chars().next()?;
+                Some(SoftHyphen {
+                    index,
+                    next_char_end: next_char_start + next_char.len_utf8(),
+                    style,
+                })
+            })
+            .collect();
+        let mut text = StyledText::new(line.text).with_runs(line.runs);
+        if let Some(indent) = self
+            .block_quote_hanging_indent
+            .filter(|_| self.block_quote_depth > 0)
+        {
+            text = text.with_wrap_indent(indent);
+        }
+        self.rendered_lines.push(RenderedLine {
+            layout: text.layout().clone(),
+            source_mappings: line.source_mappings,
+            source_end: self.current_source_index,
+            decorations: line.decorations,
+            icons: line.icons,
+            soft_hyphens,
+            copy_separator: self.copy_separator.take(),
+        });
+        self.div_stack.last_mut().unwrap().extend([text.into_any()]);
+    }
+
+    fn build(mut self) -> RenderedMarkdown {
+        debug_assert_eq!(self.div_stack.len(), 1);
+        self.flush_text();
+        RenderedMarkdown {
+            element: self.div_stack.pop().unwrap().into_any(),
+            text: RenderedText {
+                lines: self.rendered_lines.into(),
+                links: self.rendered_links.into(),
+                inline_code: self.rendered_inline_code.into(),
+                footnote_references: self.rendered_footnote_references.into(),
+                footnote_definitions: self.footnote_definitions.into(),
+                blocks: self.rendered_blocks.into(),
+            },
+            expansion_toggle: None,
+        }
+    }
+}
+
+/// Returns the byte ranges of `text` that remain visible on either side of an
+/// ellipsis when it's truncated to `max_chars` characters, or `None` if it fits.
+fn truncated_ranges(
+    text: &str,
+    max_chars: usize,
+    mode: EllipsisMode,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let char_count = text.chars().count();
+    if char_count <= max_chars {
+        return None;
+    }
+
+    let visible_chars = max_chars.saturating_sub(1);
+    let (head_chars, tail_chars) = match mode {
+        EllipsisMode::Start => (0, visible_chars),
+        EllipsisMode::Middle => (visible_chars - visible_chars / 2, visible_chars / 2),
+        EllipsisMode::End => (visible_chars, 0),
+    };
+    let byte_offset = |char_ix: usize| {
+        text.char_indices()
+            .nth(char_ix)
+            .map_or(text.len(), |(ix, _)| ix)
+    };
+    Some((
+        0..byte_offset(head_chars),
+        byte_offset(char_count - tail_chars)..text.len(),
+    ))
+}
+
+/// The source range of a code block's contents, given the events following its
+/// start tag.
+fn code_block_contents(events: &[(Range<usize>, MarkdownEvent)]) -> Range<usize> {
+    let mut contents: Option<Range<usize>> = None;
+    for (range, event) in events {
+        if let MarkdownEvent::End(MarkdownTagEnd::CodeBlock) = event {
+            break;
+        }
+        contents = Some(contents.map_or(range.clone(), |contents| contents.start..range.end));
+    }
+    contents.unwrap_or_default()
+}
+
+/// Whether the text consists of Chinese or Japanese characters, which aren't
+/// separated by spaces.
+fn is_cjk(text: &str) -> bool {
+    text.chars().all(|ch| {
+        matches!(
+            ch,
+            '\u{3040}'..='\u{30ff}'
+                | '\u{31f0}'..='\u{31ff}'
+                | '\u{3400}'..='\u{4dbf}'
+                | '\u{4e00}'..='\u{9fff}'
+                | '\u{f900}'..='\u{faff}'
+                | '\u{20000}'..='\u{2fa1f}'
+        )
+    })
+}
+
+/// The number of leading whitespace characters shared by the non-blank lines
+/// of a code block, given the events following its start.
+fn common_indent(source: &str, events: &[(Range<usize>, MarkdownEvent)]) -> usize {
+    let mut contents = String::new();
+    for (range, event) in events {
+        match event {
+            MarkdownEvent::End(MarkdownTagEnd::CodeBlock) => break,
+            MarkdownEvent::Text => contents.push_str(&source[range.clone()]),
+            _ => {}
+        }
+    }
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0)
+}
+
+/// Where to load an image from, or `None` if its URL isn't supported. Relative
+/// paths aren't supported, since there's no base path to resolve them against.
+fn image_source(url: &str) -> Option<ImageSource> {
+    if let Some(path) = url.strip_prefix("file://") {
+        Some(ImageSource::File(Arc::new(PathBuf::from(path))))
+    } else if is_external_url(url) || url.starts_with("data:") {
+        Some(ImageSource::from(url.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Fetches a remote image for [`MarkdownStyle::image_max_bytes`], giving up as
+/// soon as the response turns out to be larger than `max_bytes`.
+async fn fetch_capped_image(
+    client: Arc<dyn HttpClient>,
+    url: SharedString,
+    max_bytes: usize,
+) -> anyhow::Result<RemoteImage> {
+    let mut response = client.get(url.as_ref(), ().into(), true).await?;
+    anyhow::ensure!(
+        response.status().is_success(),
+        "unexpected status {} fetching {url}",
+        response.status()
+    );
+    let content_length = response
+        .headers()
+        .get("Content-Length")
+        .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+    if content_length.map_or(false, |length| length > max_bytes) {
+        return Ok(RemoteImage::TooLarge);
+    }
+
+    // Read at most one byte past the limit, to tell whether the body exceeds it
+    // without downloading the rest.
+    let mut body = Vec::new();
+    response
+        .body_mut()
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut body)
+        .await?;
+    if body.len() > max_bytes {
+        return Ok(RemoteImage::TooLarge);
+    }
+
+    // `img` renders other formats, like SVG, from the URL.
+    let Ok(format) = image::guess_format(&body) else {
+        return Ok(RemoteImage::Uncapped);
+    };
+    let image = image::load_from_memory_with_format(&body, format)?.into_bgra8();
+    Ok(RemoteImage::Loaded(Arc::new(ImageData::new(image))))
+}
+
+/// How close to the top or bottom of the visible text a drag has to be held to
+/// keep extending the selection, and how often it's extended.
+const DRAG_AUTOSCROLL_MARGIN: Pixels = px(16.);
+const DRAG_AUTOSCROLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+const CURSOR_WIDTH: Pixels = px(2.);
+
+const SOFT_HYPHEN: char = '\u{ad}';
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AutoscrollDirection {
+    Up,
+    Down,
+}
+
+/// The destination of the synthetic links from footnote references to their
+/// definitions, followed by the footnote's label.
+const FOOTNOTE_ANCHOR_PREFIX: &str = "#fn-";
+
+/// The kinds of GitHub-style alerts, which are block quotes starting with a
+/// marker like `[!NOTE]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CalloutKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl CalloutKind {
+    fn title(self) -> &'static str {
+        match self {
+            CalloutKind::Note => "Note",
+            CalloutKind::Tip => "Tip",
+            CalloutKind::Important => "Important",
+            CalloutKind::Warning => "Warning",
+            CalloutKind::Caution => "Caution",
+        }
+    }
+
+    fn icon(self) -> IconName {
+        match self {
+            CalloutKind::Note => IconName::Pencil,
+            CalloutKind::Tip => IconName::Sparkle,
+            CalloutKind::Important => IconName::MessageBubbles,
+            CalloutKind::Warning => IconName::ExclamationTriangle,
+            CalloutKind::Caution => IconName::XCircle,
+        }
+    }
+}
+
+/// Inline HTML elements rendered when [`MarkdownStyle::render_inline_html`] is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HtmlElement {
+    Bold,
+    Italic,
+    Subscript,
+    Superscript,
+    Code,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HtmlTag {
+    LineBreak,
+    Start(HtmlElement),
+    End(HtmlElement),
+    /// A tag that's shown as it's written.
+    Unsupported,
+}
+
+/// Returns the tags in `html` and their ranges, skipping comments and other
+/// markup that isn't a tag.
+fn html_tags(html: &str) -> Vec<(Range<usize>, HtmlTag)> {
+    let mut tags = Vec::new();
+    let mut offset = 0;
+    while let Some(tag_start) = html[offset..].find('<').map(|ix| offset + ix) {
+        let Some(tag_end) = html[tag_start..].find('>').map(|ix| tag_start + ix + 1) else {
+            break;
+        };
+        offset = tag_end;
+
+        let tag = &html[tag_start + 1..tag_end - 1];
+        let (is_self_closing, tag) = match tag.strip_suffix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let (is_end, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        if name.is_empty() || name.starts_with(['!', '?']) {
+            continue;
+        }
+        let element = match name.to_ascii_lowercase().as_str() {
+            "br" if !is_end => {
+                tags.push((tag_start..tag_end, HtmlTag::LineBreak));
+                continue;
+            }
+            // Other elements are only rendered with both a start and an end tag.
+            _ if is_self_closing => None,
+            "b" | "strong" => Some(HtmlElement::Bold),
+            "i" | "em" => Some(HtmlElement::Italic),
+            "sub" => Some(HtmlElement::Subscript),
+            "sup" => Some(HtmlElement::Superscript),
+            "code" => Some(HtmlElement::Code),
+            _ => None,
+        };
+        let tag = match element {
+            Some(element) if is_end => HtmlTag::End(element),
+            Some(element) => HtmlTag::Start(element),
+            None => HtmlTag::Unsupported,
+        };
+        tags.push((tag_start..tag_end, tag));
+    }
+    tags
+}
+
+/// Parses the alert marker that has to be alone on the first line of the first
+/// paragraph in a block quote, returning its kind and the length of that line.
+fn parse_callout_marker(paragraph: &str) -> Option<(CalloutKind, usize)> {
+    let line_len = paragraph.find('\n').map_or(paragraph.len(), |ix| ix + 1);
+    let kind = match paragraph[..line_len]
+        .trim_end()
+        .to_ascii_uppercase()
+        .as_str()
+    {
+        "[!NOTE]" => CalloutKind::Note,
+        "[!TIP]" => CalloutKind::Tip,
+        "[!IMPORTANT]" => CalloutKind::Important,
+        "[!WARNING]" => CalloutKind::Warning,
+        "[!CAUTION]" => CalloutKind::Caution,
+        _ => return None,
+    };
+    Some((kind, line_len))
+}
+
+/// Whether clicking a link to `url` should open it. Fragment-only destinations
+/// such as `#` are placeholders or in-document anchors, not something to open.
+fn is_openable_url(url: &str) -> bool {
+    !url.is_empty() && !url.starts_with('#')
+}
+
+fn is_external_url(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Sets the color of the text in the given range of a line's runs, splitting the
+/// runs that straddle its ends.
+fn recolor_runs(runs: &mut Vec<TextRun>, range: Range<usize>, color: Hsla) {
+    let mut recolored = Vec::with_capacity(runs.len() + 2);
+    let mut offset = 0;
+    for run in runs.drain(..) {
+        let run_range = offset..offset + run.len;
+        offset = run_range.end;
+        let start = range.start.clamp(run_range.start, run_range.end);
+        let end = range.end.clamp(run_range.start, run_range.end);
+        for (piece, selected) in [
+            (run_range.start..start, false),
+            (start..end, true),
+            (end..run_range.end, false),
+        ] {
+            if !piece.is_empty() {
+                recolored.push(TextRun {
+                    len: piece.len(),
+                    color: if selected { color } else { run.color },
+                    ..run.clone()
+                });
+            }
+        }
+    }
+    *runs = recolored;
+}
+
+/// A line of rendered text, as returned by [`Markdown::rendered_lines`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedLineInfo {
+    /// The range of the source that the line was rendered from.
+    pub source_range: Range<usize>,
+    /// The bounds the line was painted in, spanning all of its wrapped rows.
+    pub bounds: Bounds<Pixels>,
+    pub line_height: Pixels,
+}
+
+struct RenderedLine {
+    layout: TextLayout,
+    source_mappings: Vec<SourceMapping>,
+    source_end: usize,
+    /// Rendered ranges that aren't backed by the source and are omitted when copying.
+    decorations: Vec<Range<usize>>,
+    icons: Vec<(Range<usize>, IconName)>,
+    soft_hyphens: Vec<SoftHyphen>,
+    /// Text that precedes this line when copied, instead of a newline.
+    copy_separator: Option<String>,
+}
+
+/// A hidden soft hyphen, which is painted as a hyphen if its line wraps after it.
+struct SoftHyphen {
+    index: usize,
+    /// The end of the character after the soft hyphen, which is on the next row
+    /// if the line wraps at it.
+    next_char_end: usize,
+    style: TextStyle,
+}
+
+impl RenderedLine {
+    fn source_range(&self) -> Range<usize> {
+        self.source_mappings
+            .first()
+            .map_or(self.source_end, |mapping| mapping.source_index)..self.source_end
+    }
+
+    fn rendered_index_for_source_index(&self, source_index: usize) -> usize {
+        let mapping = match self
+            .source_mappings
+            .binary_search_by_key(&source_index, |probe| probe.source_index)
+        {
+            Ok(ix) => &self.source_mappings[ix],
+            Err(ix) => &self.source_mappings[ix.saturating_sub(1)],
+        };
+        if mapping.substituted {
+            return mapping.rendered_index;
+        }
+        mapping.rendered_index + source_index.saturating_sub(mapping.source_index)
+    }
+
+    fn source_index_for_rendered_index(&self, rendered_index: usize) -> usize {
+        let mapping = match self
+            .source_mappings
+            .binary_search_by_key(&rendered_index, |probe| probe.rendered_index)
+        {
+            Ok(ix) => &self.source_mappings[ix],
+            Err(ix) => &self.source_mappings[ix.saturating_sub(1)],
+        };
+        if mapping.substituted {
+            return mapping.source_index;
+        }
+        // Rendered indices before the first mapping belong to decorations, which
+        // map to the start of the line's source.
+        mapping.source_index + rendered_index.saturating_sub(mapping.rendered_index)
+    }
+
+    /// Like `source_index_for_rendered_index`, but maps an index between two
+    /// mappings to the end of the earlier one, as is right for the end of a range.
+    fn source_end_for_rendered_index(&self, rendered_index: usize) -> usize {
+        let ix = self
+            .source_mappings
+            .partition_point(|mapping| mapping.rendered_index < rendered_index);
+        let Some(mapping) = ix.checked_sub(1).map(|ix| &self.source_mappings[ix]) else {
+            return self.source_index_for_rendered_index(rendered_index);
+        };
+        if mapping.substituted {
+            return self
+                .source_mappings
+                .get(ix)
+                .map_or(self.source_end, |next| next.source_index);
+        }
+        mapping.source_index + (rendered_index - mapping.rendered_index)
+    }
+
+    /// Returns the rendered ranges of this line that display the given source range,
+    /// skipping decorations.
+    fn rendered_ranges_for_source_range(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let text_len = self.layout.text().len();
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (ix, mapping) in self.source_mappings.iter().enumerate() {
+            let mut segment_end = self
+                .source_mappings
+                .get(ix + 1)
+                .map_or(text_len, |next| next.rendered_index);
+            if let Some(decoration) = self
+                .decorations
+                .iter()
+                .find(|decoration| decoration.start >= mapping.rendered_index)
+            {
+                segment_end = segment_end.min(decoration.start);
+            }
+
+            let rendered_range = if mapping.substituted {
+                // Substituted text is shown entirely if any of its source is in range.
+                let source_end = self
+                    .source_mappings
+                    .get(ix + 1)
+                    .map_or(mapping.source_index, |next| next.source_index);
+                (mapping.source_index < range.end && range.start < source_end)
+                    .then(|| mapping.rendered_index..segment_end)
+            } else {
+                let segment_len = segment_end.saturating_sub(mapping.rendered_index);
+                let source_start = mapping.source_index.max(range.start);
+                let source_end = (mapping.source_index + segment_len).min(range.end);
+                (source_start < source_end).then(|| {
+                    let start = mapping.rendered_index + (source_start - mapping.source_index);
+                    start..start + (source_end - source_start)
+                })
+            };
+
+            if let Some(rendered_range) = rendered_range {
+                match ranges.last_mut() {
+                    Some(last) if last.end == rendered_range.start => last.end = rendered_range.end,
+                    _ => ranges.push(rendered_range),
+                }
+            }
+        }
+        ranges
+    }
+
+    /// Returns one rectangle per visual row covered by the given rendered range,
+    /// excluding whitespace at the edges of each row.
+    fn bounds_for_rendered_range(&self, range: Range<usize>) -> Vec<Bounds<Pixels>> {
+        let text = self.layout.text();
+        let line_height = self.layout.line_height();
+        let mut rows: Vec<Bounds<Pixels>> = Vec::new();
+        for (offset, ch) in text[range.clone()].char_indices() {
+            let ix = range.start + offset;
+            let next_ix = ix + ch.len_utf8();
+            if ch.is_whitespace() {
+                continue;
+            }
+            let Some(start) = self.layout.position_for_index(ix) else {
+                continue;
+            };
+            let end_x = match self.layout.position_for_index(next_ix) {
+                Some(end) if end.y == start.y => end.x,
+                // The character ends a wrapped row, so its width can't be measured
+                // from the next character. Approximate it instead.
+                _ => start.x + line_height * 0.5,
+            };
+
+            match rows.last_mut() {
+                Some(row) if row.top() == start.y => {
+                    row.size.width = end_x - row.left();
+                }
+                _ => rows.push(Bounds::new(start, size(end_x - start.x, line_height))),
+            }
+        }
+        rows
+    }
+
+    fn source_index_for_position(&self, position: Point<Pixels>) -> Result<usize, usize> {
+        let line_rendered_index;
+        let out_of_bounds;
+        match self.layout.index_for_position(position) {
+            Ok(ix) => {
+                line_rendered_index = ix;
+                out_of_bounds = false;
+            }
+            Err(ix) => {
+                line_rendered_index = ix;
+                out_of_bounds = true;
+            }
+        };
+        let source_index = self.source_index_for_rendered_index(line_rendered_index);
+        if out_of_bounds {
+            Err(source_index)
+        } else {
+            Ok(source_index)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct SourceMapping {
+    rendered_index: usize,
+    source_index: usize,
+    /// Whether the text up to the next mapping is rendered differently from its
+    /// source, so that indices within it can't be mapped one-to-one.
+    substituted: bool,
+}
+
+pub struct RenderedMarkdown {
+    element: AnyElement,
+    text: RenderedText,
+    expansion_toggle: Option<ExpansionToggle>,
+}
+
+/// The "Show more" or "Show less" button of markdown with a `max_height`.
+struct ExpansionToggle {
+    element: AnyElement,
+    content_layout_id: LayoutId,
+    collapsed: bool,
+    /// Whether the collapsed content is taller than `max_height`, set in prepaint.
+    truncated: bool,
+}
+
+#[derive(Clone)]
+struct RenderedText {
+    lines: Rc<[RenderedLine]>,
+    links: Rc<[RenderedLink]>,
+    inline_code: Rc<[Range<usize>]>,
+    footnote_references: Rc<[RenderedFootnoteReference]>,
+    footnote_definitions: Rc<[(SharedString, Range<usize>)]>,
+    blocks: Rc<[Range<usize>]>,
+}
+
+#[derive(Clone, Eq, PartialEq)]
+struct RenderedLink {
+    source_range: Range<usize>,
+    destination_url: SharedString,
+}
+
+#[derive(Clone, Eq, PartialEq)]
+struct RenderedFootnoteReference {
+    source_range: Range<usize>,
+    label: SharedString,
+}
+
+impl RenderedText {
+    fn source_index_for_position(&self, position: Point<Pixels>) -> Result<usize, usize> {
+        let mut lines = self.lines.iter().peekable();
+
+        while let Some(line) = lines.next() {
+            let line_bounds = line.layout.bounds();
+            if position.y > line_bounds.bottom() {
+                if let Some(next_line) = lines.peek() {
+                    if position.y < next_line.layout.bounds().top() {
+                        return Err(line.source_end);
+                    }
+                }
+
+                continue;
+            }
+
+            return line.source_index_for_position(position);
+        }
+
+        Err(self.lines.last().map_or(0, |line| line.source_end))
+    }
+
+    fn position_for_source_index(&self, source_index: usize) -> Option<(Point<Pixels>, Pixels)> {
+        for line in self.lines.iter() {
+            let line_source_start = line.source_mappings.first().unwrap().source_index;
+            if source_index < line_source_start {
+                break;
+            } else if source_index > line.source_end {
+                continue;
+            } else {
+                let line_height = line.layout.line_height();
+                let rendered_index_within_line = line.rendered_index_for_source_index(source_index);
+                let position = line.layout.position_for_index(rendered_index_within_line)?;
+                return Some((position, line_height));
+            }
+        }
+        None
+    }
+
+    fn surrounding_word_range(&self, source_index: usize) -> Range<usize> {
+        for line in self.lines.iter() {
+            if source_index > line.source_end {
+                continue;
+            }
+
+            // Skip leading decorations, which aren't part of any word in the source.
+            let line_rendered_start = line.source_mappings.first().unwrap().rendered_index;
+            let rendered_index_in_line = line.rendered_index_for_source_index(source_index);
+            let text = &line.layout.text()[line_rendered_start..];
+            let index = rendered_index_in_line - line_rendered_start;
+
+            // Words, punctuation, and runs of whitespace are separate segments.
+            // Unlike in Unicode word segmentation, periods and colons also
+            // separate words, e.g. in `foo.bar` or `std::mem`.
+            let mut segments = Vec::new();
+            for (start, segment) in text.split_word_bound_indices() {
+                let mut segment_start = start;
+                for (offset, ch) in segment.char_indices() {
+                    if matches!(ch, '.' | ':') {
+                        let ix = start + offset;
+                        if segment_start < ix {
+                            segments.push(segment_start..ix);
+                        }
+                        segments.push(ix..ix + 1);
+                        segment_start = ix + 1;
+                    }
+                }
+                if segment_start < start + segment.len() {
+                    segments.push(segment_start..start + segment.len());
+                }
+            }
+            let Some(mut first_ix) = segments
+                .iter()
+                .position(|segment| segment.contains(&index))
+                .or(segments.len().checked_sub(1))
+            else {
+                return source_index..source_index;
+            };
+
+            // Ideographs are segmented one by one, so a run of them is treated as
+            // one word, like text between spaces.
+            let mut last_ix = first_ix;
+            if is_cjk(&text[segments[first_ix].clone()]) {
+                while first_ix > 0 && is_cjk(&text[segments[first_ix - 1].clone()]) {
+                    first_ix -= 1;
+                }
+                while last_ix + 1 < segments.len() && is_cjk(&text[segments[last_ix + 1].clone()]) {
+                    last_ix += 1;
+                }
+            }
+
+            let start = line_rendered_start + segments[first_ix].start;
+            let end = line_rendered_start + segments[last_ix].end;
+            return line.source_index_for_rendered_index(start)
+                ..line.source_index_for_rendered_index(end);
+        }
+
+        source_index..source_index
+    }
+
+    fn line_ix_for_source_index(&self, source_index: usize) -> Option<usize> {
+        self.lines.iter().position(|line| {
+            let line_source_start = line.source_mappings.first().unwrap().source_index;
+            (line_source_start..=line.source_end).contains(&source_index)
+        })
+    }
+
+    /// The source index one rendered character after `source_index`, continuing
+    /// onto the next line at the end of a line.
+    fn next_source_index(&self, source_index: usize) -> usize {
+        let Some(line_ix) = self.line_ix_for_source_index(source_index) else {
+            return source_index;
+        };
+        let line = &self.lines[line_ix];
+        if source_index >= line.source_end {
+            return self
+                .lines
+                .get(line_ix + 1)
+                .map_or(source_index, |next_line| {
+                    next_line.source_mappings.first().unwrap().source_index
+                });
+        }
+
+        let text = line.layout.text();
+        let rendered_index = line.rendered_index_for_source_index(source_index);
+        let next_rendered_index = text[rendered_index.min(text.len())..]
+            .chars()
+            .next()
+            .map_or(text.len(), |ch| rendered_index + ch.len_utf8());
+        line.source_index_for_rendered_index(next_rendered_index)
+            .clamp(source_index + 1, line.source_end)
+    }
+
+    /// The source index one rendered character before `source_index`, continuing
+    /// onto the previous line at the start of a line.
+    fn previous_source_index(&self, source_index: usize) -> usize {
+        let Some(line_ix) = self.line_ix_for_source_index(source_index) else {
+            return source_index;
+        };
+        let line = &self.lines[line_ix];
+        let line_source_start = line.source_mappings.first().unwrap().source_index;
+        if source_index <= line_source_start {
+            return line_ix
+                .checked_sub(1)
+                .map_or(source_index, |prev_ix| self.lines[prev_ix].source_end);
+        }
+
+        let text = line.layout.text();
+        let rendered_index = line.rendered_index_for_source_index(source_index);
+        let previous_rendered_index = text[..rendered_index.min(text.len())]
+            .chars()
+            .next_back()
+            .map_or(0, |ch| rendered_index - ch.len_utf8());
+        line.source_index_for_rendered_index(previous_rendered_index)
+            .clamp(line_source_start, source_index - 1)
+    }
+
+    fn source_index_above(&self, source_index: usize) -> usize {
+        let Some((position, line_height)) = self.position_for_source_index(source_index) else {
+            return source_index;
+        };
+        let target = point(position.x, position.y - line_height / 2.);
+        match self.source_index_for_position(target) {
+            Ok(ix) | Err(ix) => ix,
+        }
+    }
+
+    fn source_index_below(&self, source_index: usize) -> usize {
+        let Some((position, line_height)) = self.position_for_source_index(source_index) else {
+            return source_index;
+        };
+        let target = point(position.x, position.y + line_height * 1.5);
+        match self.source_index_for_position(target) {
+            Ok(ix) | Err(ix) => ix,
+        }
+    }
+
+    /// The source index at the start of the visual row containing `source_index`.
+    fn row_start_source_index(&self, source_index: usize) -> usize {
+        let Some(line_ix) = self.line_ix_for_source_index(source_index) else {
+            return source_index;
+        };
+        let Some((position, line_height)) = self.position_for_source_index(source_index) else {
+            return source_index;
+        };
+        let line = &self.lines[line_ix];
+        let target = point(line.layout.bounds().left(), position.y + line_height / 2.);
+        match line.source_index_for_position(target) {
+            Ok(ix) | Err(ix) => ix,
+        }
+    }
+
+    /// The source index at the end of the visual row containing `source_index`.
+    fn row_end_source_index(&self, source_index: usize) -> usize {
+        let Some(line_ix) = self.line_ix_for_source_index(source_index) else {
+            return source_index;
+        };
+        let Some((position, line_height)) = self.position_for_source_index(source_index) else {
+            return source_index;
+        };
+        let line = &self.lines[line_ix];
+        let target = point(line.layout.bounds().right(), position.y + line_height / 2.);
+        match line.source_index_for_position(target) {
+            Ok(ix) | Err(ix) => ix,
+        }
+    }
+
+    fn surrounding_line_range(&self, source_index: usize) -> Range<usize> {
+        for line in self.lines.iter() {
+            if source_index > line.source_end {
+                continue;
+            }
+            let line_source_start = line.source_mappings.first().unwrap().source_index;
+            return line_source_start..line.source_end;
+        }
+
+        source_index..source_index
+    }
+
+    /// Returns the range of the lines in the innermost list item or block quote
+    /// around the given index, or of its line if it's in neither.
+    fn surrounding_block_range(&self, source_index: usize) -> Range<usize> {
+        let line_range = self.surrounding_line_range(source_index);
+        let Some(block) = self
+            .blocks
+            .iter()
+            .filter(|block| block.start <= line_range.start && line_range.end <= block.end)
+            .min_by_key(|block| block.len())
+        else {
+            return line_range;
+        };
+        self.lines
+            .iter()
+            .map(RenderedLine::source_range)
+            .filter(|range| block.start <= range.start && range.end <= block.end)
+            .fold(line_range, |block_range, range| {
+                block_range.start.min(range.start)..block_range.end.max(range.end)
+            })
+    }
+
+    fn text_for_range(&self, range: Range<usize>) -> String {
+        let mut ret = String::new();
+        let mut is_first_line = true;
+
+        for line in self.lines.iter() {
+            if range.start > line.source_end {
+                continue;
+            }
+            let line_source_start = line.source_mappings.first().unwrap().source_index;
+            if range.end < line_source_start {
+                break;
+            }
+
+            let text = line.layout.text();
+
+            let start = if range.start < line_source_start {
+                0
+            } else {
+                line.rendered_index_for_source_index(range.start)
+            };
+            let end = if range.end > line.source_end {
+                line.rendered_index_for_source_index(line.source_end)
+            } else {
+                line.rendered_index_for_source_index(range.end)
+            }
+            .min(text.len());
+
+            let mut line_text = String::new();
+            let mut ix = start;
+            for decoration in &line.decorations {
+                if decoration.end <= ix || decoration.start >= end {
+                    continue;
+                }
+                line_text.push_str(&text[ix..decoration.start.max(ix)]);
+                ix = decoration.end.min(end);
+            }
+            line_text.push_str(&text[ix..end]);
+
+            if !mem::take(&mut is_first_line) {
+                ret.push_str(line.copy_separator.as_deref().unwrap_or("\n"));
+            }
+            ret.push_str(&line_text);
+        }
+        ret
+    }
+
+    /// Returns the source ranges of the occurrences of `query` within each line,
+    /// skipping those overlapping decorations like list bullets.
+    fn find(&self, query: &str, case_sensitive: bool) -> Vec<Range<usize>> {
+        let query = if case_sensitive {
+            query.to_string()
+        } else {
+            query.to_lowercase()
+        };
+        let mut matches = Vec::new();
+        for line in self.lines.iter() {
+            let text = line.layout.text();
+            // The byte offset in `text` of each byte of the searched text, which
+            // can differ in length from `text` once lowercased.
+            let mut searched_text = String::with_capacity(text.len());
+            let mut offsets = Vec::with_capacity(text.len() + 1);
+            for (ix, ch) in text.char_indices() {
+                let len = searched_text.len();
+                if case_sensitive {
+                    searched_text.push(ch);
+                } else {
+                    searched_text.extend(ch.to_lowercase());
+                }
+                offsets.extend(iter::repeat(ix).take(searched_text.len() - len));
+            }
+            offsets.push(text.len());
+
+            for (ix, _) in searched_text.match_indices(&query) {
+                let rendered_range = offsets[ix]..offsets[ix + query.len()];
+                if line.decorations.iter().any(|decoration| {
+                    decoration.start < rendered_range.end && rendered_range.start < decoration.end
+                }) {
+                    continue;
+                }
+                matches.push(
+                    line.source_index_for_rendered_index(rendered_range.start)
+                        ..line.source_end_for_rendered_index(rendered_range.end),
+                );
+            }
+        }
+        matches
+    }
+
+    /// Returns the rectangles covering the rendered text for the given source range,
+    /// one per visual row.
+    fn bounds_for_source_range(&self, range: Range<usize>) -> Vec<Bounds<Pixels>> {
+        let mut bounds = Vec::new();
+        for line in self.lines.iter() {
+            if range.start > line.source_end {
+                continue;
+            }
+            let line_source_start = line.source_mappings.first().unwrap().source_index;
+            if range.end <= line_source_start {
+                break;
+            }
+
+            for rendered_range in line.rendered_ranges_for_source_range(range.clone()) {
+                bounds.extend(line.bounds_for_rendered_range(rendered_range));
+            }
+        }
+        bounds
+    }
+
+    /// Returns the line whose rows span the given vertical position, whether or
+    /// not the position is beside the end of the text.
+    fn line_for_position(&self, position: Point<Pixels>) -> Option<&RenderedLine> {
+        self.lines.iter().find(|line| {
+            let bounds = line.layout.bounds();
+            bounds.top() <= position.y && position.y < bounds.bottom()
+        })
+    }
+
+    fn link_for_position(&self, position: Point<Pixels>) -> Option<&RenderedLink> {
+        let source_index = self.source_index_for_position(position).ok()?;
+        self.links
+            .iter()
+            .find(|link| link.source_range.contains(&source_index))
+    }
+
+    fn footnote_reference_for_position(
+        &self,
+        position: Point<Pixels>,
+    ) -> Option<&RenderedFootnoteReference> {
+        let source_index = self.source_index_for_position(position).ok()?;
+        self.footnote_references
+            .iter()
+            .find(|reference| reference.source_range.contains(&source_index))
+    }
+
+    fn footnote_definition_range(&self, label: &str) -> Option<Range<usize>> {
+        self.footnote_definitions
+            .iter()
+            .find(|(definition_label, _)| definition_label == label)
+            .map(|(_, source_range)| source_range.clone())
+    }
+
+    /// Returns the rendered text of the definition with the given label, without
+    /// its `[label]` prefix.
+    fn footnote_definition(&self, label: &str) -> String {
+        self.footnote_definition_range(label)
+            .map(|source_range| self.text_for_range(source_range))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{Modifiers, MouseButton, TestAppContext, VisualTestContext};
+    use pulldown_cmark::HeadingLevel;
+    use std::{
+        cell::{Cell, RefCell},
+        path::Path,
+    };
+
+    #[gpui::test]
+    fn test_selection_survives_edits_after_it(cx: &mut TestAppContext) {
+        let markdown = cx.add_window(|cx| {
+            Markdown::new_synchronous("Hello world".into(), MarkdownStyle::default(), None, cx)
+        });
+        markdown
+            .update(cx, |markdown, cx| {
+                markdown.selection = Selection {
+                    start: 0,
+                    end: 5,
+                    reversed: false,
+                    pending: false,
+                };
+
+                markdown.append(" and more", cx);
+                assert_eq!(markdown.selection.start..markdown.selection.end, 0..5);
+
+                markdown.reset("Hello there".into(), cx);
+                assert_eq!(markdown.selection.start..markdown.selection.end, 0..5);
+
+                markdown.reset("Help".into(), cx);
+                assert_eq!(markdown.selection.start..markdown.selection.end, 0..0);
+            })
+            .unwrap();
+    }
+
+    #[gpui::test]
+    fn test_select_all(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "# Title\n\nSome *text*.".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.focus_view(&markdown);
+        cx.run_until_parked();
+
+        cx.dispatch_action(SelectAll);
+        cx.dispatch_action(Copy);
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().clone()),
+            Some("Title\nSome text.".to_string())
+        );
+    }
+
+    #[gpui::test]
+    fn test_smart_punctuation(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "\"hello\" --- world".into(),
+                MarkdownStyle {
+                    smart_punctuation: true,
+                    ..Default::default()
+                },
+                None,
+                cx,
+            )
+        });
+        cx.focus_view(&markdown);
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(
+                markdown.export_plain_text(None),
+                "\u{201c}hello\u{201d} \u{2014} world"
+            );
+            assert_eq!(markdown.export_plain_text(Some(1..6)), "hello");
+            assert_eq!(
+                markdown.export_plain_text(Some(6..17)),
+                "\u{201d} \u{2014} world"
+            );
+            // Ranges starting inside a substitution include all of it.
+            assert_eq!(markdown.export_plain_text(Some(9..17)), "\u{2014} world");
+        });
+
+        cx.dispatch_action(SelectAll);
+        cx.dispatch_action(Copy);
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().clone()),
+            Some("\u{201c}hello\u{201d} \u{2014} world".to_string())
+        );
+    }
+
+    #[gpui::test]
+    fn test_selected_text(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Hello *world*.\n\n- one\n- two\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selected_text(), None);
+
+            markdown.selection = Selection {
+                start: 6,
+                end: 13,
+                reversed: false,
+                pending: false,
+            };
+            assert_eq!(markdown.selected_text().as_deref(), Some("world"));
+
+            markdown.selection.end = markdown.source.len();
+            assert_eq!(
+                markdown.selected_text().as_deref(),
+                Some("world.\none\ntwo")
+            );
+
+            markdown.selection.start = markdown.selection.end;
+            assert_eq!(markdown.selected_text(), None);
+        });
+    }
+
+    #[gpui::test]
+    fn test_drag_autoscroll(cx: &mut TestAppContext) {
+        struct Container {
+            markdown: View<Markdown>,
+        }
+
+        impl Render for Container {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                div()
+                    .h(px(100.))
+                    .overflow_hidden()
+                    .child(self.markdown.clone())
+            }
+        }
+
+        let source = (1..=20)
+            .map(|ix| format!("Paragraph {ix}."))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let (container, cx) = cx.add_window_view(|cx| Container {
+            markdown: cx.new_view(|cx| {
+                Markdown::new_synchronous(source, MarkdownStyle::default(), None, cx)
+            }),
+        });
+        let markdown = cx.update(|cx| container.read(cx).markdown.clone());
+        cx.run_until_parked();
+
+        let first_line = markdown.update(cx, |markdown, _| markdown.rendered_lines()[0].bounds);
+        let bottom_edge = point(first_line.left() + px(1.), px(95.));
+        cx.simulate_mouse_down(first_line.center(), MouseButton::Left, Modifiers::default());
+        cx.simulate_mouse_move(bottom_edge, MouseButton::Left, Modifiers::default());
+        let mut head = markdown.update(cx, |markdown, _| markdown.selection.head());
+
+        // Holding the drag at the bottom edge keeps extending the selection.
+        for _ in 0..3 {
+            cx.executor().advance_clock(DRAG_AUTOSCROLL_INTERVAL);
+            cx.run_until_parked();
+            let new_head = markdown.update(cx, |markdown, _| {
+                assert!(markdown.selection.pending);
+                markdown.selection.head()
+            });
+            assert!(new_head > head);
+            head = new_head;
+        }
+
+        cx.simulate_mouse_up(bottom_edge, MouseButton::Left, Modifiers::default());
+        let head = markdown.update(cx, |markdown, _| markdown.selection.head());
+        cx.executor().advance_clock(DRAG_AUTOSCROLL_INTERVAL * 3);
+        cx.run_until_parked();
+        assert_eq!(
+            markdown.update(cx, |markdown, _| markdown.selection.head()),
+            head
+        );
+    }
+
+    #[gpui::test]
+    fn test_search(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Zed is fast. **zed** is *ZED*.\n\n- zed\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, cx| {
+            markdown.set_search_query("zed", cx);
+            assert_eq!(markdown.search_matches(), [0..3, 15..18, 25..28, 34..37]);
+
+            markdown.select_next_match(cx);
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..3);
+            assert_eq!(markdown.autoscroll_request, Some(0));
+            markdown.select_prev_match(cx);
+            assert_eq!(markdown.selection.start..markdown.selection.end, 34..37);
+            markdown.select_next_match(cx);
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..3);
+
+            markdown.set_search_case_sensitive(true, cx);
+            assert_eq!(markdown.search_matches(), [15..18, 34..37]);
+            markdown.set_search_query("", cx);
+            assert!(markdown.search_matches().is_empty());
+        });
+
+        // Matches follow changes to the rendered text.
+        markdown.update(cx, |markdown, cx| {
+            markdown.set_search_case_sensitive(false, cx);
+            markdown.set_search_query("fast", cx);
+            markdown.append(" Very fast.", cx);
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.search_matches(), [7..11, 44..48]);
+        });
+    }
+
+    #[gpui::test]
+    fn test_language_aliases(cx: &mut TestAppContext) {
+        let language_registry = Arc::new(LanguageRegistry::test(cx.executor()));
+        language_registry.add(languages::language("rust", tree_sitter_rust::language()));
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "```rs\nfn main() {}\n```\n".into(),
+                MarkdownStyle::default(),
+                Some(language_registry.clone()),
+                cx,
+            )
+        });
+        markdown.update(cx, |markdown, cx| {
+            markdown.set_language_aliases(
+                HashMap::from_iter([("rs".to_string(), "rust".to_string())]),
+                cx,
+            );
+        });
+
+        let element = MarkdownElement::new(
+            markdown.clone(),
+            MarkdownStyle::default(),
+            Some(language_registry),
+        );
+        cx.update(|cx| {
+            let language = element.load_language("rs", cx);
+            assert_eq!(
+                language.map(|language| language.name()),
+                Some("Rust".into())
+            );
+            assert!(element.load_language("rsx", cx).is_none());
+        });
+    }
+
+    #[gpui::test]
+    fn test_copy_as_markdown(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Some **bold** text".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.focus_view(&markdown);
+        cx.run_until_parked();
+
+        cx.dispatch_action(SelectAll);
+        cx.dispatch_action(Copy);
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().clone()),
+            Some("Some bold text".to_string())
+        );
+        cx.dispatch_action(CopyAsMarkdown);
+        assert_eq!(
+            cx.read_from_clipboard().map(|item| item.text().clone()),
+            Some("Some **bold** text".to_string())
+        );
+    }
+
+    #[gpui::test]
+    fn test_on_selection_changed(cx: &mut TestAppContext) {
+        let reported = Rc::new(RefCell::new(Vec::new()));
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            let mut markdown =
+                Markdown::new_synchronous("Hello world".into(), MarkdownStyle::default(), None, cx);
+            markdown.on_selection_changed({
+                let reported = reported.clone();
+                move |range, _| reported.borrow_mut().push(range)
+            });
+            markdown
+        });
+        cx.run_until_parked();
+
+        let position = |ix: usize, cx: &mut VisualTestContext| {
+            markdown.update(cx, |markdown, _| {
+                let (position, line_height) = markdown
+                    .rendered_text
+                    .as_ref()
+                    .unwrap()
+                    .position_for_source_index(ix)
+                    .unwrap();
+                point(position.x, position.y + line_height / 2.)
+            })
+        };
+
+        // Pressing the mouse leaves the selection empty, so nothing is reported
+        // until it's dragged.
+        let start = position(0, cx);
+        let end = position(5, cx);
+        cx.simulate_mouse_down(start, MouseButton::Left, Modifiers::default());
+        assert!(reported.borrow().is_empty());
+        cx.simulate_mouse_move(end, MouseButton::Left, Modifiers::default());
+        cx.simulate_mouse_up(end, MouseButton::Left, Modifiers::default());
+        assert_eq!(*reported.borrow(), [Some(0..5)]);
+
+        // Clicking clears it.
+        let inside = position(2, cx);
+        cx.simulate_mouse_down(inside, MouseButton::Left, Modifiers::default());
+        cx.simulate_mouse_up(inside, MouseButton::Left, Modifiers::default());
+        assert_eq!(*reported.borrow(), [Some(0..5), None]);
+    }
+
+    #[gpui::test]
+    fn test_select_with_keyboard(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Line one\n\nLine two".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.focus_view(&markdown);
+        cx.run_until_parked();
+
+        cx.dispatch_action(SelectRight);
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..1);
+        });
+        cx.dispatch_action(SelectDown);
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..11);
+        });
+        cx.dispatch_action(SelectToEndOfLine);
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..18);
+        });
+        cx.dispatch_action(SelectUp);
+        cx.dispatch_action(SelectToBeginningOfLine);
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..0);
+        });
+    }
+
+    #[gpui::test]
+    fn test_surrounding_word_range(cx: &mut TestAppContext) {
+        let source = "See foo.bar (hello) 你好世界。ok";
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx)
+        });
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            let rendered_text = markdown.rendered_text.as_ref().unwrap();
+            let word_at = |ix: usize| &source[rendered_text.surrounding_word_range(ix)];
+            assert_eq!(word_at(5), "foo");
+            assert_eq!(word_at(7), ".");
+            assert_eq!(word_at(9), "bar");
+            assert_eq!(word_at(12), "(");
+            assert_eq!(word_at(14), "hello");
+            assert_eq!(word_at(18), ")");
+            assert_eq!(word_at(source.find('世').unwrap()), "你好世界");
+            assert_eq!(word_at(source.find('。').unwrap()), "。");
+        });
+    }
+
+    #[gpui::test]
+    fn test_cursor(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous("Hello world".into(), MarkdownStyle::default(), None, cx)
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| assert_eq!(markdown.cursor_bounds(), None));
+
+        let set_selection = |range: Range<usize>, cx: &mut VisualTestContext| {
+            markdown.update(cx, |markdown, cx| {
+                markdown.selection = Selection {
+                    start: range.start,
+                    end: range.end,
+                    reversed: false,
+                    pending: false,
+                };
+                cx.notify();
+            });
+            cx.run_until_parked();
+        };
+        cx.focus_view(&markdown);
+        set_selection(6..6, cx);
+        markdown.update(cx, |markdown, _| {
+            let (position, line_height) = markdown
+                .rendered_text
+                .as_ref()
+                .unwrap()
+                .position_for_source_index(6)
+                .unwrap();
+            let cursor_bounds = markdown.cursor_bounds().unwrap();
+            assert_eq!(cursor_bounds.origin, position);
+            assert_eq!(cursor_bounds.size.height, line_height);
+            assert!(markdown.cursor_visible);
+        });
+
+        // The caret blinks while it stays in place.
+        cx.executor().advance_clock(CURSOR_BLINK_INTERVAL);
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert!(!markdown.cursor_visible);
+            assert!(markdown.cursor_bounds().is_some());
+        });
+
+        set_selection(0..5, cx);
+        markdown.update(cx, |markdown, _| assert_eq!(markdown.cursor_bounds(), None));
+    }
+
+    #[test]
+    fn test_links() {
+        let parsed = ParsedMarkdown::new(
+            "See [Zed](https://zed.dev) or https://example.com.\n\n\
+             Read [the `docs`][docs].\n\n\
+             [docs]: https://docs.rs\n"
+                .into(),
+        );
+        assert_eq!(
+            parsed.links(),
+            [
+                (4..26, "https://zed.dev".to_string(), "Zed".to_string()),
+                (
+                    30..49,
+                    "https://example.com".to_string(),
+                    "https://example.com".to_string()
+                ),
+                (
+                    57..75,
+                    "https://docs.rs".to_string(),
+                    "the docs".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    fn test_max_content_width(cx: &mut TestAppContext) {
+        // Returns the width of the root div after checking the text is wrapped to the column.
+        fn root_width(full_width_code_blocks: bool, cx: &mut TestAppContext) -> Pixels {
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous(
+                    format!("{}\n\n```\ncode\n```\n", "word ".repeat(100)),
+                    MarkdownStyle {
+                        max_content_width: Some(px(200.)),
+                        full_width_code_blocks,
+                        ..Default::default()
+                    },
+                    None,
+                    cx,
+                )
+            });
+            cx.run_until_parked();
+            let lines = markdown.update(cx, |markdown, _| markdown.rendered_lines());
+            assert!(lines[0].bounds.size.width <= px(200.));
+            cx.debug_bounds("inner").unwrap().size.width
+        }
+
+        assert_eq!(root_width(false, cx), px(200.));
+        // Code blocks span the full width, so only the other blocks are capped.
+        assert!(root_width(true, cx) > px(200.));
+    }
+
+    #[gpui::test]
+    fn test_max_height(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+        });
+
+        struct Container {
+            markdown: View<Markdown>,
+        }
+
+        impl Render for Container {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                div()
+                    .debug_selector(|| "container".into())
+                    .child(self.markdown.clone())
+            }
+        }
+
+        let source = (1..=20)
+            .map(|ix| format!("Paragraph {ix}."))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let (container, cx) = cx.add_window_view(|cx| Container {
+            markdown: cx.new_view(|cx| {
+                Markdown::new_synchronous(source, MarkdownStyle::default(), None, cx)
+            }),
+        });
+        let markdown = cx.update(|cx| container.read(cx).markdown.clone());
+        cx.run_until_parked();
+        let full_height = cx.debug_bounds("container").unwrap().size.height;
+
+        markdown.update(cx, |markdown, cx| markdown.max_height(rems(4.), cx));
+        cx.run_until_parked();
+        let max_height = cx.update(|cx| rems(4.).to_pixels(cx.rem_size()));
+        assert!(max_height < full_height);
+        assert_eq!(
+            cx.debug_bounds("container").unwrap().size.height,
+            max_height
+        );
+
+        // Expanding shows all of the content, followed by the toggle.
+        markdown.update(cx, |markdown, cx| markdown.set_expanded(true, cx));
+        cx.run_until_parked();
+        assert!(cx.debug_bounds("container").unwrap().size.height > full_height);
+
+        markdown.update(cx, |markdown, cx| markdown.set_expanded(false, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            cx.debug_bounds("container").unwrap().size.height,
+            max_height
+        );
+    }
+
+    #[gpui::test]
+    fn test_wrap_code_blocks(cx: &mut TestAppContext) {
+        fn code_block_rows(wrap_code_blocks: bool, cx: &mut TestAppContext) -> usize {
+            let source = format!("```\n{}\n```\n", "x".repeat(500));
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous(
+                    source,
+                    MarkdownStyle {
+                        wrap_code_blocks,
+                        ..Default::default()
+                    },
+                    None,
+                    cx,
+                )
+            });
+            cx.run_until_parked();
+            markdown.update(cx, |markdown, _| {
+                let lines = markdown.rendered_lines();
+                assert_eq!(lines.len(), 1);
+                (lines[0].bounds.size.height / lines[0].line_height).round() as usize
+            })
+        }
+
+        assert_eq!(code_block_rows(false, cx), 1);
+        assert!(code_block_rows(true, cx) > 1);
+    }
+
+    #[gpui::test]
+    fn test_block_quote_hanging_indent(cx: &mut TestAppContext) {
+        let source = format!("> {}", "word ".repeat(100));
+        let style = MarkdownStyle {
+            block_quote_hanging_indent: Some(px(20.)),
+            ..Default::default()
+        };
+        let (markdown, cx) =
+            cx.add_window_view(|cx| Markdown::new_synchronous(source.clone(), style, None, cx));
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            let lines = markdown.rendered_lines();
+            let line = &lines[0];
+            assert!(line.bounds.size.height > line.line_height);
+            let word_bounds =
+                |start: usize| markdown.bounds_for_source_range(start..start + 4).unwrap()[0];
+
+            // The first row starts at the left edge, and the rows it wraps onto are
+            // indented.
+            assert_eq!(word_bounds(2).left(), line.bounds.left());
+            let second_row_start = (2..source.len())
+                .step_by(5)
+                .find(|&start| word_bounds(start).top() > line.bounds.top())
+                .unwrap();
+            let second_row_bounds = word_bounds(second_row_start);
+            assert_eq!(second_row_bounds.left(), line.bounds.left() + px(20.));
+
+            // Positions on the indented row select its text, and the indent selects
+            // from its start.
+            assert_eq!(
+                markdown
+                    .source_index_at(second_row_bounds.center())
+                    .map(|ix| { (second_row_start..second_row_start + 4).contains(&ix) }),
+                Some(true)
+            );
+            assert_eq!(
+                markdown.source_index_at(point(
+                    line.bounds.left() + px(5.),
+                    second_row_bounds.center().y
+                )),
+                Some(second_row_start)
+            );
+        });
+    }
+
+    #[gpui::test]
+    fn test_paragraph_spacing(cx: &mut TestAppContext) {
+        fn paragraph_gap(paragraph_spacing: Rems, cx: &mut TestAppContext) -> (Pixels, Pixels) {
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous(
+                    "First.\n\nSecond.\n".into(),
+                    MarkdownStyle {
+                        paragraph_spacing,
+                        ..Default::default()
+                    },
+                    None,
+                    cx,
+                )
+            });
+            cx.run_until_parked();
+            let rem_size = cx.update(|cx| cx.rem_size());
+            markdown.update(cx, |markdown, _| {
+                let lines = markdown.rendered_lines();
+                assert_eq!(lines.len(), 2);
+                (lines[1].bounds.top() - lines[0].bounds.bottom(), rem_size)
+            })
+        }
+
+        let (default_gap, rem_size) = paragraph_gap(MarkdownStyle::default().paragraph_spacing, cx);
+        let (wide_gap, _) = paragraph_gap(rems(2.), cx);
+        assert_eq!(default_gap, rem_size * 0.5);
+        assert_eq!(wide_gap, rem_size * 2.);
+    }
+
+    #[gpui::test]
+    fn test_front_matter_leaves_no_gap(cx: &mut TestAppContext) {
+        fn first_line_top(source: &str, cx: &mut TestAppContext) -> Pixels {
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx)
+            });
+            cx.run_until_parked();
+            markdown.update(cx, |markdown, _| {
+                let lines = markdown.rendered_lines();
+                assert_eq!(lines.len(), 1);
+                lines[0].bounds.top()
+            })
+        }
+
+        assert_eq!(
+            first_line_top("---\ntitle: Intro\n---\n\nFirst paragraph.\n", cx),
+            first_line_top("First paragraph.\n", cx)
+        );
+    }
+
+    #[gpui::test]
+    fn test_show_metadata(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+        });
+
+        let source = "---\ntitle: Notes\ntags: [a]\n---\n\nBody.\n";
+        assert_eq!(
+            ParsedMarkdown::new(source.into()).metadata(),
+            Some((
+                MetadataBlockKind::YamlStyle,
+                "title: Notes\ntags: [a]\n".to_string()
+            ))
+        );
+        assert_eq!(ParsedMarkdown::new("Body.\n".into()).metadata(), None);
+
+        let style = MarkdownStyle {
+            show_metadata: true,
+            ..Default::default()
+        };
+        let (markdown, cx) =
+            cx.add_window_view(|cx| Markdown::new_synchronous(source.into(), style, None, cx));
+        cx.run_until_parked();
+        let rendered_ranges = |cx: &mut VisualTestContext| {
+            markdown.update(cx, |markdown, _| {
+                markdown
+                    .rendered_lines()
+                    .into_iter()
+                    .map(|line| line.source_range)
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(rendered_ranges(cx), [4..26, 32..37]);
+
+        // The disclosure hides the front matter's source, leaving its header.
+        let disclosure = cx.debug_bounds("ICON-ChevronDown").unwrap();
+        cx.simulate_click(disclosure.center(), Modifiers::default());
+        assert_eq!(rendered_ranges(cx), [32..37]);
+        assert!(cx.debug_bounds("ICON-ChevronRight").is_some());
     }
 
-    fn on_mouse_event<T: MouseEvent>(
-        &self,
-        cx: &mut WindowContext,
-        mut f: impl 'static + FnMut(&mut Markdown, &T, DispatchPhase, &mut ViewContext<Markdown>),
-    ) {
-        cx.on_mouse_event({
-            let markdown = self.markdown.downgrade();
-            move |event, phase, cx| {
-                markdown
-                    .update(cx, |markdown, cx| f(markdown, event, phase, cx))
-                    .log_err();
-            }
+    #[gpui::test]
+    fn test_link_tooltip(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
         });
-    }
-}
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Visit [Zed](https://zed.dev) today.".into(),
+                MarkdownStyle {
+                    link_tooltips: true,
+                    ..Default::default()
+                },
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
 
-impl Element for MarkdownElement {
-    type RequestLayoutState = RenderedMarkdown;
-    type PrepaintState = Hitbox;
+        let link_bounds = markdown.update(cx, |markdown, _| markdown.link_bounds());
+        assert_eq!(link_bounds.len(), 1);
+        cx.simulate_mouse_move(link_bounds[0].1[0].center(), None, Modifiers::default());
+        markdown.update(cx, |markdown, _| {
+            let hovered_link = markdown.hovered_link.as_ref().unwrap();
+            assert_eq!(hovered_link.source_range, 6..28);
+            assert_eq!(hovered_link.destination_url, "https://zed.dev");
+            assert!(markdown.link_tooltip.is_none());
+        });
 
-    fn id(&self) -> Option<ElementId> {
-        None
+        cx.executor()
+            .advance_clock(MarkdownStyle::default().link_tooltip_delay);
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| assert!(markdown.link_tooltip.is_some()));
+
+        cx.simulate_mouse_move(point(px(0.), px(0.)), None, Modifiers::default());
+        markdown.update(cx, |markdown, _| {
+            assert!(markdown.hovered_link.is_none());
+            assert!(markdown.link_tooltip.is_none());
+        });
     }
 
-    fn request_layout(
-        &mut self,
-        _id: Option<&GlobalElementId>,
-        cx: &mut WindowContext,
-    ) -> (gpui::LayoutId, Self::RequestLayoutState) {
-        let mut builder = MarkdownElementBuilder::new(cx.text_style(), self.style.syntax.clone());
-        let parsed_markdown = self.markdown.read(cx).parsed_markdown.clone();
-        for (range, event) in parsed_markdown.events.iter() {
-            match event {
-                MarkdownEvent::Start(tag) => {
-                    match tag {
-                        MarkdownTag::Paragraph => {
-                            builder.push_div(div().mb_2().line_height(rems(1.3)));
-                        }
-                        MarkdownTag::Heading { level, .. } => {
-                            let mut heading = div().mb_2();
-                            heading = match level {
-                                pulldown_cmark::HeadingLevel::H1 => heading.text_3xl(),
-                                pulldown_cmark::HeadingLevel::H2 => heading.text_2xl(),
-                                pulldown_cmark::HeadingLevel::H3 => heading.text_xl(),
-                                pulldown_cmark::HeadingLevel::H4 => heading.text_lg(),
-                                _ => heading,
-                            };
-                            builder.push_div(heading);
-                        }
-                        MarkdownTag::BlockQuote => {
-                            builder.push_text_style(self.style.block_quote.clone());
-                            builder.push_div(
-                                div()
-                                    .pl_4()
-                                    .mb_2()
-                                    .border_l_4()
-                                    .border_color(self.style.block_quote_border_color),
-                            );
-                        }
-                        MarkdownTag::CodeBlock(kind) => {
-                            let language = if let CodeBlockKind::Fenced(language) = kind {
-                                self.load_language(language.as_ref(), cx)
-                            } else {
-                                None
-                            };
+    #[gpui::test]
+    fn test_multi_click_selection(cx: &mut TestAppContext) {
+        let source = "> First paragraph.\n>\n> Second paragraph.\n\n- Item text.\n\n  More text.\n- Next item.\n";
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx)
+        });
+        cx.run_until_parked();
+        let lines = markdown.update(cx, |markdown, _| markdown.rendered_lines());
+        assert_eq!(lines.len(), 5);
 
-                            builder.push_code_block(language);
-                            builder.push_text_style(self.style.code_block.clone());
-                            builder.push_div(div().rounded_lg().p_4().mb_2().w_full().when_some(
-                                self.style.code_block.background_color,
-                                |div, color| div.bg(color),
-                            ));
-                        }
-                        MarkdownTag::HtmlBlock => builder.push_div(div()),
-                        MarkdownTag::List(bullet_index) => {
-                            builder.push_list(*bullet_index);
-                            builder.push_div(div().pl_4());
-                        }
-                        MarkdownTag::Item => {
-                            let bullet = if let Some(bullet_index) = builder.next_bullet_index() {
-                                format!("{}.", bullet_index)
-                            } else {
-                                "•".to_string()
-                            };
-                            builder.push_div(
-                                div()
-                                    .h_flex()
-                                    .mb_2()
-                                    .line_height(rems(1.3))
-                                    .items_start()
-                                    .gap_1()
-                                    .child(bullet),
-                            );
-                            // Without `w_0`, text doesn't wrap to the width of the container.
-                            builder.push_div(div().flex_1().w_0());
-                        }
-                        MarkdownTag::Emphasis => builder.push_text_style(TextStyleRefinement {
-                            font_style: Some(FontStyle::Italic),
-                            ..Default::default()
-                        }),
-                        MarkdownTag::Strong => builder.push_text_style(TextStyleRefinement {
-                            font_weight: Some(FontWeight::BOLD),
-                            ..Default::default()
-                        }),
-                        MarkdownTag::Strikethrough => {
-                            builder.push_text_style(TextStyleRefinement {
-                                strikethrough: Some(StrikethroughStyle {
-                                    thickness: px(1.),
-                                    color: None,
-                                }),
-                                ..Default::default()
-                            })
-                        }
-                        MarkdownTag::Link { dest_url, .. } => {
-                            if builder.code_block_stack.is_empty() {
-                                builder.push_link(dest_url.clone(), range.clone());
-                                builder.push_text_style(self.style.link.clone())
-                            }
-                        }
-                        _ => log::error!("unsupported markdown tag {:?}", tag),
-                    }
-                }
-                MarkdownEvent::End(tag) => match tag {
-                    MarkdownTagEnd::Paragraph => {
-                        builder.pop_div();
-                    }
-                    MarkdownTagEnd::Heading(_) => builder.pop_div(),
-                    MarkdownTagEnd::BlockQuote => {
-                        builder.pop_text_style();
-                        builder.pop_div()
-                    }
-                    MarkdownTagEnd::CodeBlock => {
-                        builder.trim_trailing_newline();
-                        builder.pop_div();
-                        builder.pop_text_style();
-                        builder.pop_code_block();
-                    }
-                    MarkdownTagEnd::HtmlBlock => builder.pop_div(),
-                    MarkdownTagEnd::List(_) => {
-                        builder.pop_list();
-                        builder.pop_div();
-                    }
-                    MarkdownTagEnd::Item => {
-                        builder.pop_div();
-                        builder.pop_div();
-                    }
-                    MarkdownTagEnd::Emphasis => builder.pop_text_style(),
-                    MarkdownTagEnd::Strong => builder.pop_text_style(),
-                    MarkdownTagEnd::Strikethrough => builder.pop_text_style(),
-                    MarkdownTagEnd::Link => {
-                        if builder.code_block_stack.is_empty() {
-                            builder.pop_text_style()
-                        }
-                    }
-                    _ => log::error!("unsupported markdown tag end: {:?}", tag),
+        let click = |line: usize, click_count: usize, cx: &mut VisualTestContext| {
+            let position = lines[line].bounds.origin + point(px(5.), px(2.));
+            cx.simulate_event(MouseDownEvent {
+                position,
+                modifiers: Modifiers::default(),
+                button: MouseButton::Left,
+                click_count,
+                first_mouse: false,
+            });
+            cx.simulate_event(MouseUpEvent {
+                position,
+                modifiers: Modifiers::default(),
+                button: MouseButton::Left,
+                click_count,
+            });
+            markdown.update(cx, |markdown, _| {
+                markdown.selection.start..markdown.selection.end
+            })
+        };
+
+        // Triple-clicking selects a paragraph.
+        assert_eq!(click(0, 3, cx), lines[0].source_range);
+        assert_eq!(click(2, 3, cx), lines[2].source_range);
+        // Quadruple-clicking selects the block quote or list item around it.
+        assert_eq!(
+            click(0, 4, cx),
+            lines[0].source_range.start..lines[1].source_range.end
+        );
+        assert_eq!(
+            click(3, 4, cx),
+            lines[2].source_range.start..lines[3].source_range.end
+        );
+        assert_eq!(click(4, 4, cx), lines[4].source_range);
+    }
+
+    #[gpui::test]
+    fn test_inline_code_padding(cx: &mut TestAppContext) {
+        fn background_bounds(
+            inline_code_padding: Pixels,
+            cx: &mut TestAppContext,
+        ) -> (Vec<Bounds<Pixels>>, Vec<Bounds<Pixels>>) {
+            let style = MarkdownStyle {
+                inline_code: TextStyleRefinement {
+                    background_color: Some(gpui::red()),
+                    ..Default::default()
                 },
-                MarkdownEvent::Text => {
-                    builder.push_text(&parsed_markdown.source[range.clone()], range.start);
-                }
-                MarkdownEvent::Code => {
-                    builder.push_text_style(self.style.inline_code.clone());
-                    builder.push_text(&parsed_markdown.source[range.clone()], range.start);
-                    builder.pop_text_style();
-                }
-                MarkdownEvent::Html => {
-                    builder.push_text(&parsed_markdown.source[range.clone()], range.start);
-                }
-                MarkdownEvent::InlineHtml => {
-                    builder.push_text(&parsed_markdown.source[range.clone()], range.start);
-                }
-                MarkdownEvent::Rule => {
-                    builder.push_div(
-                        div()
-                            .border_b_1()
-                            .my_2()
-                            .border_color(self.style.rule_color),
-                    );
-                    builder.pop_div()
-                }
-                MarkdownEvent::SoftBreak => builder.push_text("\n", range.start),
-                MarkdownEvent::HardBreak => builder.push_text("\n", range.start),
-                _ => log::error!("unsupported markdown event {:?}", event),
-            }
+                inline_code_padding,
+                ..Default::default()
+            };
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous("Run `cargo test` now.".into(), style, None, cx)
+            });
+            cx.run_until_parked();
+            markdown.update(cx, |markdown, _| {
+                (
+                    markdown.bounds_for_source_range(5..15).unwrap(),
+                    markdown.inline_code_background_bounds().to_vec(),
+                )
+            })
         }
 
-        let mut rendered_markdown = builder.build();
-        let child_layout_id = rendered_markdown.element.request_layout(cx);
-        let layout_id = cx.request_layout(Style::default(), [child_layout_id]);
-        (layout_id, rendered_markdown)
-    }
+        let (code_bounds, background_bounds) = background_bounds(px(3.), cx);
+        assert_eq!(code_bounds.len(), 1);
+        assert_eq!(
+            background_bounds,
+            vec![Bounds::new(
+                point(code_bounds[0].left() - px(3.), code_bounds[0].top()),
+                size(
+                    code_bounds[0].size.width + px(6.),
+                    code_bounds[0].size.height
+                ),
+            )]
+        );
 
-    fn prepaint(
-        &mut self,
-        _id: Option<&GlobalElementId>,
-        bounds: Bounds<Pixels>,
-        rendered_markdown: &mut Self::RequestLayoutState,
-        cx: &mut WindowContext,
-    ) -> Self::PrepaintState {
-        let hitbox = cx.insert_hitbox(bounds, false);
-        rendered_markdown.element.prepaint(cx);
-        self.autoscroll(&rendered_markdown.text, cx);
-        hitbox
+        // Without padding, the background is left to the text runs.
+        let (_, background_bounds) = background_bounds(Pixels::ZERO, cx);
+        assert!(background_bounds.is_empty());
     }
 
-    fn paint(
-        &mut self,
-        _id: Option<&GlobalElementId>,
-        bounds: Bounds<Pixels>,
-        rendered_markdown: &mut Self::RequestLayoutState,
-        hitbox: &mut Self::PrepaintState,
-        cx: &mut WindowContext,
-    ) {
-        let focus_handle = self.markdown.read(cx).focus_handle.clone();
-        cx.set_focus_handle(&focus_handle);
-
-        let mut context = KeyContext::default();
-        context.add("Markdown");
-        cx.set_key_context(context);
-        let view = self.markdown.clone();
-        cx.on_action(std::any::TypeId::of::<crate::Copy>(), {
-            let text = rendered_markdown.text.clone();
-            move |_, phase, cx| {
-                let text = text.clone();
-                if phase == DispatchPhase::Bubble {
-                    view.update(cx, move |this, cx| this.copy(&text, cx))
-                }
+    #[gpui::test]
+    fn test_render_markdown_static(cx: &mut TestAppContext) {
+        struct StaticMarkdown;
+
+        impl Render for StaticMarkdown {
+            fn render(&mut self, _: &mut ViewContext<Self>) -> impl IntoElement {
+                let style = MarkdownStyle::default();
+                div()
+                    .debug_selector(|| "STATIC-MARKDOWN".into())
+                    .child(render_markdown_static(
+                        "Some code:\n\n```rust\nfn main() {}\n```\n",
+                        &style,
+                        style.syntax.clone(),
+                    ))
             }
+        }
+
+        let (_, cx) = cx.add_window_view(|_| StaticMarkdown);
+        cx.run_until_parked();
+        let bounds = cx.debug_bounds("STATIC-MARKDOWN").unwrap();
+        assert!(bounds.size.width > px(0.));
+        // The paragraph and the code block are each at least a line tall.
+        let line_height = cx.update(|cx| cx.line_height());
+        assert!(bounds.size.height >= line_height * 2.);
+    }
+
+    #[gpui::test]
+    fn test_hovered_line_background(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "First line.\n\nSecond line.\n".into(),
+                MarkdownStyle {
+                    hovered_line_background: Some(gpui::red()),
+                    ..Default::default()
+                },
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        let lines = markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.hovered_line_bounds(), None);
+            markdown.rendered_lines()
+        });
+        assert_eq!(lines.len(), 2);
+        let notifications = Rc::new(Cell::new(0));
+        let _subscription = cx.update(|cx| {
+            let notifications = notifications.clone();
+            cx.observe(&markdown, move |_, _| {
+                notifications.set(notifications.get() + 1)
+            })
+        });
+
+        cx.simulate_mouse_move(lines[1].bounds.center(), None, Modifiers::default());
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.hovered_line, Some(lines[1].source_range.clone()));
+            assert_eq!(markdown.hovered_line_bounds(), Some(lines[1].bounds));
         });
+        assert_eq!(notifications.get(), 1);
 
-        self.paint_mouse_listeners(hitbox, &rendered_markdown.text, cx);
-        rendered_markdown.element.paint(cx);
-        self.paint_selection(bounds, &rendered_markdown.text, cx);
+        // Moving within the line doesn't need a redraw.
+        let position = lines[1].bounds.center() + point(px(5.), px(0.));
+        cx.simulate_mouse_move(position, None, Modifiers::default());
+        assert_eq!(notifications.get(), 1);
+
+        let below = point(px(1.), lines[1].bounds.bottom() + px(20.));
+        cx.simulate_mouse_move(below, None, Modifiers::default());
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.hovered_line, None);
+            assert_eq!(markdown.hovered_line_bounds(), None);
+        });
     }
-}
 
-impl IntoElement for MarkdownElement {
-    type Element = Self;
+    #[gpui::test]
+    fn test_bounds_for_source_range(cx: &mut TestAppContext) {
+        let source = "Hello brave new world.\n\nSecond paragraph.";
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx)
+        });
+        cx.run_until_parked();
 
-    fn into_element(self) -> Self::Element {
-        self
+        markdown.update(cx, |markdown, cx| {
+            let bounds = markdown.bounds_for_source_range(6..11).unwrap();
+            assert_eq!(bounds.len(), 1);
+            assert!(bounds[0].size.width > px(0.));
+            assert!(bounds[0].size.height > px(0.));
+            let line_bounds = markdown.rendered_lines()[0].bounds;
+            assert!(line_bounds.contains(&bounds[0].origin));
+            assert!(line_bounds.contains(&bounds[0].lower_right()));
+            let center_index = markdown.source_index_at(bounds[0].center()).unwrap();
+            assert!((6..11).contains(&center_index));
+
+            let unpainted = cx.new_view(|cx| {
+                Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx)
+            });
+            assert_eq!(unpainted.read(cx).bounds_for_source_range(6..11), None);
+        });
     }
-}
 
-struct MarkdownElementBuilder {
-    div_stack: Vec<Div>,
-    rendered_lines: Vec<RenderedLine>,
-    pending_line: PendingLine,
-    rendered_links: Vec<RenderedLink>,
-    current_source_index: usize,
-    base_text_style: TextStyle,
-    text_style_stack: Vec<TextStyleRefinement>,
-    code_block_stack: Vec<Option<Arc<Language>>>,
-    list_stack: Vec<ListStackEntry>,
-    syntax_theme: Arc<SyntaxTheme>,
-}
+    #[gpui::test]
+    fn test_context_menu(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+        });
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "Visit [Zed](https://zed.dev) today.".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        let (visit, link) = markdown.update(cx, |markdown, _| {
+            (
+                markdown.bounds_for_source_range(0..5).unwrap()[0].center(),
+                markdown.link_bounds()[0].1[0].center(),
+            )
+        });
+        let entries = |cx: &mut VisualTestContext| {
+            markdown.update(cx, |markdown, _| {
+                markdown
+                    .context_menu
+                    .as_ref()
+                    .map(|context_menu| context_menu.entries.clone())
+            })
+        };
 
-#[derive(Default)]
-struct PendingLine {
-    text: String,
-    runs: Vec<TextRun>,
-    source_mappings: Vec<SourceMapping>,
-}
+        // There's nothing to act on without a selection or a link.
+        cx.simulate_mouse_down(visit, MouseButton::Right, Modifiers::default());
+        cx.run_until_parked();
+        assert_eq!(entries(cx), None);
 
-struct ListStackEntry {
-    bullet_index: Option<u64>,
-}
+        cx.simulate_mouse_down(link, MouseButton::Right, Modifiers::default());
+        cx.run_until_parked();
+        assert_eq!(
+            entries(cx),
+            Some(vec![ContextMenuEntry::OpenLink("https://zed.dev".into())])
+        );
 
-impl MarkdownElementBuilder {
-    fn new(base_text_style: TextStyle, syntax_theme: Arc<SyntaxTheme>) -> Self {
-        Self {
-            div_stack: vec![div().debug_selector(|| "inner".into())],
-            rendered_lines: Vec::new(),
-            pending_line: PendingLine::default(),
-            rendered_links: Vec::new(),
-            current_source_index: 0,
-            base_text_style,
-            text_style_stack: Vec::new(),
-            code_block_stack: Vec::new(),
-            list_stack: Vec::new(),
-            syntax_theme,
-        }
-    }
+        // Clicking outside the menu dismisses it.
+        cx.simulate_click(visit, Modifiers::default());
+        cx.run_until_parked();
+        assert_eq!(entries(cx), None);
 
-    fn push_text_style(&mut self, style: TextStyleRefinement) {
-        self.text_style_stack.push(style);
+        markdown.update(cx, |markdown, cx| {
+            markdown.selection = Selection {
+                start: 0,
+                end: 5,
+                reversed: false,
+                pending: false,
+            };
+            cx.notify();
+        });
+        cx.run_until_parked();
+        cx.simulate_mouse_down(visit, MouseButton::Right, Modifiers::default());
+        cx.run_until_parked();
+        assert_eq!(
+            entries(cx),
+            Some(vec![
+                ContextMenuEntry::Copy,
+                ContextMenuEntry::CopyAsMarkdown
+            ])
+        );
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.selection.start..markdown.selection.end, 0..5);
+        });
     }
 
-    fn text_style(&self) -> TextStyle {
-        let mut style = self.base_text_style.clone();
-        for refinement in &self.text_style_stack {
-            style.refine(refinement);
-        }
-        style
-    }
+    #[gpui::test]
+    fn test_on_link_clicked(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "See [the docs](docs/intro.md) and [Zed](https://zed.dev).".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        let clicks = Rc::new(RefCell::new(Vec::new()));
+        markdown.update(cx, |markdown, _| {
+            let clicks = clicks.clone();
+            markdown.on_link_clicked(move |url, source_range, _| {
+                clicks.borrow_mut().push((url.to_string(), source_range))
+            });
+        });
+        cx.run_until_parked();
 
-    fn pop_text_style(&mut self) {
-        self.text_style_stack.pop();
+        let link_bounds = markdown.update(cx, |markdown, _| markdown.link_bounds());
+        assert_eq!(link_bounds.len(), 2);
+        cx.simulate_click(link_bounds[1].1[0].center(), Modifiers::default());
+        cx.simulate_click(link_bounds[0].1[0].center(), Modifiers::default());
+        assert_eq!(
+            *clicks.borrow(),
+            [
+                ("https://zed.dev".to_string(), 34..56),
+                ("docs/intro.md".to_string(), 4..29),
+            ]
+        );
     }
 
-    fn push_div(&mut self, div: Div) {
-        self.flush_text();
-        self.div_stack.push(div);
-    }
+    #[gpui::test]
+    fn test_code_block_info_string(cx: &mut TestAppContext) {
+        let language_registry = Arc::new(LanguageRegistry::test(cx.executor()));
+        language_registry.add(languages::language("rust", tree_sitter_rust::language()));
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "```rust,ignore\nfn main() {}\n```\n".into(),
+                MarkdownStyle::default(),
+                Some(language_registry.clone()),
+                cx,
+            )
+        });
+        let decorated = Rc::new(RefCell::new(Vec::new()));
+        markdown.update(cx, |markdown, cx| {
+            let decorated = decorated.clone();
+            markdown.code_block_decorator(move |language, metadata, div| {
+                decorated
+                    .borrow_mut()
+                    .push((language.to_string(), metadata.to_string()));
+                div
+            });
+            cx.notify();
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            decorated.borrow().last(),
+            Some(&("rust".to_string(), "ignore".to_string()))
+        );
 
-    fn pop_div(&mut self) {
-        self.flush_text();
-        let div = self.div_stack.pop().unwrap().into_any();
-        self.div_stack.last_mut().unwrap().extend(iter::once(div));
+        let element = MarkdownElement::new(
+            markdown.clone(),
+            MarkdownStyle::default(),
+            Some(language_registry),
+        );
+        let parsed = cx.update(|cx| markdown.read(cx).parsed_markdown().clone());
+        let language = parsed.events.iter().find_map(|(_, event)| match event {
+            MarkdownEvent::Start(MarkdownTag::CodeBlock(CodeBlockKind::Fenced {
+                language,
+                ..
+            })) => Some(language.clone()),
+            _ => None,
+        });
+        cx.update(|cx| {
+            let language = element.load_language(&language.unwrap(), cx);
+            assert_eq!(
+                language.map(|language| language.name()),
+                Some("Rust".into())
+            );
+        });
     }
 
-    fn push_list(&mut self, bullet_index: Option<u64>) {
-        self.list_stack.push(ListStackEntry { bullet_index });
-    }
+    #[gpui::test]
+    fn test_math_renderer(cx: &mut TestAppContext) {
+        let source = "Area is $\\pi r^2$.\n\n$$\n\\sum_{i=1}^n i\n$$\n";
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                source.into(),
+                MarkdownStyle {
+                    math: true,
+                    ..Default::default()
+                },
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(
+                markdown.export_plain_text(None),
+                "Area is \\pi r^2.\n\\sum_{i=1}^n i"
+            );
+        });
 
-    fn next_bullet_index(&mut self) -> Option<u64> {
-        self.list_stack.last_mut().and_then(|entry| {
-            let item_index = entry.bullet_index.as_mut()?;
-            *item_index += 1;
-            Some(*item_index - 1)
-        })
+        let formulas = Rc::new(RefCell::new(Vec::new()));
+        markdown.update(cx, |markdown, cx| {
+            let formulas = formulas.clone();
+            markdown.math_renderer(move |tex, display| {
+                formulas.borrow_mut().push((tex.to_string(), display));
+                div().size_4().into_any_element()
+            });
+            cx.notify();
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            *formulas.borrow(),
+            [
+                ("\\pi r^2".to_string(), false),
+                ("\\sum_{i=1}^n i".to_string(), true)
+            ]
+        );
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.export_plain_text(None), "Area is \n.");
+        });
     }
 
-    fn pop_list(&mut self) {
-        self.list_stack.pop();
+    #[gpui::test]
+    fn test_code_block_renderer(cx: &mut TestAppContext) {
+        let source = "```run\necho hi\n```\n\n```sh\nls\n```\n";
+        let blocks = Rc::new(RefCell::new(Vec::new()));
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            let mut markdown =
+                Markdown::new_synchronous(source.into(), MarkdownStyle::default(), None, cx);
+            let blocks = blocks.clone();
+            markdown.register_code_block_renderer("run", move |code, range, _| {
+                blocks.borrow_mut().push((code.to_string(), range));
+                div().size_4().into_any_element()
+            });
+            markdown
+        });
+        cx.run_until_parked();
+
+        assert_eq!(*blocks.borrow(), [("echo hi\n".to_string(), 0..18)]);
+        // Blocks of other languages are still rendered as code.
+        markdown.update(cx, |markdown, _| {
+            let lines = markdown.rendered_lines();
+            assert_eq!(lines.len(), 1);
+            assert_eq!(lines[0].source_range.start, 26);
+        });
     }
 
-    fn push_code_block(&mut self, language: Option<Arc<Language>>) {
-        self.code_block_stack.push(language);
+    #[gpui::test]
+    fn test_heading_sizes(cx: &mut TestAppContext) {
+        let mut heading_sizes = MarkdownStyle::default().heading_sizes;
+        heading_sizes[4] = rems(1.);
+        heading_sizes[5] = rems(2.);
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "##### Five\n\n###### Six\n".into(),
+                MarkdownStyle {
+                    heading_sizes,
+                    ..Default::default()
+                },
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            let lines = markdown.rendered_lines();
+            assert_eq!(lines.len(), 2);
+            // Line heights are proportional to the font size.
+            assert!((lines[1].line_height - lines[0].line_height * 2.).abs() < px(1.));
+        });
     }
 
-    fn pop_code_block(&mut self) {
-        self.code_block_stack.pop();
+    #[gpui::test]
+    fn test_line_height(cx: &mut TestAppContext) {
+        let style = MarkdownStyle {
+            line_height: rems(2.),
+            ..Default::default()
+        };
+        let heading_size = style.heading_sizes[0];
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous("# Title\n\nOne\n\n- Two\n".into(), style, None, cx)
+        });
+        cx.run_until_parked();
+        let rem_size = cx.update(|cx| cx.rem_size());
+
+        markdown.update(cx, |markdown, _| {
+            let lines = markdown.rendered_lines();
+            assert_eq!(lines.len(), 3);
+            // Headings scale the line height by their font size.
+            let heading_line_height = heading_size.to_pixels(rem_size) * 2.;
+            assert!((lines[0].line_height - heading_line_height).abs() < px(1.));
+            assert_eq!(lines[1].line_height, rem_size * 2.);
+            assert_eq!(lines[2].line_height, rem_size * 2.);
+
+            // Selections span the full height of the line they're on.
+            let selection = markdown.bounds_for_source_range(9..12).unwrap();
+            assert_eq!(selection.len(), 1);
+            assert_eq!(selection[0].size.height, lines[1].line_height);
+            assert_eq!(selection[0].top(), lines[1].bounds.top());
+        });
     }
 
-    fn push_link(&mut self, destination_url: SharedString, source_range: Range<usize>) {
-        self.rendered_links.push(RenderedLink {
-            source_range,
-            destination_url,
+    #[gpui::test]
+    fn test_callouts(cx: &mut TestAppContext) {
+        assert_eq!(
+            parse_callout_marker("[!WARNING]\nBody\n"),
+            Some((CalloutKind::Warning, 11))
+        );
+        assert_eq!(
+            parse_callout_marker("[!note]"),
+            Some((CalloutKind::Note, 7))
+        );
+        assert_eq!(parse_callout_marker("[!NOTE] inline\n"), None);
+        assert_eq!(parse_callout_marker("[!UNKNOWN]\n"), None);
+
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "> [!WARNING]\n> Be *careful* here.\n\n> Just a quote.\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(
+                markdown.export_plain_text(None),
+                "Warning\nBe careful here.\nJust a quote."
+            );
         });
     }
 
-    fn push_text(&mut self, text: &str, source_index: usize) {
-        self.pending_line.source_mappings.push(SourceMapping {
-            rendered_index: self.pending_line.text.len(),
-            source_index,
+    #[gpui::test]
+    fn test_footnotes(cx: &mut TestAppContext) {
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "One[^a] two[^b].\n\n[^a]: First note.\n\n[^b]: Second note.\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
         });
-        self.pending_line.text.push_str(text);
-        self.current_source_index = source_index + text.len();
+        cx.run_until_parked();
 
-        if let Some(Some(language)) = self.code_block_stack.last() {
-            let mut offset = 0;
-            for (range, highlight_id) in language.highlight_text(&Rope::from(text), 0..text.len()) {
-                if range.start > offset {
-                    self.pending_line
-                        .runs
-                        .push(self.text_style().to_run(range.start - offset));
-                }
+        markdown.update(cx, |markdown, _| {
+            let rendered_text = markdown.rendered_text.as_ref().unwrap();
+            let links = rendered_text
+                .links
+                .iter()
+                .map(|link| (link.destination_url.to_string(), link.source_range.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                links,
+                [("#fn-a".to_string(), 3..7), ("#fn-b".to_string(), 11..15)]
+            );
+            assert_eq!(rendered_text.footnote_definition("a"), "First note.");
+            assert_eq!(rendered_text.footnote_definition("b"), "Second note.");
+        });
+    }
 
-                let mut run_style = self.text_style();
-                if let Some(highlight) = highlight_id.style(&self.syntax_theme) {
-                    run_style = run_style.highlight(highlight);
-                }
-                self.pending_line.runs.push(run_style.to_run(range.len()));
-                offset = range.end;
-            }
+    #[gpui::test]
+    fn test_toggle_task(cx: &mut TestAppContext) {
+        let markdown = cx.add_window(|cx| {
+            Markdown::new_synchronous(
+                "- [ ] todo\n- [x] done\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        let changed_sources = Rc::new(RefCell::new(Vec::new()));
+        markdown
+            .update(cx, |markdown, cx| {
+                let changed_sources = changed_sources.clone();
+                markdown.on_source_changed(move |source, _| {
+                    changed_sources.borrow_mut().push(source.to_string())
+                });
 
-            if offset < text.len() {
-                self.pending_line
-                    .runs
-                    .push(self.text_style().to_run(text.len() - offset));
-            }
-        } else {
-            self.pending_line
-                .runs
-                .push(self.text_style().to_run(text.len()));
-        }
+                markdown.toggle_task(2..5, 0..11, cx);
+                assert_eq!(markdown.source(), "- [x] todo\n- [x] done\n");
+                markdown.toggle_task(13..16, 11..22, cx);
+                assert_eq!(markdown.source(), "- [x] todo\n- [ ] done\n");
+                // Ranges that no longer point at a task marker are ignored.
+                markdown.toggle_task(6..9, 0..11, cx);
+                assert_eq!(markdown.source(), "- [x] todo\n- [ ] done\n");
+            })
+            .unwrap();
+        assert_eq!(
+            *changed_sources.borrow(),
+            ["- [x] todo\n- [x] done\n", "- [x] todo\n- [ ] done\n"]
+        );
+    }
+
+    #[gpui::test]
+    fn test_on_toggle_task(cx: &mut TestAppContext) {
+        let markdown = cx.add_window(|cx| {
+            Markdown::new_synchronous(
+                "- [ ] todo\n- [x] done\n".into(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        let toggles = Rc::new(RefCell::new(Vec::new()));
+        markdown
+            .update(cx, |markdown, cx| {
+                let toggles = toggles.clone();
+                markdown.on_toggle_task(move |item_range, checked| {
+                    toggles.borrow_mut().push((item_range, checked))
+                });
+
+                markdown.toggle_task(13..16, 11..22, cx);
+                markdown.toggle_task(2..5, 0..11, cx);
+            })
+            .unwrap();
+        assert_eq!(*toggles.borrow(), [(11..22, false), (0..11, true)]);
     }
 
-    fn trim_trailing_newline(&mut self) {
-        if self.pending_line.text.ends_with('\n') {
-            self.pending_line
-                .text
-                .truncate(self.pending_line.text.len() - 1);
-            self.pending_line.runs.last_mut().unwrap().len -= 1;
-            self.current_source_index -= 1;
+    #[test]
+    fn test_placeholder_link_destinations() {
+        let parsed = ParsedMarkdown::new("[empty]() [hash](#) [real](https://zed.dev)".into());
+        let destinations = parsed
+            .events
+            .iter()
+            .filter_map(|(_, event)| match event {
+                MarkdownEvent::Start(MarkdownTag::Link { dest_url, .. }) => {
+                    Some(dest_url.to_string())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(destinations, ["", "#", "https://zed.dev"]);
+        assert!(!is_openable_url(""));
+        assert!(!is_openable_url("#"));
+        assert!(!is_openable_url("#section"));
+        assert!(is_openable_url("https://zed.dev"));
+    }
+
+    #[test]
+    fn test_scaled_text_sizes() {
+        let rem_size = px(16.);
+        for font_size in [
+            AbsoluteLength::Pixels(px(14.)),
+            AbsoluteLength::Rems(rems(0.875)),
+        ] {
+            let (scaled_rem_size, scaled_font_size) = scaled_text_sizes(rem_size, font_size, 2.);
+            assert_eq!(scaled_rem_size, px(32.));
+            assert_eq!(
+                scaled_font_size.to_pixels(scaled_rem_size),
+                font_size.to_pixels(rem_size) * 2.
+            );
         }
     }
 
-    fn flush_text(&mut self) {
-        let line = mem::take(&mut self.pending_line);
-        if line.text.is_empty() {
-            return;
+    #[test]
+    fn test_headings() {
+        let parsed = ParsedMarkdown::new("# Intro\n\ntext\n\n## The `main` fn\n".into());
+        assert_eq!(
+            parsed.headings(),
+            vec![
+                MarkdownHeading {
+                    level: HeadingLevel::H1,
+                    text: "Intro".into(),
+                    slug: "intro".into(),
+                    source_range: 0..8,
+                },
+                MarkdownHeading {
+                    level: HeadingLevel::H2,
+                    text: "The main fn".into(),
+                    slug: "the-main-fn".into(),
+                    source_range: 15..32,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heading_slugs() {
+        let parsed = ParsedMarkdown::new("# Intro\n## Intro\n## What's new?\n# Intro\n".into());
+        let slugs = parsed
+            .headings()
+            .into_iter()
+            .map(|heading| heading.slug)
+            .collect::<Vec<_>>();
+        assert_eq!(slugs, ["intro", "intro-1", "whats-new", "intro-2"]);
+    }
+
+    #[gpui::test]
+    fn test_reset_discards_stale_parse(cx: &mut TestAppContext) {
+        let markdown =
+            cx.new_view(|cx| Markdown::new("# Old\n".into(), MarkdownStyle::default(), None, cx));
+        // Keep the old parse running, rather than letting `reset` drop it.
+        let _stale_parse = markdown.update(cx, |markdown, cx| {
+            let stale_parse = markdown.pending_parse.take();
+            markdown.reset("New\n".into(), cx);
+            stale_parse
+        });
+        cx.run_until_parked();
+
+        markdown.update(cx, |markdown, _| {
+            assert_eq!(markdown.parsed_markdown.source.as_ref(), "New\n");
+            assert!(matches!(
+                markdown.parsed_markdown.events[0].1,
+                MarkdownEvent::Start(MarkdownTag::Paragraph)
+            ));
+        });
+    }
+
+    #[gpui::test]
+    fn test_reset_keeps_collapsed_headings(cx: &mut TestAppContext) {
+        fn heading_ranges(markdown: &Markdown) -> Vec<Range<usize>> {
+            markdown
+                .parsed_markdown
+                .events
+                .iter()
+                .filter_map(|(range, event)| match event {
+                    MarkdownEvent::Start(MarkdownTag::Heading { .. }) => Some(range.clone()),
+                    _ => None,
+                })
+                .collect()
         }
 
-        let text = StyledText::new(line.text).with_runs(line.runs);
-        self.rendered_lines.push(RenderedLine {
-            layout: text.layout().clone(),
-            source_mappings: line.source_mappings,
-            source_end: self.current_source_index,
+        let (markdown, cx) = cx.add_window_view(|cx| {
+            Markdown::new_synchronous(
+                "# One\n\n# Two\n\n# Three\n".to_string(),
+                MarkdownStyle::default(),
+                None,
+                cx,
+            )
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, cx| {
+            markdown.collapsed_headings = heading_ranges(markdown).into_iter().collect();
+            markdown.reset("# One\n\n# Two!\n\n# Three\n".to_string(), cx);
+        });
+        cx.run_until_parked();
+        markdown.update(cx, |markdown, _| {
+            let headings = heading_ranges(markdown);
+            assert_eq!(
+                markdown.collapsed_headings,
+                HashSet::from_iter([headings[0].clone(), headings[2].clone()])
+            );
         });
-        self.div_stack.last_mut().unwrap().extend([text.into_any()]);
     }
 
-    fn build(mut self) -> RenderedMarkdown {
-        debug_assert_eq!(self.div_stack.len(), 1);
-        self.flush_text();
-        RenderedMarkdown {
-            element: self.div_stack.pop().unwrap().into_any(),
-            text: RenderedText {
-                lines: self.rendered_lines.into(),
-                links: self.rendered_links.into(),
-            },
+    #[test]
+    fn test_block_diff() {
+        let old = ParsedMarkdown::new("# Title\n\nFirst paragraph.\n\nSecond paragraph.\n".into());
+        let new = ParsedMarkdown::new("# Title\n\nSecond paragraph.\n\n- New item\n".into());
+        assert_eq!(
+            old.block_diff(&new),
+            vec![
+                BlockDiff::Unchanged {
+                    old: 0..8,
+                    new: 0..8,
+                },
+                BlockDiff::Removed(9..26),
+                BlockDiff::Unchanged {
+                    old: 27..45,
+                    new: 9..27,
+                },
+                BlockDiff::Added(28..39),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heading_numbering() {
+        let mut numbering = HeadingNumbering::default();
+        assert_eq!(numbering.next(HeadingLevel::H1), "1");
+        assert_eq!(numbering.next(HeadingLevel::H2), "1.1");
+        assert_eq!(numbering.next(HeadingLevel::H2), "1.2");
+        assert_eq!(numbering.next(HeadingLevel::H3), "1.2.1");
+        assert_eq!(numbering.next(HeadingLevel::H1), "2");
+        // Skipped levels are numbered as 1.
+        assert_eq!(numbering.next(HeadingLevel::H3), "2.1.1");
+        assert_eq!(numbering.next(HeadingLevel::H2), "2.2");
+
+        // Documents that start below H1 aren't prefixed with a zero.
+        let mut numbering = HeadingNumbering::default();
+        assert_eq!(numbering.next(HeadingLevel::H2), "1");
+        assert_eq!(numbering.next(HeadingLevel::H3), "1.1");
+        assert_eq!(numbering.next(HeadingLevel::H2), "2");
+    }
+
+    #[test]
+    fn test_truncated_ranges() {
+        let text = "https://example.com/a/b/c";
+        assert_eq!(truncated_ranges(text, 25, EllipsisMode::Middle), None);
+
+        let truncate = |mode| {
+            let (head, tail) = truncated_ranges(text, 11, mode).unwrap();
+            format!("{}…{}", &text[head], &text[tail])
+        };
+        assert_eq!(truncate(EllipsisMode::Start), "….com/a/b/c");
+        assert_eq!(truncate(EllipsisMode::Middle), "https…a/b/c");
+        assert_eq!(truncate(EllipsisMode::End), "https://ex…");
+
+        let (head, tail) = truncated_ranges("αβγδε", 3, EllipsisMode::End).unwrap();
+        assert_eq!(head, 0.."αβ".len());
+        assert!(tail.is_empty());
+    }
+
+    #[test]
+    fn test_table_cells() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        let text = "| a | b | c |\n|:--|:-:|--:|\n| 1 | 2 | 3 |\n";
+        let mut alignments = Vec::new();
+        for (range, event) in parse_markdown(text, ParseOptions::default()) {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::Table(column_alignments)) => {
+                    builder.push_table(column_alignments, div())
+                }
+                MarkdownEvent::Start(MarkdownTag::TableHead | MarkdownTag::TableRow) => {
+                    builder.push_table_row(div())
+                }
+                MarkdownEvent::Start(MarkdownTag::TableCell) => {
+                    alignments.push(builder.push_table_cell())
+                }
+                MarkdownEvent::Text => builder.push_text(&text[range.clone()], range.start),
+                MarkdownEvent::End(MarkdownTagEnd::Table) => builder.pop_table(),
+                MarkdownEvent::End(_) => builder.pop_div(),
+                _ => {}
+            }
         }
+
+        assert_eq!(
+            alignments,
+            [
+                Alignment::Left,
+                Alignment::Center,
+                Alignment::Right,
+                Alignment::Left,
+                Alignment::Center,
+                Alignment::Right,
+            ]
+        );
+        let separators = builder
+            .rendered_lines
+            .iter()
+            .map(|line| line.copy_separator.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            separators,
+            [None, Some("\t"), Some("\t"), None, Some("\t"), Some("\t")]
+        );
     }
-}
 
-struct RenderedLine {
-    layout: TextLayout,
-    source_mappings: Vec<SourceMapping>,
-    source_end: usize,
-}
+    #[test]
+    fn test_highlighted_inline_code() {
+        let syntax_theme = Arc::new(SyntaxTheme::new_test([("keyword", gpui::red())]));
+        let rust = languages::language("rust", tree_sitter_rust::language());
+        rust.set_theme(&syntax_theme);
 
-impl RenderedLine {
-    fn rendered_index_for_source_index(&self, source_index: usize) -> usize {
-        let mapping = match self
-            .source_mappings
-            .binary_search_by_key(&source_index, |probe| probe.source_index)
-        {
-            Ok(ix) => &self.source_mappings[ix],
-            Err(ix) => &self.source_mappings[ix - 1],
+        let runs = |language: Option<&Arc<Language>>| {
+            let mut builder =
+                MarkdownElementBuilder::new(TextStyle::default(), syntax_theme.clone(), div());
+            builder.push_highlighted_text("fn main", 0, language);
+            builder.pending_line.runs
         };
-        mapping.rendered_index + (source_index - mapping.source_index)
+        let plain_runs = runs(None);
+        let highlighted_runs = runs(Some(&rust));
+        assert_eq!(plain_runs.len(), 1);
+        assert_ne!(highlighted_runs, plain_runs);
+        assert_eq!(highlighted_runs[0].len, 2);
+        assert_eq!(highlighted_runs[0].color, gpui::red());
     }
 
-    fn source_index_for_rendered_index(&self, rendered_index: usize) -> usize {
-        let mapping = match self
-            .source_mappings
-            .binary_search_by_key(&rendered_index, |probe| probe.rendered_index)
-        {
-            Ok(ix) => &self.source_mappings[ix],
-            Err(ix) => &self.source_mappings[ix - 1],
+    #[test]
+    fn test_diagnostics() {
+        let parsed = ParsedMarkdown::new(
+            "Hi <span>there</span> <b>x</b> <!-- note --> ![x][missing] ![y][ok]\n\n[ok]: y.png\n"
+                .into(),
+        );
+        assert_eq!(
+            parsed.diagnostics(),
+            [
+                MarkdownDiagnostic {
+                    kind: MarkdownDiagnosticKind::UnsupportedHtmlTag,
+                    range: 3..9,
+                },
+                MarkdownDiagnostic {
+                    kind: MarkdownDiagnosticKind::UnsupportedHtmlTag,
+                    range: 14..21,
+                },
+                MarkdownDiagnostic {
+                    kind: MarkdownDiagnosticKind::UnresolvedImageReference,
+                    range: 45..58,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_html() {
+        assert_eq!(
+            html_tags("<br> <B class=\"x\"></b> <br/> <span></span> <sub/>"),
+            [
+                (0..4, HtmlTag::LineBreak),
+                (5..18, HtmlTag::Start(HtmlElement::Bold)),
+                (18..22, HtmlTag::End(HtmlElement::Bold)),
+                (23..28, HtmlTag::LineBreak),
+                (29..35, HtmlTag::Unsupported),
+                (35..42, HtmlTag::Unsupported),
+                (43..49, HtmlTag::Unsupported),
+            ]
+        );
+
+        let style = MarkdownStyle {
+            render_inline_html: true,
+            ..Default::default()
         };
-        mapping.source_index + (rendered_index - mapping.rendered_index)
+        let mut builder =
+            MarkdownElementBuilder::new(TextStyle::default(), style.syntax.clone(), div());
+        builder.push_text("a", 0);
+        builder.push_html("<br>", 1, 1, &style);
+        builder.push_text("b ", 5);
+        builder.push_html("<b>", 7, 1, &style);
+        builder.push_text("x", 10);
+        builder.push_html("</b>", 11, 1, &style);
+        builder.push_html("</i><span>", 15, 1, &style);
+
+        let line = &builder.pending_line;
+        assert_eq!(line.text, "a\nb x</i><span>");
+        let bold_runs = line
+            .runs
+            .iter()
+            .scan(0, |offset, run| {
+                *offset += run.len;
+                Some((*offset - run.len..*offset, run.font.weight))
+            })
+            .filter(|(_, weight)| *weight == FontWeight::BOLD)
+            .map(|(range, _)| range)
+            .collect::<Vec<_>>();
+        assert_eq!(bold_runs, [5..6]);
+
+        // Elements left open end with their block.
+        builder.push_html("<em>", 25, 1, &style);
+        assert_eq!(builder.text_style().font_style, FontStyle::Italic);
+        builder.pop_html_elements(0);
+        assert_eq!(builder.text_style().font_style, FontStyle::Normal);
     }
 
-    fn source_index_for_position(&self, position: Point<Pixels>) -> Result<usize, usize> {
-        let line_rendered_index;
-        let out_of_bounds;
-        match self.layout.index_for_position(position) {
-            Ok(ix) => {
-                line_rendered_index = ix;
-                out_of_bounds = false;
-            }
-            Err(ix) => {
-                line_rendered_index = ix;
-                out_of_bounds = true;
-            }
+    #[test]
+    fn test_strikethrough_style() {
+        let strikethrough = StrikethroughStyle {
+            thickness: px(3.),
+            color: Some(gpui::red()),
         };
-        let source_index = self.source_index_for_rendered_index(line_rendered_index);
-        if out_of_bounds {
-            Err(source_index)
-        } else {
-            Ok(source_index)
-        }
+        let style = MarkdownStyle {
+            strikethrough,
+            ..Default::default()
+        };
+        let mut builder =
+            MarkdownElementBuilder::new(TextStyle::default(), style.syntax.clone(), div());
+        builder.push_text("kept ", 0);
+        builder.push_text_style(style.strikethrough_text_style());
+        builder.push_text("removed", 7);
+        builder.pop_text_style();
+
+        let runs = &builder.pending_line.runs;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].strikethrough, None);
+        assert_eq!(runs[1].strikethrough, Some(strikethrough));
     }
-}
 
-#[derive(Copy, Clone, Debug, Default)]
-struct SourceMapping {
-    rendered_index: usize,
-    source_index: usize,
-}
+    #[test]
+    fn test_soft_hyphens() {
+        let events = parse_markdown("hy&shy;phen&nbsp;x", ParseOptions::default());
+        assert!(events.iter().any(|(_, event)| {
+            matches!(event, MarkdownEvent::SubstitutedText(text) if text == "\u{ad}")
+        }));
 
-pub struct RenderedMarkdown {
-    element: AnyElement,
-    text: RenderedText,
-}
+        let mut builder =
+            MarkdownElementBuilder::new(TextStyle::default(), Default::default(), div());
+        builder.soft_hyphens = true;
+        builder.push_text("hy", 0);
+        builder.push_substituted_text("\u{ad}", 2..7);
+        builder.push_text("phen", 7);
+        builder.push_substituted_text("\u{a0}", 11..17);
+        builder.push_text("x", 17);
 
-#[derive(Clone)]
-struct RenderedText {
-    lines: Rc<[RenderedLine]>,
-    links: Rc<[RenderedLink]>,
-}
+        // The soft hyphen is kept as a break opportunity, but hidden.
+        let runs = builder
+            .pending_line
+            .runs
+            .iter()
+            .scan(0, |offset, run| {
+                *offset += run.len;
+                Some((*offset - run.len..*offset, run.color))
+            })
+            .filter(|(_, color)| *color == Hsla::transparent_black())
+            .map(|(range, _)| range)
+            .collect::<Vec<_>>();
+        assert_eq!(runs, [2..4]);
 
-#[derive(Clone, Eq, PartialEq)]
-struct RenderedLink {
-    source_range: Range<usize>,
-    destination_url: SharedString,
-}
+        builder.flush_text();
+        let line = &builder.rendered_lines[0];
+        assert_eq!(line.soft_hyphens.len(), 1);
+        assert_eq!(line.soft_hyphens[0].index, 2);
+        assert_eq!(line.soft_hyphens[0].next_char_end, 5);
+    }
 
-impl RenderedText {
-    fn source_index_for_position(&self, position: Point<Pixels>) -> Result<usize, usize> {
-        let mut lines = self.lines.iter().peekable();
+    #[test]
+    fn test_selection_text_color() {
+        let text_color = TextStyle::default().color;
+        let mut builder =
+            MarkdownElementBuilder::new(TextStyle::default(), Default::default(), div());
+        // The selection covers `b **bo` in `ab **bold** cd`.
+        builder.selection_text_color = Some((1..7, gpui::red()));
+        builder.push_text("ab ", 0);
+        builder.push_text_style(TextStyleRefinement {
+            font_weight: Some(FontWeight::BOLD),
+            ..Default::default()
+        });
+        builder.push_text("bold", 5);
+        builder.pop_text_style();
+        builder.push_text(" cd", 11);
 
-        while let Some(line) = lines.next() {
-            let line_bounds = line.layout.bounds();
-            if position.y > line_bounds.bottom() {
-                if let Some(next_line) = lines.peek() {
-                    if position.y < next_line.layout.bounds().top() {
-                        return Err(line.source_end);
-                    }
-                }
+        let runs = builder
+            .pending_line
+            .runs
+            .iter()
+            .scan(0, |offset, run| {
+                *offset += run.len;
+                Some((*offset - run.len..*offset, run.color, run.font.weight))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            runs,
+            [
+                (0..1, text_color, FontWeight::NORMAL),
+                (1..3, gpui::red(), FontWeight::NORMAL),
+                (3..5, gpui::red(), FontWeight::BOLD),
+                (5..7, text_color, FontWeight::BOLD),
+                (7..10, text_color, FontWeight::NORMAL),
+            ]
+        );
+    }
 
-                continue;
+    #[test]
+    fn test_nested_block_quote_colors() {
+        let style = MarkdownStyle {
+            block_quote_border_colors: vec![gpui::red(), gpui::blue()],
+            ..Default::default()
+        };
+        let mut builder =
+            MarkdownElementBuilder::new(TextStyle::default(), style.syntax.clone(), div());
+        let mut border_colors = Vec::new();
+        for (_, event) in parse_markdown("> outer\n>\n> > inner\n", ParseOptions::default()) {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::BlockQuote) => {
+                    border_colors
+                        .push(style.nested_block_quote_border_color(builder.block_quote_depth));
+                    builder.block_quote_depth += 1;
+                }
+                MarkdownEvent::End(MarkdownTagEnd::BlockQuote) => builder.block_quote_depth -= 1,
+                _ => {}
             }
-
-            return line.source_index_for_position(position);
         }
+        assert_eq!(border_colors, [gpui::red(), gpui::blue()]);
+        assert_eq!(builder.block_quote_depth, 0);
+        assert_eq!(style.nested_block_quote_border_color(2), gpui::red());
 
-        Err(self.lines.last().map_or(0, |line| line.source_end))
+        let style = MarkdownStyle {
+            block_quote_border_color: gpui::green(),
+            ..Default::default()
+        };
+        assert_eq!(style.nested_block_quote_border_color(1), gpui::green());
     }
 
-    fn position_for_source_index(&self, source_index: usize) -> Option<(Point<Pixels>, Pixels)> {
-        for line in self.lines.iter() {
-            let line_source_start = line.source_mappings.first().unwrap().source_index;
-            if source_index < line_source_start {
-                break;
-            } else if source_index > line.source_end {
-                continue;
-            } else {
-                let line_height = line.layout.line_height();
-                let rendered_index_within_line = line.rendered_index_for_source_index(source_index);
-                let position = line.layout.position_for_index(rendered_index_within_line)?;
-                return Some((position, line_height));
+    #[test]
+    fn test_substituted_text_mapping() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        let text = "a &mdash; b";
+        for (range, event) in parse_markdown(text, ParseOptions::default()) {
+            match event {
+                MarkdownEvent::Text => builder.push_text(&text[range.clone()], range.start),
+                MarkdownEvent::SubstitutedText(substitution) => {
+                    builder.push_substituted_text(&substitution, range)
+                }
+                _ => {}
             }
         }
-        None
+        builder.flush_text();
+
+        let line = &builder.rendered_lines[0];
+        let em_dash_len = '\u{2014}'.len_utf8();
+        // Indices inside the entity map to its start, keeping selections on
+        // character boundaries.
+        assert_eq!(line.rendered_index_for_source_index(2), 2);
+        assert_eq!(line.rendered_index_for_source_index(5), 2);
+        assert_eq!(line.rendered_index_for_source_index(9), 2 + em_dash_len);
+        assert_eq!(line.rendered_index_for_source_index(10), 3 + em_dash_len);
+        assert_eq!(line.source_index_for_rendered_index(3), 2);
+        assert_eq!(line.source_index_for_rendered_index(2 + em_dash_len), 9);
     }
 
-    fn surrounding_word_range(&self, source_index: usize) -> Range<usize> {
-        for line in self.lines.iter() {
-            if source_index > line.source_end {
-                continue;
+    #[test]
+    fn test_list_bullets() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        let mut bullets = Vec::new();
+        for (_, event) in parse_markdown(
+            "3. first\n   1. nested\n   2. nested\n4. second\n   - bullet\n",
+            ParseOptions::default(),
+        ) {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::List(bullet_index)) => {
+                    builder.push_list(bullet_index)
+                }
+                MarkdownEvent::Start(MarkdownTag::Item) => {
+                    bullets.push(builder.next_bullet(&["•".into()]).to_string())
+                }
+                MarkdownEvent::End(MarkdownTagEnd::List(_)) => builder.pop_list(),
+                _ => {}
             }
+        }
+        assert_eq!(bullets, ["3.", "1.", "2.", "4.", "•"]);
+    }
 
-            let line_rendered_start = line.source_mappings.first().unwrap().rendered_index;
-            let rendered_index_in_line =
-                line.rendered_index_for_source_index(source_index) - line_rendered_start;
-            let text = line.layout.text();
-            let previous_space = if let Some(idx) = text[0..rendered_index_in_line].rfind(' ') {
-                idx + ' '.len_utf8()
-            } else {
-                0
-            };
-            let next_space = if let Some(idx) = text[rendered_index_in_line..].find(' ') {
-                rendered_index_in_line + idx
-            } else {
-                text.len()
-            };
-
-            return line.source_index_for_rendered_index(line_rendered_start + previous_space)
-                ..line.source_index_for_rendered_index(line_rendered_start + next_space);
+    #[test]
+    fn test_unordered_bullets() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        let unordered_bullets = ["•".into(), "◦".into()];
+        let mut bullets = Vec::new();
+        for (_, event) in parse_markdown(
+            "- outer\n  - inner\n    - innermost\n- outer\n",
+            ParseOptions::default(),
+        ) {
+            match event {
+                MarkdownEvent::Start(MarkdownTag::List(bullet_index)) => {
+                    builder.push_list(bullet_index)
+                }
+                MarkdownEvent::Start(MarkdownTag::Item) => {
+                    bullets.push(builder.next_bullet(&unordered_bullets).to_string())
+                }
+                MarkdownEvent::End(MarkdownTagEnd::List(_)) => builder.pop_list(),
+                _ => {}
+            }
         }
+        assert_eq!(bullets, ["•", "◦", "•", "•"]);
+    }
 
-        source_index..source_index
+    #[test]
+    fn test_code_block_contents() {
+        let source = "Text\n\n```rust title=\"main.rs\"\nfn main() {\n\n    todo!()\n}\n```\n";
+        let events = parse_markdown(source, ParseOptions::default());
+        let start_ix = events
+            .iter()
+            .position(|(_, event)| matches!(event, MarkdownEvent::Start(MarkdownTag::CodeBlock(_))))
+            .unwrap();
+        let contents = code_block_contents(&events[start_ix + 1..]);
+        assert_eq!(&source[contents], "fn main() {\n\n    todo!()\n}\n");
+
+        let events = parse_markdown("```\n```\n", ParseOptions::default());
+        assert_eq!(code_block_contents(&events[1..]), 0..0);
     }
 
-    fn surrounding_line_range(&self, source_index: usize) -> Range<usize> {
-        for line in self.lines.iter() {
-            if source_index > line.source_end {
-                continue;
+    #[test]
+    fn test_dedent_code_blocks() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        let source = "```\n    fn main() {\n\n        todo!()\n    }\n```\n";
+        let events = parse_markdown(source, ParseOptions::default());
+        let indent = common_indent(source, &events[1..]);
+        assert_eq!(indent, 4);
+        for (range, event) in events.iter() {
+            if let MarkdownEvent::Text = event {
+                builder.push_dedented_text(&source[range.clone()], range.start, indent, 4);
             }
-            let line_source_start = line.source_mappings.first().unwrap().source_index;
-            return line_source_start..line.source_end;
         }
+        assert_eq!(builder.pending_line.text, "fn main() {\n\n    todo!()\n}\n");
+        // The rendered text stays mapped to the source, past the stripped indentation.
+        let mappings = builder
+            .pending_line
+            .source_mappings
+            .iter()
+            .map(|mapping| (mapping.rendered_index, mapping.source_index))
+            .collect::<Vec<_>>();
+        assert_eq!(mappings, [(0, 8), (12, 20), (13, 25), (25, 41)]);
+    }
 
-        source_index..source_index
+    #[test]
+    fn test_code_tabs_mapping() {
+        let mut builder = MarkdownElementBuilder::new(
+            TextStyle::default(),
+            Arc::new(SyntaxTheme::default()),
+            div(),
+        );
+        builder.push_code_text("\tx\t\ty", 10, 2);
+        assert_eq!(builder.pending_line.text, "  x    y");
+        let mappings = builder
+            .pending_line
+            .source_mappings
+            .iter()
+            .map(|mapping| {
+                (
+                    mapping.rendered_index,
+                    mapping.source_index,
+                    mapping.substituted,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            mappings,
+            [
+                (0, 10, true),
+                (2, 11, false),
+                (3, 12, true),
+                (5, 13, true),
+                (7, 14, false)
+            ]
+        );
     }
 
-    fn text_for_range(&self, range: Range<usize>) -> String {
-        let mut ret = vec![];
+    #[gpui::test]
+    fn test_code_tab_size(cx: &mut TestAppContext) {
+        fn x_position(source: &str, code_tab_size: usize, cx: &mut TestAppContext) -> Pixels {
+            let (markdown, cx) = cx.add_window_view(|cx| {
+                Markdown::new_synchronous(
+                    source.into(),
+                    MarkdownStyle {
+                        code_tab_size,
+                        ..Default::default()
+                    },
+                    None,
+                    cx,
+                )
+            });
+            cx.run_until_parked();
+            markdown.update(cx, |markdown, _| {
+                let (position, _) = markdown
+                    .rendered_text
+                    .as_ref()
+                    .unwrap()
+                    .position_for_source_index(source.find('x').unwrap())
+                    .unwrap();
+                position.x
+            })
+        }
 
-        for line in self.lines.iter() {
-            if range.start > line.source_end {
-                continue;
-            }
-            let line_source_start = line.source_mappings.first().unwrap().source_index;
-            if range.end < line_source_start {
-                break;
-            }
+        let tab_indented = "```\n\tx\n```\n";
+        assert_eq!(
+            x_position(tab_indented, 4, cx),
+            x_position("```\n    x\n```\n", 4, cx)
+        );
+        assert_eq!(
+            x_position(tab_indented, 2, cx),
+            x_position("```\n  x\n```\n", 2, cx)
+        );
+    }
 
-            let text = line.layout.text();
+    #[gpui::test]
+    fn test_image_max_bytes(cx: &mut TestAppContext) {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgba8(2, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        cx.update(|cx| {
+            cx.update_http_client(http::FakeHttpClient::create(move |request| {
+                let response = match request.uri().path() {
+                    "/small.png" => {
+                        http::Response::builder().body(http::AsyncBody::from(png.clone()))
+                    }
+                    "/large.png" => http::Response::builder()
+                        .header("Content-Length", "100000")
+                        .body(http::AsyncBody::empty()),
+                    // Without a Content-Length, the body is read up to the limit.
+                    _ => http::Response::builder().body(http::AsyncBody::from(vec![0; 100_000])),
+                };
+                async move { Ok(response.unwrap()) }
+            }))
+        });
 
-            let start = if range.start < line_source_start {
-                0
-            } else {
-                line.rendered_index_for_source_index(range.start)
-            };
-            let end = if range.end > line.source_end {
-                line.rendered_index_for_source_index(line.source_end)
-            } else {
-                line.rendered_index_for_source_index(range.end)
-            }
-            .min(text.len());
+        let source = "![small](https://zed.dev/small.png) ![large](https://zed.dev/large.png) ![unsized](https://zed.dev/unsized.png)";
+        let style = MarkdownStyle {
+            image_max_bytes: Some(1024),
+            ..Default::default()
+        };
+        let (markdown, cx) =
+            cx.add_window_view(|cx| Markdown::new_synchronous(source.into(), style, None, cx));
+        cx.run_until_parked();
+        let image_states = |cx: &mut VisualTestContext| {
+            markdown.update(cx, |markdown, _| {
+                ["small", "large", "unsized"].map(|name| {
+                    match &markdown.remote_images[format!("https://zed.dev/{name}.png").as_str()] {
+                        RemoteImage::Loading { .. } => "loading",
+                        RemoteImage::Loaded(_) => "loaded",
+                        RemoteImage::TooLarge => "too large",
+                        RemoteImage::Uncapped => "uncapped",
+                        RemoteImage::Failed => "failed",
+                    }
+                })
+            })
+        };
+        assert_eq!(image_states(cx), ["loaded", "too large", "too large"]);
 
-            ret.push(text[start..end].to_string());
-        }
-        ret.join("\n")
+        // Clicking the placeholder of a large image loads it in full.
+        let placeholder = cx
+            .debug_bounds("large-image-https://zed.dev/large.png")
+            .unwrap();
+        cx.simulate_click(placeholder.center(), Modifiers::default());
+        assert_eq!(image_states(cx), ["loaded", "uncapped", "too large"]);
     }
 
-    fn link_for_position(&self, position: Point<Pixels>) -> Option<&RenderedLink> {
-        let source_index = self.source_index_for_position(position).ok()?;
-        self.links
-            .iter()
-            .find(|link| link.source_range.contains(&source_index))
+    #[test]
+    fn test_image_source() {
+        assert!(matches!(
+            image_source("file:///tmp/image.png"),
+            Some(ImageSource::File(path)) if path.as_path() == Path::new("/tmp/image.png")
+        ));
+        assert!(matches!(
+            image_source("https://zed.dev/logo.png"),
+            Some(ImageSource::Uri(uri)) if uri.as_ref() == "https://zed.dev/logo.png"
+        ));
+        assert!(matches!(
+            image_source("data:image/png;base64,iVBORw0KGgo="),
+            Some(ImageSource::Uri(_))
+        ));
+        assert!(image_source("images/logo.png").is_none());
     }
 }
@@ -1,13 +1,43 @@
 use gpui::SharedString;
 use linkify::LinkFinder;
+pub use pulldown_cmark::MetadataBlockKind;
 pub use pulldown_cmark::TagEnd as MarkdownTagEnd;
-use pulldown_cmark::{Alignment, HeadingLevel, LinkType, MetadataBlockKind, Options, Parser};
+use pulldown_cmark::{Alignment, BrokenLink, CowStr, HeadingLevel, LinkType, Options, Parser};
 use std::ops::Range;
 
-pub fn parse_markdown(text: &str) -> Vec<(Range<usize>, MarkdownEvent)> {
+/// Optional extensions to the markdown syntax. All of them are off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Straight quotes become curly quotes, `--` and `---` become en and em
+    /// dashes, and `...` becomes an ellipsis.
+    pub smart_punctuation: bool,
+    /// `$...$` and `$$...$$` spans become [`MarkdownEvent::InlineMath`] and
+    /// [`MarkdownEvent::DisplayMath`].
+    pub math: bool,
+}
+
+/// Parses `text` into events whose ranges point into it.
+pub fn parse_markdown(text: &str, options: ParseOptions) -> Vec<(Range<usize>, MarkdownEvent)> {
     let mut events = Vec::new();
     let mut within_link = false;
-    for (pulldown_event, mut range) in Parser::new_ext(text, Options::all()).into_offset_iter() {
+    // Undefined image references still render as images, so that their alt
+    // text is shown instead of the raw `![alt][ref]` syntax.
+    let mut resolve_broken_link = |link: BrokenLink| {
+        if text[link.span.clone()].starts_with('!') {
+            log::warn!("unresolved markdown image reference: {:?}", link.reference);
+            Some((CowStr::Borrowed(""), CowStr::Borrowed("")))
+        } else {
+            None
+        }
+    };
+    let mut pulldown_options = Options::all();
+    pulldown_options.set(Options::ENABLE_SMART_PUNCTUATION, options.smart_punctuation);
+    let parser = Parser::new_with_broken_link_callback(
+        text,
+        pulldown_options,
+        Some(&mut resolve_broken_link),
+    );
+    for (pulldown_event, mut range) in parser.into_offset_iter() {
         match pulldown_event {
             pulldown_cmark::Event::Start(tag) => {
                 if let pulldown_cmark::Tag::Link { .. } = tag {
@@ -21,7 +51,20 @@ pub fn parse_markdown(text: &str) -> Vec<(Range<usize>, MarkdownEvent)> {
                 }
                 events.push((range, MarkdownEvent::End(tag)));
             }
-            pulldown_cmark::Event::Text(_) => {
+            pulldown_cmark::Event::Text(decoded) => {
+                // HTML entities like `&amp;` and smart punctuation are emitted on their
+                // own, already substituted.
+                let source = &text[range.clone()];
+                let is_entity = source.starts_with('&') && source.ends_with(';');
+                let is_punctuation = source.chars().all(|c| matches!(c, '"' | '\'' | '-' | '.'));
+                if (is_entity || is_punctuation) && *decoded != *source {
+                    events.push((
+                        range,
+                        MarkdownEvent::SubstitutedText(decoded.to_string().into()),
+                    ));
+                    continue;
+                }
+
                 // Automatically detect links in text if we're not already within a markdown
                 // link.
                 if !within_link {
@@ -57,8 +100,24 @@ pub fn parse_markdown(text: &str) -> Vec<(Range<usize>, MarkdownEvent)> {
                 }
             }
             pulldown_cmark::Event::Code(_) => {
-                range.start += 1;
-                range.end -= 1;
+                // Exclude the delimiting backtick strings, which can be longer than a
+                // single backtick when the code itself contains backticks.
+                let source = &text[range.clone()];
+                let backtick_count = source.len() - source.trim_start_matches('`').len();
+                range.start += backtick_count;
+                range.end -= backtick_count;
+
+                // A single space (or line ending) padding both sides is stripped too.
+                let content = &text[range.clone()];
+                let is_padding = |c: char| c == ' ' || c == '\n';
+                if content.len() > 1
+                    && content.starts_with(is_padding)
+                    && content.ends_with(is_padding)
+                    && !content.chars().all(is_padding)
+                {
+                    range.start += 1;
+                    range.end -= 1;
+                }
                 events.push((range, MarkdownEvent::Code))
             }
             pulldown_cmark::Event::Html(_) => events.push((range, MarkdownEvent::Html)),
@@ -74,7 +133,422 @@ pub fn parse_markdown(text: &str) -> Vec<(Range<usize>, MarkdownEvent)> {
             }
         }
     }
-    events
+    if options.math {
+        extract_math(text, events)
+    } else {
+        events
+    }
+}
+
+/// Replaces `$...$` and `$$...$$` spans within runs of text with math events.
+/// Spans can't cross other inline elements, like emphasis or links.
+fn extract_math(
+    text: &str,
+    events: Vec<(Range<usize>, MarkdownEvent)>,
+) -> Vec<(Range<usize>, MarkdownEvent)> {
+    let is_text = |event: &MarkdownEvent| {
+        matches!(
+            event,
+            MarkdownEvent::Text | MarkdownEvent::SubstitutedText(_) | MarkdownEvent::SoftBreak
+        )
+    };
+
+    let mut result = Vec::with_capacity(events.len());
+    let mut verbatim_depth = 0;
+    let mut ix = 0;
+    while ix < events.len() {
+        match &events[ix].1 {
+            MarkdownEvent::Start(MarkdownTag::CodeBlock(_) | MarkdownTag::MetadataBlock(_)) => {
+                verbatim_depth += 1
+            }
+            MarkdownEvent::End(MarkdownTagEnd::CodeBlock | MarkdownTagEnd::MetadataBlock(_)) => {
+                verbatim_depth -= 1
+            }
+            event if verbatim_depth == 0 && is_text(event) => {
+                let run_len = events[ix..]
+                    .iter()
+                    .take_while(|(_, event)| is_text(event))
+                    .count();
+                let run = &events[ix..ix + run_len];
+                let run_start = run[0].0.start;
+                let spans = math_spans(&text[run_start..run[run_len - 1].0.end])
+                    .into_iter()
+                    .map(|(span, display)| (span.start + run_start..span.end + run_start, display))
+                    .collect::<Vec<_>>();
+                push_math_run(run, &spans, &mut result);
+                ix += run_len;
+                continue;
+            }
+            _ => {}
+        }
+        result.push(events[ix].clone());
+        ix += 1;
+    }
+    result
+}
+
+/// Pushes the events of a run of text, with math events in place of `spans`.
+fn push_math_run(
+    run: &[(Range<usize>, MarkdownEvent)],
+    spans: &[(Range<usize>, bool)],
+    result: &mut Vec<(Range<usize>, MarkdownEvent)>,
+) {
+    for (range, event) in run {
+        if let MarkdownEvent::Text = event {
+            let mut start = range.start;
+            for (span, display) in spans {
+                if span.end <= start || span.start >= range.end {
+                    continue;
+                }
+                if span.start > start {
+                    result.push((start..span.start, MarkdownEvent::Text));
+                }
+                // Spans start with a `$`, which is always part of a text event.
+                if span.start >= range.start {
+                    let (delimiter_len, event) = if *display {
+                        (2, MarkdownEvent::DisplayMath)
+                    } else {
+                        (1, MarkdownEvent::InlineMath)
+                    };
+                    result.push((span.start + delimiter_len..span.end - delimiter_len, event));
+                }
+                start = span.end;
+            }
+            if start < range.end {
+                result.push((start..range.end, MarkdownEvent::Text));
+            }
+        } else if !spans
+            .iter()
+            .any(|(span, _)| span.start <= range.start && range.end <= span.end)
+        {
+            result.push((range.clone(), event.clone()));
+        }
+    }
+}
+
+/// Finds the `$$...$$` (display) and `$...$` (inline) spans in `text`, including
+/// their delimiters. Like in Pandoc, an inline span can't start before or end
+/// after whitespace, and can't be followed by a digit, so prices like `$5` are
+/// left alone. Escaped dollar signs don't delimit spans.
+fn math_spans(text: &str) -> Vec<(Range<usize>, bool)> {
+    let bytes = text.as_bytes();
+    let find_closing = |mut ix: usize, display: bool| {
+        while ix < bytes.len() {
+            match bytes[ix] {
+                b'\\' => ix += 2,
+                b'$' if display => {
+                    if bytes.get(ix + 1) == Some(&b'$') {
+                        return Some(ix);
+                    }
+                    ix += 1;
+                }
+                b'$' => {
+                    if !bytes[ix - 1].is_ascii_whitespace()
+                        && !bytes.get(ix + 1).map_or(false, u8::is_ascii_digit)
+                    {
+                        return Some(ix);
+                    }
+                    ix += 1;
+                }
+                _ => ix += 1,
+            }
+        }
+        None
+    };
+
+    let mut spans = Vec::new();
+    let mut ix = 0;
+    while ix < bytes.len() {
+        match bytes[ix] {
+            b'\\' => ix += 2,
+            b'$' if bytes.get(ix + 1) == Some(&b'$') => {
+                match find_closing(ix + 2, true).filter(|&end| end > ix + 2) {
+                    Some(end) => {
+                        spans.push((ix..end + 2, true));
+                        ix = end + 2;
+                    }
+                    None => ix += 2,
+                }
+            }
+            b'$' if bytes
+                .get(ix + 1)
+                .map_or(false, |next| !next.is_ascii_whitespace()) =>
+            {
+                match find_closing(ix + 1, false) {
+                    Some(end) => {
+                        spans.push((ix..end + 1, false));
+                        ix = end + 1;
+                    }
+                    None => ix += 1,
+                }
+            }
+            _ => ix += 1,
+        }
+    }
+    spans
+}
+
+/// Parses `text`, whose prefix previously parsed to `old_events`, by reparsing
+/// only its last two top-level blocks. The appended text may extend the last
+/// block, or turn its first line into a continuation of the block before it,
+/// such as a table row.
+///
+/// Returns `None` when the appended text could change how earlier blocks parse,
+/// and the whole text has to be parsed again instead.
+pub fn parse_appended_markdown(
+    text: &str,
+    old_events: &[(Range<usize>, MarkdownEvent)],
+    options: ParseOptions,
+) -> Option<Vec<(Range<usize>, MarkdownEvent)>> {
+    // Link reference and footnote definitions apply to the whole document.
+    if text.contains("]:") {
+        return None;
+    }
+
+    let mut block_ixs = Vec::new();
+    let mut depth = 0;
+    for (ix, (_, event)) in old_events.iter().enumerate() {
+        match event {
+            MarkdownEvent::Start(_) => {
+                if depth == 0 {
+                    block_ixs.push(ix);
+                }
+                depth += 1;
+            }
+            MarkdownEvent::End(_) => depth -= 1,
+            _ => {
+                if depth == 0 {
+                    block_ixs.push(ix);
+                }
+            }
+        }
+    }
+
+    let first_reparsed_ix = *block_ixs.iter().nth_back(1)?;
+    let block_start = old_events[first_reparsed_ix].0.start;
+    let reparse_start = text[..block_start].rfind('\n')? + 1;
+    if old_events[..first_reparsed_ix]
+        .last()
+        .map_or(false, |(range, _)| range.end > reparse_start)
+    {
+        return None;
+    }
+
+    // Front matter is only recognized at the start of the document, where a
+    // thematic break turns into front matter once a closing delimiter follows.
+    let suffix = &text[reparse_start..];
+    if suffix.starts_with("---") || suffix.starts_with("+++") {
+        return None;
+    }
+    if (text.starts_with("---") || text.starts_with("+++"))
+        && suffix.lines().any(|line| {
+            ["---", "+++", "..."]
+                .iter()
+                .any(|delimiter| line.starts_with(delimiter))
+        })
+    {
+        return None;
+    }
+
+    let mut events = old_events[..first_reparsed_ix].to_vec();
+    events.extend(
+        parse_markdown(suffix, options)
+            .into_iter()
+            .map(|(range, event)| {
+                (
+                    range.start + reparse_start..range.end + reparse_start,
+                    event,
+                )
+            }),
+    );
+    Some(events)
+}
+
+/// Parses `new_text`, an edit of `old_text` which previously parsed to
+/// `old_events`, by reparsing only the top-level blocks touched by the edit and
+/// the blocks on either side of them, which the edit could merge with. The
+/// events of the other blocks are kept, shifted by the change in length.
+///
+/// Returns `None` when the edit could change how blocks further away parse,
+/// and the whole text has to be parsed again instead.
+pub fn parse_edited_markdown(
+    old_text: &str,
+    new_text: &str,
+    old_events: &[(Range<usize>, MarkdownEvent)],
+    options: ParseOptions,
+) -> Option<Vec<(Range<usize>, MarkdownEvent)>> {
+    // Link reference and footnote definitions apply to the whole document.
+    if old_text.contains("]:") || new_text.contains("]:") {
+        return None;
+    }
+
+    let mut prefix_len = old_text
+        .bytes()
+        .zip(new_text.bytes())
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !new_text.is_char_boundary(prefix_len) {
+        prefix_len -= 1;
+    }
+    let max_suffix_len = old_text.len().min(new_text.len()) - prefix_len;
+    let mut suffix_len = old_text
+        .bytes()
+        .rev()
+        .zip(new_text.bytes().rev())
+        .take(max_suffix_len)
+        .take_while(|(old, new)| old == new)
+        .count();
+    while !new_text.is_char_boundary(new_text.len() - suffix_len) {
+        suffix_len -= 1;
+    }
+
+    // Fences, HTML blocks, and display math can extend to any later line, so
+    // the whole lines touched by the edit are checked for them.
+    let line_start = new_text[..prefix_len].rfind('\n').map_or(0, |ix| ix + 1);
+    let touched_lines = |text: &str| {
+        let edit_end = text.len() - suffix_len;
+        let line_end = text[edit_end..]
+            .find('\n')
+            .map_or(text.len(), |ix| edit_end + ix);
+        line_start..line_end
+    };
+    let old_lines = touched_lines(old_text);
+    let new_lines = touched_lines(new_text);
+    if [&old_text[old_lines.clone()], &new_text[new_lines]]
+        .iter()
+        .any(|lines| {
+            lines.contains("```")
+                || lines.contains("~~~")
+                || lines.contains("$$")
+                || lines.contains('<')
+        })
+    {
+        return None;
+    }
+
+    // The ranges of the top-level blocks, and the indices of their first and
+    // last events.
+    let mut blocks: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let mut depth = 0;
+    for (ix, (range, event)) in old_events.iter().enumerate() {
+        // Task list markers can be emitted after their list has ended, in the
+        // blocks that follow it.
+        if blocks
+            .last()
+            .map_or(false, |(block, _)| range.start < block.start)
+        {
+            return None;
+        }
+        if depth == 0 && !matches!(event, MarkdownEvent::End(_)) {
+            blocks.push((range.clone(), ix..ix));
+        }
+        match event {
+            MarkdownEvent::Start(_) => depth += 1,
+            MarkdownEvent::End(_) => {
+                depth -= 1;
+                if depth == 0 {
+                    blocks.last_mut()?.1.end = ix;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let first_touched = blocks.partition_point(|(range, _)| range.end < old_lines.start);
+    let last_touched = blocks
+        .partition_point(|(range, _)| range.start <= old_lines.end)
+        .checked_sub(1)
+        .map_or(first_touched, |ix| ix.max(first_touched));
+    let first_block = first_touched.saturating_sub(1);
+    let last_block = (last_touched + 1).min(blocks.len().checked_sub(1)?);
+
+    let reparse_start = if first_block == 0 {
+        0
+    } else {
+        old_text[..blocks[first_block].0.start].rfind('\n')? + 1
+    };
+    // Trailing blank lines can belong to the last block, so it's reparsed to the end.
+    let old_reparse_end = if last_block + 1 == blocks.len() {
+        old_text.len()
+    } else {
+        blocks[last_block].0.end.max(old_lines.end)
+    };
+    if reparse_start > prefix_len || old_reparse_end < old_text.len() - suffix_len {
+        return None;
+    }
+    let mut new_reparse_end = old_reparse_end + new_text.len() - old_text.len();
+    // The last reparsed line includes its line ending, as the block would.
+    if !new_text[..new_reparse_end].ends_with('\n') {
+        if let Some(ix) = new_text[new_reparse_end..].find('\n') {
+            new_reparse_end += ix + 1;
+        }
+    }
+
+    // The reparsed text has to be separated from the blocks around it by blank
+    // lines, so that it can't continue them or be continued by them.
+    let preceding = &new_text[..reparse_start];
+    let through_reparsed = &new_text[..new_reparse_end];
+    let following = &new_text[new_reparse_end..];
+    let ends_with_blank_line = |text: &str| text.ends_with("\n\n") || text.ends_with("\n\r\n");
+    if !(preceding.is_empty() || ends_with_blank_line(preceding))
+        || !(following.is_empty()
+            || following.starts_with('\n')
+            || following.starts_with("\r\n")
+            || ends_with_blank_line(through_reparsed))
+    {
+        return None;
+    }
+    if old_events[..blocks[first_block].1.start]
+        .last()
+        .map_or(false, |(range, _)| range.end > reparse_start)
+    {
+        return None;
+    }
+
+    // Whether a `---` or `+++` line opens a metadata block depends on the
+    // lines before it.
+    let reparsed_text = &new_text[reparse_start..new_reparse_end];
+    if reparsed_text
+        .lines()
+        .any(|line| line.starts_with("---") || line.starts_with("+++"))
+    {
+        return None;
+    }
+
+    let shift = |(range, event): &(Range<usize>, MarkdownEvent)| {
+        (
+            range.start + new_text.len() - old_text.len()
+                ..range.end + new_text.len() - old_text.len(),
+            event.clone(),
+        )
+    };
+    let mut events = old_events[..blocks[first_block].1.start].to_vec();
+    events.extend(
+        parse_markdown(reparsed_text, options)
+            .into_iter()
+            .map(|(range, event)| {
+                (
+                    range.start + reparse_start..range.end + reparse_start,
+                    event,
+                )
+            }),
+    );
+
+    // The edit can make a block absorb the ones after it, e.g. indented blocks
+    // following a new list item. If the block after the edit still parses the
+    // same on its own, the blocks after it do too.
+    if last_block > last_touched {
+        let next_block = old_events[blocks[last_block].1.start..=blocks[last_block].1.end]
+            .iter()
+            .map(shift)
+            .collect::<Vec<_>>();
+        if !events.ends_with(&next_block) {
+            return None;
+        }
+    }
+
+    events.extend(old_events[blocks[last_block].1.end + 1..].iter().map(shift));
+    Some(events)
 }
 
 /// A static-lifetime equivalent of pulldown_cmark::Event so we can cache the
@@ -89,8 +563,16 @@ pub enum MarkdownEvent {
     End(MarkdownTagEnd),
     /// A text node.
     Text,
+    /// A text node that's rendered as the given text instead of its source, like
+    /// a decoded HTML entity.
+    SubstitutedText(SharedString),
     /// An inline code node.
     Code,
+    /// A `$`-delimited TeX formula. The range excludes the delimiters.
+    InlineMath,
+    /// A `$$`-delimited TeX formula, displayed on its own. The range excludes
+    /// the delimiters.
+    DisplayMath,
     /// An HTML node.
     Html,
     /// An inline HTML node.
@@ -188,8 +670,13 @@ pub enum MarkdownTag {
 #[derive(Clone, Debug, PartialEq)]
 pub enum CodeBlockKind {
     Indented,
-    /// The value contained in the tag describes the language of the code, which may be empty.
-    Fenced(SharedString),
+    /// The info string of the fence, split into the language of the code and the
+    /// metadata following it, e.g. `rust` and `ignore` for ```` ```rust,ignore ````
+    /// or `js` and `{1,3-4}` for ```` ```js {1,3-4} ````. Either may be empty.
+    Fenced {
+        language: SharedString,
+        metadata: SharedString,
+    },
 }
 
 impl From<pulldown_cmark::Tag<'_>> for MarkdownTag {
@@ -228,9 +715,17 @@ impl From<pulldown_cmark::Tag<'_>> for MarkdownTag {
                 pulldown_cmark::CodeBlockKind::Indented => {
                     MarkdownTag::CodeBlock(CodeBlockKind::Indented)
                 }
-                pulldown_cmark::CodeBlockKind::Fenced(info) => MarkdownTag::CodeBlock(
-                    CodeBlockKind::Fenced(SharedString::from(info.into_string())),
-                ),
+                pulldown_cmark::CodeBlockKind::Fenced(info) => {
+                    let info = info.trim();
+                    let is_separator = |ch: char| ch == ',' || ch.is_whitespace();
+                    let (language, metadata) = info.split_once(is_separator).unwrap_or((info, ""));
+                    MarkdownTag::CodeBlock(CodeBlockKind::Fenced {
+                        language: SharedString::from(language.to_string()),
+                        metadata: SharedString::from(
+                            metadata.trim_start_matches(is_separator).to_string(),
+                        ),
+                    })
+                }
             },
             pulldown_cmark::Tag::List(start_number) => MarkdownTag::List(start_number),
             pulldown_cmark::Tag::Item => MarkdownTag::Item,
@@ -271,3 +766,374 @@ impl From<pulldown_cmark::Tag<'_>> for MarkdownTag {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rendered_text(source: &str) -> String {
+        parse_markdown(source, ParseOptions::default())
+            .into_iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::Text => Some(&source[range]),
+                MarkdownEvent::SoftBreak | MarkdownEvent::HardBreak => Some("\n"),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn assert_no_html(source: &str) {
+        for (range, event) in parse_markdown(source, ParseOptions::default()) {
+            assert!(
+                !matches!(event, MarkdownEvent::Html | MarkdownEvent::InlineHtml),
+                "unexpected HTML event for {:?} in {:?}",
+                &source[range],
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_bare_less_than_is_text() {
+        let source = "a < b";
+        assert_no_html(source);
+        assert_eq!(rendered_text(source), "a < b");
+
+        let source = "x <= y";
+        assert_no_html(source);
+        assert_eq!(rendered_text(source), "x <= y");
+
+        let source = "a stray <\nfollowed by text";
+        assert_no_html(source);
+        assert_eq!(rendered_text(source), "a stray <\nfollowed by text");
+    }
+
+    fn code_spans(source: &str) -> Vec<&str> {
+        parse_markdown(source, ParseOptions::default())
+            .into_iter()
+            .filter_map(|(range, event)| (event == MarkdownEvent::Code).then(|| &source[range]))
+            .collect()
+    }
+
+    #[test]
+    fn test_code_span_ranges() {
+        assert_eq!(code_spans("a `b` c"), vec!["b"]);
+        assert_eq!(code_spans("a `` b`c `` d"), vec!["b`c"]);
+        assert_eq!(code_spans("a ` `` ` b"), vec!["``"]);
+        assert_eq!(code_spans("a `multi\nline` code"), vec!["multi\nline"]);
+    }
+
+    #[test]
+    fn test_code_block_info_string() {
+        let source = "```rust,ignore\na\n```\n\n```js {1,3-4}\nb\n```\n\n```\nc\n```\n";
+        let code_blocks = parse_markdown(source, ParseOptions::default())
+            .into_iter()
+            .filter_map(|(_, event)| match event {
+                MarkdownEvent::Start(MarkdownTag::CodeBlock(CodeBlockKind::Fenced {
+                    language,
+                    metadata,
+                })) => Some((language.to_string(), metadata.to_string())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            code_blocks,
+            [
+                ("rust".to_string(), "ignore".to_string()),
+                ("js".to_string(), "{1,3-4}".to_string()),
+                (String::new(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_front_matter_is_a_metadata_block() {
+        let source = "---\ntitle: Example\n---\n# Heading\n";
+        let events = parse_markdown(source, ParseOptions::default());
+        assert_eq!(
+            events[0],
+            (
+                0..22,
+                MarkdownEvent::Start(MarkdownTag::MetadataBlock(MetadataBlockKind::YamlStyle))
+            )
+        );
+        let heading_start = events
+            .iter()
+            .position(|(_, event)| {
+                matches!(event, MarkdownEvent::Start(MarkdownTag::Heading { .. }))
+            })
+            .unwrap();
+        assert_eq!(
+            events[heading_start - 1],
+            (
+                0..22,
+                MarkdownEvent::End(MarkdownTagEnd::MetadataBlock(MetadataBlockKind::YamlStyle))
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_appended_markdown() {
+        let document = [
+            "# Streaming\n\n",
+            "Some *emphasized* and **strong** text with `code` and https://zed.dev.\n",
+            "A second line of the same paragraph.\n\n",
+            "- first item\n- second item\n  continued\n\n  1. nested\n\n",
+            "Heading\n---\n\n",
+            "```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\n",
+            "> quoted\nlazily continued\n\n",
+            "| a | b |\n|---|:-:|\n| 1 | 2 |\n\n",
+            "    indented code\n\n",
+            "***\n\n",
+            "<div>\nhtml\n</div>\n\n",
+            "- [ ] task\n- [x] done\n\n",
+            "```\nunclosed fence",
+        ]
+        .concat()
+        .repeat(4);
+        let chunk_count = 1000;
+        let chunk_len = document.len().div_ceil(chunk_count);
+
+        let mut text = String::new();
+        let mut events = Vec::new();
+        let mut incremental_parses = 0;
+        for chunk in document.as_bytes().chunks(chunk_len) {
+            text.push_str(std::str::from_utf8(chunk).unwrap());
+            events = match parse_appended_markdown(&text, &events, ParseOptions::default()) {
+                Some(events) => {
+                    incremental_parses += 1;
+                    events
+                }
+                None => parse_markdown(&text, ParseOptions::default()),
+            };
+        }
+
+        assert_eq!(text, document);
+        assert_eq!(events, parse_markdown(&document, ParseOptions::default()));
+        assert!(incremental_parses > chunk_count / 2);
+
+        // Definitions can affect earlier blocks.
+        let text = "[link][ref]\n\n[ref]: https://zed.dev\n";
+        assert_eq!(
+            parse_appended_markdown(
+                text,
+                &parse_markdown(&text[..13], ParseOptions::default()),
+                ParseOptions::default()
+            ),
+            None
+        );
+
+        // Closing delimiters turn a leading thematic break into front matter.
+        for (old_text, appended) in [("---\ne\n1. e", "\n---"), ("---\nt\n+ |\n", "---")] {
+            let text = format!("{old_text}{appended}");
+            let old_events = parse_markdown(old_text, ParseOptions::default());
+            let events = parse_appended_markdown(&text, &old_events, ParseOptions::default())
+                .unwrap_or_else(|| parse_markdown(&text, ParseOptions::default()));
+            assert_eq!(events, parse_markdown(&text, ParseOptions::default()));
+        }
+    }
+
+    #[test]
+    fn test_parse_edited_markdown() {
+        let old_text = "# Title\n\nFirst paragraph.\n\nThe quick brown fox\njumps over the dog.\n\n- a\n- b\n\nLast paragraph.\n";
+        let new_text = old_text.replace("brown", "red");
+        let old_events = parse_markdown(old_text, ParseOptions::default());
+        let edited =
+            parse_edited_markdown(old_text, &new_text, &old_events, ParseOptions::default())
+                .unwrap();
+        assert_eq!(edited, parse_markdown(&new_text, ParseOptions::default()));
+
+        // Turning a paragraph into a list item changes how the indented
+        // paragraph after it parses.
+        let old_text = "Intro.\n\nitem\n\n    more\n\nOutro.\n";
+        let new_text = old_text.replace("item", "- item");
+        let old_events = parse_markdown(old_text, ParseOptions::default());
+        let edited =
+            parse_edited_markdown(old_text, &new_text, &old_events, ParseOptions::default())
+                .unwrap_or_else(|| parse_markdown(&new_text, ParseOptions::default()));
+        assert_eq!(edited, parse_markdown(&new_text, ParseOptions::default()));
+
+        // Definitions can affect any block.
+        let old_text = "[link][ref]\n\n[ref]: https://zed.dev\n";
+        let new_text = old_text.replace("zed.dev", "zed.dev/docs");
+        assert_eq!(
+            parse_edited_markdown(
+                old_text,
+                &new_text,
+                &parse_markdown(old_text, ParseOptions::default()),
+                ParseOptions::default()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_html_entities() {
+        let source = "a &amp; b &#39;c&#x2014; &mdash; &bogus; &amp";
+        let substitutions = parse_markdown(source, ParseOptions::default())
+            .into_iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::SubstitutedText(text) => Some((range, text.to_string())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            substitutions,
+            [
+                (2..7, "&".to_string()),
+                (10..15, "'".to_string()),
+                (16..24, "\u{2014}".to_string()),
+                (25..32, "\u{2014}".to_string()),
+            ]
+        );
+        // Invalid entities are left untouched.
+        assert!(rendered_text(source).contains("&bogus; &amp"));
+    }
+
+    #[test]
+    fn test_math() {
+        let source = "Euler: $e^{i\\pi} + 1 = 0$, or\n\n$$\n\\int_0^1 x\\,dx\n$$\n\nCosts $5 or $10. Escaped \\$x\\$ and `$y$`.\n\n```\n$z$\n```\n";
+        let options = ParseOptions {
+            math: true,
+            ..Default::default()
+        };
+        let events = parse_markdown(source, options);
+        let math = events
+            .iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::InlineMath => Some((&source[range.clone()], false)),
+                MarkdownEvent::DisplayMath => Some((&source[range.clone()], true)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            math,
+            [("e^{i\\pi} + 1 = 0", false), ("\n\\int_0^1 x\\,dx\n", true)]
+        );
+        let text = events
+            .iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::Text => Some(&source[range.clone()]),
+                _ => None,
+            })
+            .collect::<String>();
+        assert_eq!(text, "Euler: , orCosts $5 or $10. Escaped $x$ and .$z$\n");
+
+        // Without the option, dollar signs are just text.
+        assert!(rendered_text(source).starts_with("Euler: $e^{i\\pi} + 1 = 0$, or"));
+    }
+
+    #[test]
+    fn test_smart_punctuation() {
+        fn substitutions(source: &str, smart_punctuation: bool) -> Vec<(Range<usize>, String)> {
+            parse_markdown(
+                source,
+                ParseOptions {
+                    smart_punctuation,
+                    ..Default::default()
+                },
+            )
+            .into_iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::SubstitutedText(text) => Some((range, text.to_string())),
+                _ => None,
+            })
+            .collect()
+        }
+
+        let source = "\"hello\" --- world... it's -- ok";
+        assert_eq!(
+            substitutions(source, true),
+            [
+                (0..1, "\u{201c}".to_string()),
+                (6..7, "\u{201d}".to_string()),
+                (8..11, "\u{2014}".to_string()),
+                (17..20, "\u{2026}".to_string()),
+                (23..24, "\u{2019}".to_string()),
+                (26..28, "\u{2013}".to_string()),
+            ]
+        );
+        assert_eq!(substitutions(source, false), []);
+    }
+
+    #[test]
+    fn test_list_start_after_interruption() {
+        fn list_starts(source: &str) -> Vec<Option<u64>> {
+            parse_markdown(source, ParseOptions::default())
+                .into_iter()
+                .filter_map(|(_, event)| match event {
+                    MarkdownEvent::Start(MarkdownTag::List(start)) => Some(start),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        // A list interrupted by a paragraph continues from its own start number.
+        assert_eq!(
+            list_starts("1. a\n\n paragraph\n\n2. b\n"),
+            vec![Some(1), Some(2)]
+        );
+        // A genuinely new list restarts numbering.
+        assert_eq!(
+            list_starts("1. a\n\nparagraph\n\n1. b\n"),
+            vec![Some(1), Some(1)]
+        );
+        // Items separated by blank lines stay in the same list.
+        assert_eq!(list_starts("1. a\n\n2. b\n"), vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_image_references() {
+        let events = parse_markdown(
+            "![logo][defined] ![missing][undefined]\n\n[defined]: /logo.png\n",
+            ParseOptions::default(),
+        );
+        let images = events
+            .iter()
+            .filter_map(|(range, event)| match event {
+                MarkdownEvent::Start(MarkdownTag::Image { dest_url, .. }) => {
+                    Some((range.clone(), dest_url.to_string()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            images,
+            vec![(0..16, "/logo.png".to_string()), (17..38, String::new())]
+        );
+        assert!(events.contains(&(19..26, MarkdownEvent::Text)));
+    }
+
+    #[test]
+    fn test_autolink_trailing_punctuation() {
+        fn autolinks(source: &str) -> Vec<(&str, String)> {
+            parse_markdown(source, ParseOptions::default())
+                .into_iter()
+                .filter_map(|(range, event)| match event {
+                    MarkdownEvent::Start(MarkdownTag::Link { dest_url, .. }) => {
+                        Some((&source[range], dest_url.to_string()))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let url = "https://example.com";
+        assert_eq!(
+            autolinks("See https://example.com."),
+            vec![(url, url.into())]
+        );
+        assert_eq!(
+            autolinks("See (https://example.com), then"),
+            vec![(url, url.into())]
+        );
+        assert_eq!(
+            autolinks("Wow https://example.com!"),
+            vec![(url, url.into())]
+        );
+        assert_eq!(
+            rendered_text("See https://example.com."),
+            "See https://example.com."
+        );
+    }
+}
@@ -130,6 +130,7 @@ impl DevServerProjects {
             block_quote_border_color: Default::default(),
             syntax: cx.theme().syntax().clone(),
             selection_background_color: cx.theme().players().local().selection,
+            ..Default::default()
         };
         let markdown = cx.new_view(|cx| Markdown::new("".to_string(), markdown_style, None, cx));
 
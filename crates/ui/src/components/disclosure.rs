@@ -1,14 +1,23 @@
-use std::sync::Arc;
+use std::{rc::Rc, sync::Arc, time::Duration};
 
-use gpui::ClickEvent;
+use gpui::{
+    ease_in_out, percentage, transparent_black, Animation, AnimationExt, AnyElement, Bounds,
+    ClickEvent, Element, FocusHandle, GlobalElementId, LayoutId, Pixels, Transformation,
+};
+use smallvec::SmallVec;
 
-use crate::{prelude::*, Color, IconButton, IconName, IconSize};
+use crate::{prelude::*, ButtonLike, Color, IconButton, IconName, IconSize};
 
 #[derive(IntoElement)]
 pub struct Disclosure {
     id: ElementId,
     is_open: bool,
+    animated: bool,
+    label: Option<SharedString>,
+    children: SmallVec<[AnyElement; 2]>,
     on_toggle: Option<Arc<dyn Fn(&ClickEvent, &mut WindowContext) + 'static>>,
+    on_first_open: Option<Rc<dyn Fn(&mut WindowContext) + 'static>>,
+    focus_handle: Option<FocusHandle>,
 }
 
 impl Disclosure {
@@ -16,7 +25,12 @@ impl Disclosure {
         Self {
             id: id.into(),
             is_open,
+            animated: false,
+            label: None,
+            children: SmallVec::new(),
             on_toggle: None,
+            on_first_open: None,
+            focus_handle: None,
         }
     }
 
@@ -27,10 +41,129 @@ impl Disclosure {
         self.on_toggle = handler.into();
         self
     }
-}
 
-impl RenderOnce for Disclosure {
-    fn render(self, _cx: &mut WindowContext) -> impl IntoElement {
+    /// Invokes the handler once, the first time the disclosure is rendered open
+    /// after being rendered closed, e.g. to start loading its children. It's never
+    /// invoked for a disclosure that starts out open.
+    pub fn on_first_open(mut self, handler: impl Fn(&mut WindowContext) + 'static) -> Self {
+        self.on_first_open = Some(Rc::new(handler));
+        self
+    }
+
+    /// Makes the disclosure focusable with the given handle. While focused, it
+    /// shows a focus ring and Enter or Space toggles it.
+    pub fn track_focus(mut self, focus_handle: &FocusHandle) -> Self {
+        self.focus_handle = Some(focus_handle.clone());
+        self
+    }
+
+    /// Rotates a single chevron between the closed and open states, rather than
+    /// swapping icons. Has no effect while the window reduces motion.
+    pub fn animated(mut self, enabled: bool) -> Self {
+        self.animated = enabled;
+        self
+    }
+
+    /// Renders the label next to the chevron. The whole header row toggles the
+    /// disclosure.
+    pub fn label(mut self, label: impl Into<SharedString>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    fn render_chevron(&self) -> AnyElement {
+        if !self.animated {
+            return Icon::new(match self.is_open {
+                true => IconName::ChevronDown,
+                false => IconName::ChevronRight,
+            })
+            .color(Color::Muted)
+            .size(IconSize::Small)
+            .into_any_element();
+        }
+
+        let is_open = self.is_open;
+        // The animation is nested within the disclosure's id, so each disclosure
+        // animates independently. Its own id changes with the state, which
+        // restarts the animation on every toggle.
+        Icon::new(IconName::ChevronRight)
+            .color(Color::Muted)
+            .size(IconSize::Small)
+            .with_animation(
+                if is_open {
+                    "disclosure-open"
+                } else {
+                    "disclosure-closed"
+                },
+                Animation::new(Duration::from_millis(150)).with_easing(ease_in_out),
+                move |icon, delta| {
+                    let progress = if is_open { delta } else { 1. - delta };
+                    icon.transform(Transformation::rotate(percentage(progress / 4.)))
+                },
+            )
+            .into_any_element()
+    }
+
+    fn render_disclosure(mut self, cx: &mut WindowContext) -> AnyElement {
+        if cx.should_reduce_motion() {
+            self.animated = false;
+        }
+        if let Some(focus_handle) = self.focus_handle.take() {
+            let on_toggle = self.on_toggle.clone();
+            return div()
+                .track_focus(&focus_handle)
+                .rounded_md()
+                .border_1()
+                .border_color(transparent_black())
+                .focus(|style| style.border_color(cx.theme().colors().border_focused))
+                .when_some(on_toggle, |this, on_toggle| {
+                    this.on_key_down(move |event, cx| {
+                        let keystroke = &event.keystroke;
+                        if matches!(keystroke.key.as_str(), "enter" | "space")
+                            && !keystroke.modifiers.modified()
+                        {
+                            cx.stop_propagation();
+                            on_toggle(&ClickEvent::default(), cx);
+                        }
+                    })
+                })
+                .child(self.render_disclosure(cx))
+                .into_any_element();
+        }
+
+        if self.label.is_some() || !self.children.is_empty() {
+            let chevron = self.render_chevron();
+            return v_flex()
+                .child(
+                    h_flex()
+                        .id(self.id)
+                        .gap_1()
+                        .cursor_pointer()
+                        .child(chevron)
+                        .when_some(self.label, |this, label| this.child(Label::new(label)))
+                        .when_some(self.on_toggle, move |this, on_toggle| {
+                            this.on_click(move |event, cx| on_toggle(event, cx))
+                        }),
+                )
+                .when(self.is_open, |this| this.children(self.children))
+                .into_any_element();
+        }
+
+        if self.animated {
+            let chevron = self.render_chevron();
+            let mut button = ButtonLike::new(self.id)
+                .child(chevron)
+                .when_some(self.on_toggle, move |this, on_toggle| {
+                    this.on_click(move |event, cx| on_toggle(event, cx))
+                });
+            // Like an `IconButton`, named after the icon it shows, which is always
+            // the right chevron, rotated.
+            button.base = button
+                .base
+                .debug_selector(|| format!("ICON-{:?}", IconName::ChevronRight));
+            return button.into_any_element();
+        }
+
         IconButton::new(
             self.id,
             match self.is_open {
@@ -43,5 +176,191 @@ impl RenderOnce for Disclosure {
         .when_some(self.on_toggle, move |this, on_toggle| {
             this.on_click(move |event, cx| on_toggle(event, cx))
         })
+        .into_any_element()
+    }
+}
+
+impl ParentElement for Disclosure {
+    fn extend(&mut self, elements: impl IntoIterator<Item = AnyElement>) {
+        self.children.extend(elements)
+    }
+}
+
+impl RenderOnce for Disclosure {
+    fn render(mut self, cx: &mut WindowContext) -> impl IntoElement {
+        match self.on_first_open.take() {
+            Some(on_first_open) => FirstOpenTracker {
+                id: self.id.clone(),
+                is_open: self.is_open,
+                on_first_open,
+                child: self.render_disclosure(cx),
+            }
+            .into_any_element(),
+            None => self.render_disclosure(cx),
+        }
+    }
+}
+
+/// Wraps a disclosure to invoke its `on_first_open` handler, remembering whether
+/// it has been open in the element state keyed by the disclosure's id.
+struct FirstOpenTracker {
+    id: ElementId,
+    is_open: bool,
+    on_first_open: Rc<dyn Fn(&mut WindowContext) + 'static>,
+    child: AnyElement,
+}
+
+impl IntoElement for FirstOpenTracker {
+    type Element = Self;
+
+    fn into_element(self) -> Self::Element {
+        self
+    }
+}
+
+impl Element for FirstOpenTracker {
+    type RequestLayoutState = ();
+    type PrepaintState = ();
+
+    fn id(&self) -> Option<ElementId> {
+        Some(self.id.clone())
+    }
+
+    fn request_layout(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        cx: &mut WindowContext,
+    ) -> (LayoutId, Self::RequestLayoutState) {
+        (self.child.request_layout(cx), ())
+    }
+
+    fn prepaint(
+        &mut self,
+        id: Option<&GlobalElementId>,
+        _bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        cx: &mut WindowContext,
+    ) {
+        let is_open = self.is_open;
+        let is_first_open = cx.with_element_state(id.unwrap(), |has_been_open, _| {
+            match has_been_open {
+                Some(has_been_open) => (is_open && !has_been_open, has_been_open || is_open),
+                // A disclosure that starts out open was never opened.
+                None => (false, is_open),
+            }
+        });
+        if is_first_open {
+            let on_first_open = self.on_first_open.clone();
+            // Handlers may update views, which can't happen in the middle of a frame.
+            cx.defer(move |cx| on_first_open(cx));
+        }
+        self.child.prepaint(cx);
+    }
+
+    fn paint(
+        &mut self,
+        _id: Option<&GlobalElementId>,
+        _bounds: Bounds<Pixels>,
+        _request_layout: &mut Self::RequestLayoutState,
+        _prepaint: &mut Self::PrepaintState,
+        cx: &mut WindowContext,
+    ) {
+        self.child.paint(cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use gpui::{TestAppContext, VisualTestContext};
+
+    use super::*;
+
+    struct DisclosureView {
+        focus_handle: FocusHandle,
+        toggle_count: Rc<Cell<usize>>,
+    }
+
+    impl Render for DisclosureView {
+        fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+            let toggle_count = self.toggle_count.clone();
+            let on_toggle: Arc<dyn Fn(&ClickEvent, &mut WindowContext)> =
+                Arc::new(move |_, _| toggle_count.set(toggle_count.get() + 1));
+            Disclosure::new("disclosure", false)
+                .track_focus(&self.focus_handle)
+                .on_toggle(on_toggle)
+        }
+    }
+
+    #[gpui::test]
+    fn test_keyboard_toggle(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+        });
+        let toggle_count = Rc::new(Cell::new(0));
+        let (view, cx) = cx.add_window_view(|cx| DisclosureView {
+            focus_handle: cx.focus_handle(),
+            toggle_count: toggle_count.clone(),
+        });
+
+        cx.simulate_keystrokes("space");
+        assert_eq!(toggle_count.get(), 0);
+
+        cx.update(|cx| view.read(cx).focus_handle.clone().focus(cx));
+        cx.simulate_keystrokes("space");
+        assert_eq!(toggle_count.get(), 1);
+        cx.simulate_keystrokes("enter");
+        assert_eq!(toggle_count.get(), 2);
+        cx.simulate_keystrokes("a");
+        assert_eq!(toggle_count.get(), 2);
+    }
+
+    struct AnimatedDisclosureView {
+        is_open: bool,
+    }
+
+    impl Render for AnimatedDisclosureView {
+        fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+            Disclosure::new("disclosure", self.is_open).animated(true)
+        }
+    }
+
+    #[gpui::test]
+    fn test_reduced_motion(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let store = settings::SettingsStore::test(cx);
+            cx.set_global(store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            cx.set_reduced_motion(Some(true));
+        });
+        let (view, cx) = cx.add_window_view(|_| AnimatedDisclosureView { is_open: false });
+        let toggle = |cx: &mut VisualTestContext| {
+            view.update(cx, |view, cx| {
+                view.is_open = !view.is_open;
+                cx.notify();
+            });
+            cx.run_until_parked();
+        };
+
+        // The chevron is swapped for one pointing down as soon as the disclosure opens.
+        toggle(cx);
+        assert!(cx.debug_bounds("ICON-ChevronDown").is_some());
+        assert!(cx.debug_bounds("ICON-ChevronRight").is_none());
+        assert!(!cx.has_pending_frame_callbacks());
+
+        // Otherwise, the right chevron is kept and rotates back.
+        cx.update(|cx| cx.set_reduced_motion(Some(false)));
+        toggle(cx);
+        assert!(cx.debug_bounds("ICON-ChevronRight").is_some());
+        assert!(cx.debug_bounds("ICON-ChevronDown").is_none());
+        assert!(cx.has_pending_frame_callbacks());
+
+        // It's rotated to point down when the disclosure opens.
+        toggle(cx);
+        assert!(cx.debug_bounds("ICON-ChevronRight").is_some());
+        assert!(cx.debug_bounds("ICON-ChevronDown").is_none());
     }
 }
@@ -14,5 +14,13 @@ impl Render for DisclosureStory {
             .child(Disclosure::new("toggled", true))
             .child(Story::label("Not Toggled"))
             .child(Disclosure::new("not_toggled", false))
+            .child(Story::label("Animated"))
+            .child(Disclosure::new("animated", true).animated(true))
+            .child(Story::label("With Label and Content"))
+            .child(
+                Disclosure::new("with_label", true)
+                    .label("Details")
+                    .child(Label::new("Shown while the disclosure is open.")),
+            )
     }
 }